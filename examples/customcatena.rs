@@ -15,21 +15,15 @@ impl catena::catena::Algorithms for CustomCatena {
     fn gamma (&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
         -> Vec<u8> { state }
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-        -> Vec<u8> { state.clone() }
+        -> Result<Vec<u8>, catena::catena::CatenaError> { Ok(state.clone()) }
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> { state }
 }
 
 fn main() {
     // create an instance of CustomCatena
-    let mut custom_catena = catena::catena::Catena {
-        algorithms: CustomCatena,
-        vid: "CustomCatena",
-        g_low: 10,
-        g_high: 10,
-        lambda: 10,
-        n: 64,
-        k: 64,
-    };
+    // with the version ID, n, k, g_low, g_high and lambda
+    let mut custom_catena = catena::catena::Catena::new(
+        CustomCatena, "CustomCatena", 64, 64, 10, 10, 10);
 
     let pwd   = b"password".to_vec();
     let ad    = b"associated_data".to_vec();
@@ -38,6 +32,7 @@ fn main() {
     let output_length = 64;
 
     // use the methods of the custom Catena
-    let hash = custom_catena.hash(&pwd, &salt, &ad, output_length, &gamma);
+    let hash = custom_catena.hash(&pwd, &salt, &ad, output_length, &gamma)
+        .unwrap();
     println!("{:?}", hash);
 }
@@ -1,33 +1,100 @@
 extern crate catena;
 
 use std::env;
+use std::process;
 use catena::bytes::Bytes;
 use catena::bytes::HexRepresentation;
 
+fn usage(program: &str) {
+    println!("Catena password scrambler");
+    println!("");
+    println!("Usage:");
+    println!("  {:?} [--instance name] [--garlic g] [--gamma-file path] pwd ad salt [gamma] m", program);
+    println!("");
+    println!("Options:");
+    println!("  --instance:   the Catena instance (default: dragonfly-full)");
+    println!("  --garlic:     garlic used as g_low and g_high");
+    println!("  --gamma-file: read γ as raw bytes from a file instead of the");
+    println!("                gamma argument");
+    println!("");
+    println!("Arguments:");
+    println!("  pwd:   password as string");
+    println!("  ad:    associated data as hex");
+    println!("  salt:  salt as hex");
+    println!("  gamma: γ as hex");
+    println!("  m:     output length");
+}
+
 fn main() {
-    let mut catena_dff = catena::default_instances::dragonfly_full::new();
+    let args: Vec<String> = env::args().collect();
 
-    let args: Vec<_> = env::args().collect();
-    if args.len() == 6 {
-    let pwd   = args[1].as_bytes().to_vec();
-    let ad    = args[2].to_be_bytes();
-    let salt  = args[3].to_be_bytes();
-    let gamma = args[4].to_be_bytes();
-    let output_length = args[5].parse::<u16>().unwrap();
+    let mut instance_name = "dragonfly-full".to_string();
+    let mut garlic: Option<u8> = None;
+    let mut gamma_file: Option<String> = None;
+    let mut positional: Vec<String> = Vec::new();
 
-    let hash = catena_dff.hash(&pwd, &salt, &ad, output_length, &gamma);
-    println!("{:?}", hash.to_hex_string());
+    let mut i = 1;
+    while i < args.len() {
+        if (args[i] == "--instance" || args[i] == "--garlic"
+            || args[i] == "--gamma-file") && i + 1 < args.len() {
+            if args[i] == "--instance" {
+                instance_name = args[i + 1].clone();
+            } else if args[i] == "--gamma-file" {
+                gamma_file = Some(args[i + 1].clone());
+            } else {
+                match catena::cli::parse_garlic(&args[i + 1]) {
+                    Ok(g) => garlic = Some(g),
+                    Err(e) => {
+                        println!("{}: {:?}", e, args[i + 1]);
+                        process::exit(1);
+                    }
+                }
+            }
+            i += 2;
         } else {
-            println!("Catena-Dragonfly-Full password scrambler");
-            println!("");
-            println!("Usage:");
-            println!("  {:?} pwd ad salt gamma m", args[0]);
-            println!("");
-            println!("Arguments:");
-            println!("  pwd:   password as string");
-            println!("  ad:    associated data as hex");
-            println!("  salt:  salt as hex");
-            println!("  gamma: γ as hex");
-            println!("  m:     output length");
+            positional.push(args[i].clone());
+            i += 1;
+        }
+    }
+
+    let expected_args = if gamma_file.is_some() { 4 } else { 5 };
+    if positional.len() != expected_args {
+        usage(&args[0]);
+        return;
+    }
+
+    let mut catena = match catena::cli::parse_instance(&instance_name) {
+        Some(catena) => catena,
+        None => {
+            println!("unknown instance: {:?}", instance_name);
+            process::exit(1);
         }
+    };
+    if let Some(g) = garlic {
+        catena.set_garlic(g, g);
+    }
+
+    let pwd   = positional[0].as_bytes().to_vec();
+    let ad    = positional[1].to_be_vec();
+    let salt  = positional[2].to_be_vec();
+    let gamma = match gamma_file {
+        Some(path) => match catena::load_gamma(&path) {
+            Ok(gamma) => gamma,
+            Err(e) => {
+                println!("{}: {:?}", e, path);
+                process::exit(1);
+            }
+        },
+        None => positional[3].to_be_vec(),
+    };
+    let output_length = positional[expected_args - 1].parse::<u16>().unwrap();
+
+    let hash = match catena.hash(&pwd, &salt, &ad, output_length, &gamma) {
+        Ok(hash) => hash,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    println!("{:?}", hash.to_hex_string());
 }
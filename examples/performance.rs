@@ -9,9 +9,9 @@ use std::path::Path;
 
 fn main() {
     let pwd   = "012345".as_bytes().to_vec();
-    let ad    = "000000".to_string().to_be_bytes();
-    let salt  = "6789ab".to_string().to_be_bytes();
-    let gamma = "6789ab".to_string().to_be_bytes();
+    let ad    = "000000".to_string().to_be_vec();
+    let salt  = "6789ab".to_string().to_be_vec();
+    let gamma = "6789ab".to_string().to_be_vec();
     let output_length = 64;
 
     let number_of_tests = 10;
@@ -162,7 +162,7 @@ fn benchmark<T: catena::catena::Algorithms>(
 {
     let start = time::now();
     for _ in 0..number_of_tests {
-        let _hash = catena.hash(pwd, salt, ad, output_length, gamma);
+        let _hash = catena.hash(pwd, salt, ad, output_length, gamma).unwrap();
     }
     let end = time::now();
     let time = (end - start).num_milliseconds() / number_of_tests as i64;
@@ -0,0 +1,40 @@
+//! Fuzz target for `Catena::hash` with a reduced Catena-Dragonfly.
+//!
+//! The input is split into the arguments of `hash`: the first two bytes are
+//! the output length (little-endian), the next three bytes give the lengths of
+//! the password, the salt and the associated data, and the remaining bytes
+//! after those fields are used as gamma.
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate catena;
+
+/// Garlic of the fuzzed instance, low enough for many runs per second.
+const GARLIC: u8 = 4;
+
+fn split_off(data: &mut &[u8], length: usize) -> Vec<u8> {
+    let length = length.min(data.len());
+    let (field, rest) = data.split_at(length);
+    *data = rest;
+    field.to_vec()
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 5 {
+        return;
+    }
+
+    let output_length = data[0] as u16 | (data[1] as u16) << 8;
+    let lengths = [data[2] as usize, data[3] as usize, data[4] as usize];
+    let mut rest = &data[5..];
+
+    let pwd = split_off(&mut rest, lengths[0]);
+    let salt = split_off(&mut rest, lengths[1]);
+    let ad = split_off(&mut rest, lengths[2]);
+    let gamma = rest.to_vec();
+
+    let mut dragonfly = catena::default_instances::dragonfly::new();
+    dragonfly.g_low = GARLIC;
+    dragonfly.g_high = GARLIC;
+
+    let _ = dragonfly.hash(&pwd, &salt, &ad, output_length, &gamma);
+});
@@ -7,17 +7,42 @@ pub trait HexRepresentation {
 }
 
 impl HexRepresentation for Vec<u8> {
+    #[cfg(not(feature = "constant-time"))]
     fn to_hex_string(&self) -> String {
         let strs: Vec<String> = self.iter()
             .map(|b| format!("{:02x}", b))
             .collect();
         strs.join("")
     }
+
+    #[cfg(feature = "constant-time")]
+    fn to_hex_string(&self) -> String {
+        to_hex_ct(self)
+    }
+}
+
+/// Convert bytes to a lower-case hex string without branches or table lookups
+/// depending on the bytes, so it can be used for secret values like keys.
+pub fn to_hex_ct(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for &b in bytes {
+        hex.push(nibble_to_hex_ct(b >> 4));
+        hex.push(nibble_to_hex_ct(b & 0x0f));
+    }
+    hex
+}
+
+/// Convert a nibble to its hex digit. For `x > 9` the arithmetic shift of
+/// `9 - x` is all ones, which adds the offset from `'0' + x` to `'a' + x - 10`.
+fn nibble_to_hex_ct(x: u8) -> char {
+    let x = x as i16;
+    (b'0' as i16 + x + (((9 - x) >> 8) & (b'a' as i16 - b'0' as i16 - 10)))
+        as u8 as char
 }
 
 impl <T: Bytes> HexRepresentation for T {
     fn to_hex_string(&self) -> String {
-        self.to_be_bytes().to_hex_string()
+        self.to_be_vec().to_hex_string()
     }
 }
 
@@ -29,21 +54,56 @@ pub trait ByteState {
     fn set_word(&mut self, word_size: usize, index: usize, new_value: Vec<u8>);
     /// reverse all words
     fn reverse_words(&mut self, word_size: usize);
+    /// swap the words of `word_size` at positions `i` and `j` in place
+    fn swap_words(&mut self, word_size: usize, i: usize, j: usize);
+    /// move the word of `word_size` at position `i` to position `perm(i)`
+    /// for all words, following the cycles of `perm` in place
+    ///
+    /// Panics if `perm` is not a permutation of the word positions or if
+    /// `word_size` is 0.
+    fn permute_words(&mut self, word_size: usize, perm: &dyn Fn(usize) -> usize);
+}
+
+/// Panic if the word `index` of `word_size` bytes is not within `len` bytes.
+#[cfg(feature = "checked-state")]
+fn check_word(operation: &str, len: usize, word_size: usize, index: usize) {
+    let end = index.checked_add(1).and_then(|i| i.checked_mul(word_size));
+    if end.map_or(true, |end| end > len) {
+        panic!("{}: index {} word_size {} exceeds len {}",
+               operation, index, word_size, len);
+    }
 }
 
 impl ByteState for Vec<u8> {
     fn get_word(&self, word_size: usize, index: usize) -> Vec<u8> {
+        #[cfg(feature = "checked-state")]
+        check_word("get_word", self.len(), word_size, index);
         [&self[index * word_size .. (index + 1) * word_size]].concat()
     }
 
     fn set_word(&mut self, word_size: usize, index: usize, new_value: Vec<u8>) {
+        #[cfg(feature = "checked-state")]
+        {
+            check_word("set_word", self.len(), word_size, index);
+            if new_value.len() < word_size {
+                panic!("set_word: new_value len {} is shorter than word_size {}",
+                       new_value.len(), word_size);
+            }
+        }
         for i in 0..word_size {
             self[index * word_size + i] = new_value[i];
         }
     }
 
     fn reverse_words(&mut self, word_size: usize) {
-        // TODO if len mod size != 0 panic stuff
+        #[cfg(feature = "checked-state")]
+        {
+            if word_size == 0 || self.len() % word_size != 0 {
+                panic!("reverse_words: len {} is not a multiple of word_size {}",
+                       self.len(), word_size);
+            }
+        }
+        // without checked-state, a trailing partial word is left unchanged
         let mut reversed: Vec<u8> = Vec::new();
         let number_of_words = self.len() / word_size;
         // iterate over all words
@@ -59,42 +119,163 @@ impl ByteState for Vec<u8> {
             self[i] = reversed[i];
         }
     }
+
+    fn swap_words(&mut self, word_size: usize, i: usize, j: usize) {
+        #[cfg(feature = "checked-state")]
+        {
+            check_word("swap_words", self.len(), word_size, i);
+            check_word("swap_words", self.len(), word_size, j);
+        }
+        for b in 0..word_size {
+            self.swap(i * word_size + b, j * word_size + b);
+        }
+    }
+
+    fn permute_words(&mut self, word_size: usize, perm: &dyn Fn(usize) -> usize) {
+        #[cfg(feature = "checked-state")]
+        {
+            if word_size == 0 || self.len() % word_size != 0 {
+                panic!("permute_words: len {} is not a multiple of word_size {}",
+                       self.len(), word_size);
+            }
+        }
+        self[..].permute_words(word_size, perm);
+    }
+}
+
+impl ByteState for [u8] {
+    fn get_word(&self, word_size: usize, index: usize) -> Vec<u8> {
+        self[index * word_size .. (index + 1) * word_size].to_vec()
+    }
+
+    fn set_word(&mut self, word_size: usize, index: usize, new_value: Vec<u8>) {
+        self[index * word_size .. (index + 1) * word_size]
+            .copy_from_slice(&new_value[..word_size]);
+    }
+
+    fn reverse_words(&mut self, word_size: usize) {
+        for word in self.chunks_mut(word_size) {
+            if word.len() == word_size {
+                word.reverse();
+            }
+        }
+    }
+
+    fn swap_words(&mut self, word_size: usize, i: usize, j: usize) {
+        for b in 0..word_size {
+            self.swap(i * word_size + b, j * word_size + b);
+        }
+    }
+
+    fn permute_words(&mut self, word_size: usize, perm: &dyn Fn(usize) -> usize) {
+        // also without checked-state, 0 would divide by zero
+        if word_size == 0 {
+            panic!("permute_words: word_size is 0");
+        }
+        // a trailing partial word is left unchanged
+        let number_of_words = self.len() / word_size;
+        // one flag per word instead of a copy of the state
+        let mut visited = vec![false; number_of_words];
+        for start in 0..number_of_words {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            // the word at `start` always is the one which moves next
+            let mut j = perm(start);
+            while j != start {
+                if j >= number_of_words || visited[j] {
+                    panic!("permute_words: not a permutation of {} words",
+                           number_of_words);
+                }
+                visited[j] = true;
+                self.swap_words(word_size, start, j);
+                j = perm(j);
+            }
+        }
+    }
+}
+
+/// A state of fixed size, e.g. `[u8; N]` on the stack, for small instances
+/// where the allocation of a `Vec` dominates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArrayState<A>(pub A);
+
+impl <A: AsRef<[u8]> + AsMut<[u8]>> ByteState for ArrayState<A> {
+    fn get_word(&self, word_size: usize, index: usize) -> Vec<u8> {
+        self.0.as_ref().get_word(word_size, index)
+    }
+
+    fn set_word(&mut self, word_size: usize, index: usize, new_value: Vec<u8>) {
+        self.0.as_mut().set_word(word_size, index, new_value);
+    }
+
+    fn reverse_words(&mut self, word_size: usize) {
+        self.0.as_mut().reverse_words(word_size);
+    }
+
+    fn swap_words(&mut self, word_size: usize, i: usize, j: usize) {
+        self.0.as_mut().swap_words(word_size, i, j);
+    }
+
+    fn permute_words(&mut self, word_size: usize, perm: &dyn Fn(usize) -> usize) {
+        self.0.as_mut().permute_words(word_size, perm);
+    }
 }
 
 impl <T: Bytes> ByteState for T {
     fn get_word(&self, word_size: usize, index: usize) -> Vec<u8> {
-        self.to_be_bytes().get_word(word_size, index)
+        self.to_be_vec().get_word(word_size, index)
     }
 
     fn set_word(&mut self, word_size: usize, index: usize, new_value: Vec<u8>) {
-        self.to_be_bytes().set_word(word_size, index, new_value)
+        self.to_be_vec().set_word(word_size, index, new_value)
     }
 
     fn reverse_words(&mut self, word_size: usize) {
-        self.to_be_bytes().reverse_words(word_size);
+        self.to_be_vec().reverse_words(word_size);
+    }
+
+    /// Panics, as the words of a value converted with `to_be_vec` can not be
+    /// swapped in place.
+    fn swap_words(&mut self, _word_size: usize, _i: usize, _j: usize) {
+        panic!("swap_words: the bytes of a Bytes value are a temporary copy \
+                and can not be swapped in place");
+    }
+
+    /// Panics, as the words of a value converted with `to_be_vec` can not be
+    /// permuted in place.
+    fn permute_words(&mut self, _word_size: usize,
+                     _perm: &dyn Fn(usize) -> usize) {
+        panic!("permute_words: the bytes of a Bytes value are a temporary copy \
+                and can not be permuted in place");
     }
 }
 
 /// Everything that is convertible to a Vec<u8>
+///
+/// The methods are not named `to_be_bytes` and `to_le_bytes`, as the
+/// inherent methods of the integer types with these names return arrays and
+/// would shadow them.
 pub trait Bytes {
     /// convert to `Vec<u8>` in big endian
-    fn to_be_bytes(&self) -> Vec<u8>;
+    fn to_be_vec(&self) -> Vec<u8>;
     /// convert to `Vec<u8> in little endian
-    fn to_le_bytes(&self) -> Vec<u8>;
+    fn to_le_vec(&self) -> Vec<u8>;
 }
 
 impl Bytes for u8 {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
         vec![*self]
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
         vec![*self]
     }
 }
 
 impl Bytes for u16 {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
         let v_1: u8 = ((*self >> 8) & 0xff) as u8;
         let v_2: u8 = ( *self       & 0xff) as u8;
 
@@ -102,7 +283,7 @@ impl Bytes for u16 {
         v
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
         let v_1: u8 = ( *self       & 0xff) as u8;
         let v_2: u8 = ((*self >> 8) & 0xff) as u8;
 
@@ -112,7 +293,7 @@ impl Bytes for u16 {
 }
 
 impl Bytes for u32 {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
     let v_1: u8 = ((*self >> 24) & 0xff) as u8;
     let v_2: u8 = ((*self >> 16) & 0xff) as u8;
     let v_3: u8 = ((*self >> 8)  & 0xff) as u8;
@@ -122,7 +303,7 @@ impl Bytes for u32 {
     v
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
     let v_1: u8 = ( *self        & 0xff) as u8;
     let v_2: u8 = ((*self >> 8)  & 0xff) as u8;
     let v_3: u8 = ((*self >> 16) & 0xff) as u8;
@@ -134,7 +315,7 @@ impl Bytes for u32 {
 }
 
 impl Bytes for u64 {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
         let mut vec_u8: Vec<u8> = Vec::new();
         vec_u8.append(&mut(vec!(((self & 0xff00000000000000u64) >> 56) as u8)));
         vec_u8.append(&mut(vec!(((self & 0x00ff000000000000u64) >> 48) as u8)));
@@ -147,7 +328,7 @@ impl Bytes for u64 {
         vec_u8
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
         let mut vec_u8: Vec<u8> = Vec::new();
         vec_u8.append(&mut(vec!(( self & 0x00000000000000ffu64       ) as u8)));
         vec_u8.append(&mut(vec!(((self & 0x000000000000ff00u64) >> 8 ) as u8)));
@@ -162,21 +343,21 @@ impl Bytes for u64 {
 }
 
 impl Bytes for Vec<u64> {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
         let mut vec_u8: Vec<u8> = Vec::new();
         for i in self {
-            vec_u8.append(&mut i.to_be_bytes());
+            vec_u8.append(&mut i.to_be_vec());
         }
         vec_u8
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
         unimplemented!();
     }
 }
 
 impl Bytes for String {
-    fn to_be_bytes(&self) -> Vec<u8> {
+    fn to_be_vec(&self) -> Vec<u8> {
         let mut result: Vec<u8> = Vec::new();
 
         let mut counter = 0;
@@ -199,7 +380,7 @@ impl Bytes for String {
         result
     }
 
-    fn to_le_bytes(&self) -> Vec<u8> {
+    fn to_le_vec(&self) -> Vec<u8> {
         unimplemented!();
     }
 }
@@ -208,12 +389,135 @@ impl Bytes for String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn swap_words_test() {
+        let original: Vec<u8> = (0..12).collect();
+        let mut state = original.clone();
+
+        state.swap_words(4, 0, 2);
+        assert_eq!(state, vec![8, 9, 10, 11, 4, 5, 6, 7, 0, 1, 2, 3]);
+        state.swap_words(4, 2, 0);
+        assert_eq!(state, original);
+
+        state.swap_words(4, 1, 1);
+        assert_eq!(state, original);
+    }
+
+    #[test]
+    #[should_panic(expected = "can not be swapped in place")]
+    fn swap_words_bytes_value_test() {
+        let mut value = 0x0102030405060708u64;
+        value.swap_words(4, 0, 1);
+    }
+
+    #[test]
+    fn permute_words_test() {
+        let original: Vec<u8> = (0..12).collect();
+        let mut state = original.clone();
+
+        state.permute_words(2, &|i| i);
+        assert_eq!(state, original);
+
+        // the cycle 0 -> 2 -> 5 -> 0 and the swap 1 <-> 4, word 3 stays
+        let perm = [2, 4, 5, 3, 1, 0];
+        state.permute_words(2, &|i| perm[i]);
+        assert_eq!(state, vec![10, 11, 8, 9, 0, 1, 6, 7, 2, 3, 4, 5]);
+        for i in 0..6 {
+            assert_eq!(state.get_word(2, perm[i]), original.get_word(2, i));
+        }
+
+        let mut array_state = ArrayState([0u8; 12]);
+        array_state.0.copy_from_slice(&original);
+        array_state.permute_words(2, &|i| perm[i]);
+        assert_eq!(&array_state.0[..], &state[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "permute_words: not a permutation of 3 words")]
+    fn permute_words_invalid_test() {
+        vec![0u8; 12].permute_words(4, &|_| 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "permute_words: word_size is 0")]
+    fn permute_words_zero_word_size_test() {
+        let mut state = [0u8; 12];
+        state[..].permute_words(0, &|i| i);
+    }
+
+    #[test]
+    #[should_panic(expected = "can not be permuted in place")]
+    fn permute_words_bytes_value_test() {
+        let mut value = 0x0102030405060708u64;
+        value.permute_words(4, &|i| 1 - i);
+    }
+
+    #[test]
+    fn array_state_test() {
+        // garlic 2 with words of 64 bytes
+        let mut vec_state: Vec<u8> = (0..256).map(|b| b as u8).collect();
+        let mut array_state = ArrayState([0u8; 256]);
+        array_state.0.copy_from_slice(&vec_state);
+
+        let expected: Vec<u8> = (64..128).collect();
+        assert_eq!(array_state.0[..].get_word(64, 1), expected);
+        assert_eq!(vec_state.get_word(64, 1), expected);
+
+        vec_state.reverse_words(64);
+        vec_state.swap_words(64, 0, 3);
+        vec_state.set_word(64, 2, vec![7; 64]);
+        array_state.reverse_words(64);
+        array_state.swap_words(64, 0, 3);
+        array_state.set_word(64, 2, vec![7; 64]);
+
+        assert_eq!(&array_state.0[..], &vec_state[..]);
+        for i in 0..4 {
+            assert_eq!(array_state.get_word(64, i), vec_state.get_word(64, i));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "checked-state")]
+    #[should_panic(expected = "get_word: index 5 word_size 64 exceeds len 128")]
+    fn checked_get_word_test() {
+        let _ = vec![0u8; 128].get_word(64, 5);
+    }
+
+    #[test]
+    #[cfg(feature = "checked-state")]
+    #[should_panic(expected = "set_word: index 2 word_size 64 exceeds len 128")]
+    fn checked_set_word_test() {
+        vec![0u8; 128].set_word(64, 2, vec![0; 64]);
+    }
+
+    #[test]
+    #[cfg(feature = "checked-state")]
+    #[should_panic(expected = "set_word: new_value len 32 is shorter than word_size 64")]
+    fn checked_set_word_short_value_test() {
+        vec![0u8; 128].set_word(64, 1, vec![0; 32]);
+    }
+
+    #[test]
+    #[cfg(feature = "checked-state")]
+    #[should_panic(expected = "reverse_words: len 100 is not a multiple of word_size 64")]
+    fn checked_reverse_words_test() {
+        vec![0u8; 100].reverse_words(64);
+    }
+
+    #[test]
+    #[cfg(feature = "checked-state")]
+    #[should_panic(expected = "swap_words: index 3 word_size 4 exceeds len 12")]
+    fn checked_swap_words_test() {
+        let mut state: Vec<u8> = (0..12).collect();
+        state.swap_words(4, 0, 3);
+    }
+
     #[test]
     fn hex_to_vec_u8_test_1() {
         let x = "78".to_string();
         let expected: Vec<u8> = vec![120];
 
-        assert_eq!(x.to_be_bytes(), expected);
+        assert_eq!(x.to_be_vec(), expected);
     }
 
     #[test]
@@ -233,6 +537,42 @@ mod tests {
                                      0x3a, 0x68, 0x5b, 0x14, 0x48, 0xb7, 0x55,
                                      0xd5, 0x6f, 0x70, 0x1a, 0xfe, 0x9b, 0xe2,
                                      0xce];
-        assert_eq!(input.to_be_bytes(), expected);
+        assert_eq!(input.to_be_vec(), expected);
+    }
+
+    #[test]
+    fn byte_order_test() {
+        // the byte order of these conversions is part of the outputs of
+        // Catena, see `catena::endianness_report`
+        assert_eq!(0x0102u16.to_be_vec(), vec![0x01, 0x02]);
+        assert_eq!(0x0102u16.to_le_vec(), vec![0x02, 0x01]);
+        assert_eq!(0x01020304u32.to_be_vec(), vec![0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(0x01020304u32.to_le_vec(), vec![0x04, 0x03, 0x02, 0x01]);
+
+        let value = 0x0102030405060708u64;
+        let be = value.to_be_vec();
+        let le = value.to_le_vec();
+        assert_eq!(be, vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+        assert_eq!(le, vec![0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(::helpers::conversions::bytes_to_u64(&le, 0), value);
+        assert_eq!(::helpers::conversions::bytes_to_u64_be(&be, 0), value);
+        assert_eq!(value.to_hex_string(), "0102030405060708");
+        assert_eq!(0xabu8.to_be_vec(), 0xabu8.to_le_vec());
+    }
+
+    #[test]
+    fn to_hex_ct_test() {
+        for b in 0..256 {
+            let byte = b as u8;
+            assert_eq!(to_hex_ct(&[byte]), format!("{:02x}", byte));
+        }
+
+        let bytes: Vec<u8> = (0..256).map(|b| b as u8).rev().collect();
+        let expected: Vec<String> = bytes.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(to_hex_ct(&bytes), expected.join(""));
+        assert_eq!(bytes.to_hex_string(), expected.join(""));
+        assert_eq!(to_hex_ct(&[]), "");
     }
 }
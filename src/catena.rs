@@ -1,11 +1,30 @@
 //! The Catena functions as specified in the paper.
-extern crate rand;
 
 use bytes::Bytes;
 use bytes::ByteState;
-use self::rand::Rng;
-
-use std::f32;
+use bytes::HexRepresentation;
+use components::gamma::saltmix::SeedCache;
+use components::graph::GraphKind;
+use components::instrument::Ablated;
+use components::instrument::Recomposed;
+use components::output::CounterExpand;
+use components::output::OutputStrategy;
+use components::output::Truncate;
+use inputs::DerivedKey;
+use inputs::Gamma;
+use inputs::PasswordHash;
+use inputs::Salt;
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+#[cfg(feature = "debug-trace")]
+use std::ops::Range;
+#[cfg(feature = "parallel")]
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
 /// The possible domains (modes) of Catena.
 #[derive(Clone, Copy, Debug)]
@@ -15,6 +34,467 @@ enum Domain {
     ProofOfWork,
 }
 
+/// The first input of H built from the tweak `t`, the password and the salt
+/// `s` in the order of `INITIAL_INPUT_ORDER`.
+fn initial_input(t: &Vec<u8>, pwd: &Vec<u8>, s: &Vec<u8>) -> Vec<u8> {
+    let mut input = Vec::with_capacity(t.len() + pwd.len() + s.len());
+    for part in INITIAL_INPUT_ORDER.iter() {
+        let bytes = match *part {
+            InputPart::Tweak => t,
+            InputPart::Password => pwd,
+            InputPart::Salt => s,
+        };
+        input.extend_from_slice(bytes);
+    }
+    input
+}
+
+/// Whether more than half of the words of `k` bytes of the state equal their
+/// predecessor. A correct H' produces distinct words with overwhelming
+/// probability.
+#[cfg(feature = "debug-trace")]
+fn is_suspicious_state(v: &Vec<u8>, k: usize) -> bool {
+    let words: Vec<&[u8]> = v.chunks(k).collect();
+    let duplicates = words.windows(2).filter(|w| w[0] == w[1]).count();
+    duplicates > words.len() / 2
+}
+
+/// The number of threads of the parallel functions, see
+/// `Catena::set_parallelism`.
+#[cfg(feature = "parallel")]
+fn threads(options: &Options) -> usize {
+    match options.parallelism {
+        Some(threads) => threads,
+        None => thread::available_parallelism()
+            .map(|t| t.get())
+            .unwrap_or(1),
+    }
+}
+
+/// Reject a key of `Catena::hash_authenticated` which keyed Blake2b does not
+/// accept.
+fn check_mac_key(mac_key: &[u8]) -> Result<(), CatenaError> {
+    if mac_key.len() > 64 {
+        Err(CatenaError::MacKeyTooLong { max: 64, actual: mac_key.len() })
+    } else {
+        Ok(())
+    }
+}
+
+/// Errors returned by the checked functions of Catena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatenaError {
+    /// The output of `Catena::self_test` did not match the expected value.
+    SelfTestFailed,
+    /// Reserving memory for the state failed.
+    AllocationFailed {
+        /// The number of bytes which could not be reserved.
+        bytes: usize,
+    },
+    /// A garlic could not be parsed or is out of range.
+    InvalidGarlic,
+    /// Getting or setting the CPU affinity of the current thread failed.
+    AffinityFailed {
+        /// The error number returned by the operating system.
+        errno: i32,
+    },
+    /// A PHC string could not be parsed.
+    InvalidPhc,
+    /// The version ID is not one of the predefined instances.
+    UnknownVariant,
+    /// The deadline of `Catena::proof_of_work_client_timed` passed before a
+    /// matching salt or password was found.
+    ProofOfWorkTimeout,
+    /// No salt or password of the proof of work matches the hash.
+    ProofOfWorkNotFound,
+    /// The parameters of the proof of work do not fit together, see
+    /// `Catena::pow_validate`.
+    InvalidProofOfWork {
+        /// `"p"`, `"salt"`, `"pwd"` or `"mode"`.
+        parameter: &'static str,
+    },
+    /// Repeated computations of the same input or the reference and an
+    /// accelerated implementation disagree, see
+    /// `Catena::assert_deterministic`.
+    NotDeterministic,
+    /// More than half of the words of the state after F equal their
+    /// predecessor, which indicates a broken H' or F, see
+    /// `Catena::check_flap_state`.
+    SuspiciousState,
+    /// The output length is not a positive multiple of the word size.
+    InvalidWordSize,
+    /// The requested output length is zero.
+    ZeroOutputLength,
+    /// γ is empty, but the instance uses it and requires it, see
+    /// `Catena::with_require_gamma`.
+    EmptyGamma,
+    /// The output of H or H' does not have the length given by `n` or `k`,
+    /// see `Catena::new_checked`, or the client output of the server relief
+    /// is not a word of `k` bytes, see `Catena::validate_client_output`.
+    OutputLengthMismatch {
+        /// `"H"`, `"H'"` or `"flap"`.
+        function: &'static str,
+        /// The length given by `n` or `k`.
+        expected: usize,
+        /// The length of the output.
+        actual: usize,
+    },
+    /// A stored hash has a length this instance cannot produce, which
+    /// indicates wrong parameters rather than a wrong password, see
+    /// `Catena::validate_against_hash`.
+    StoredLengthMismatch {
+        /// The output length of H, the largest length of a hash.
+        max: usize,
+        /// The length of the stored hash.
+        actual: usize,
+    },
+    /// The password does not match the stored hash.
+    PasswordMismatch,
+    /// The MAC of a stored hash does not match, so the hash, the associated
+    /// data or the parameters were modified, see
+    /// `Catena::verify_authenticated`.
+    MacMismatch,
+    /// The key of the MAC is longer than Blake2b allows, see
+    /// `Catena::hash_authenticated`.
+    MacKeyTooLong {
+        /// The largest length of a key in bytes.
+        max: usize,
+        /// The length of the key.
+        actual: usize,
+    },
+    /// A file of test vectors is not a JSON array, see
+    /// `conformance::load_vectors`.
+    InvalidJson,
+    /// A field of a test vector is missing or invalid, see
+    /// `conformance::load_vectors`.
+    InvalidTestVector {
+        /// The position of the vector in the file.
+        index: usize,
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// The initial value of flap needs more hashes of H than the cap allows,
+    /// see `Catena::with_max_init_blocks`.
+    InitialValueTooLarge {
+        /// The number of hashes `2k/n`.
+        blocks: usize,
+        /// The largest allowed number of hashes.
+        max: usize,
+    },
+    /// `k` is not a positive multiple of `n`, see `Catena::hash`.
+    InvalidParameters {
+        /// Output length of H' in bytes.
+        k: usize,
+        /// Output length of H in bytes.
+        n: usize,
+    },
+    /// The requested output is longer than the output of H, see
+    /// `Catena::hash`.
+    OutputTooLong {
+        /// The requested output length.
+        requested: usize,
+        /// The output length of H.
+        max: usize,
+    },
+    /// The salt is empty, see `Catena::hash`.
+    EmptySalt,
+    /// An option which changes the output is set, but the function can not
+    /// represent or apply it, see `Catena::encode_header`.
+    UnsupportedOption {
+        /// The name of the field of `Options`.
+        option: &'static str,
+    },
+    /// Reading an input from a file or writing an output failed.
+    IoFailed {
+        /// The kind of the underlying I/O error.
+        kind: io::ErrorKind,
+    },
+}
+
+impl fmt::Display for CatenaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CatenaError::SelfTestFailed =>
+                write!(f, "self test failed"),
+            CatenaError::AllocationFailed { bytes } =>
+                write!(f, "allocation of {} bytes failed", bytes),
+            CatenaError::InvalidGarlic =>
+                write!(f, "invalid garlic"),
+            CatenaError::AffinityFailed { errno } =>
+                write!(f, "setting the CPU affinity failed (errno {})", errno),
+            CatenaError::InvalidPhc =>
+                write!(f, "invalid PHC string"),
+            CatenaError::UnknownVariant =>
+                write!(f, "unknown Catena variant"),
+            CatenaError::ProofOfWorkTimeout =>
+                write!(f, "proof of work timed out"),
+            CatenaError::ProofOfWorkNotFound =>
+                write!(f, "no salt or password found for the proof of work"),
+            CatenaError::InvalidProofOfWork { parameter } =>
+                write!(f, "proof of work parameter \"{}\" is invalid",
+                       parameter),
+            CatenaError::NotDeterministic =>
+                write!(f, "output is not deterministic"),
+            CatenaError::SuspiciousState =>
+                write!(f, "state after F is suspiciously uniform"),
+            CatenaError::ZeroOutputLength =>
+                write!(f, "output length is zero"),
+            CatenaError::EmptyGamma =>
+                write!(f, "gamma is empty"),
+            CatenaError::InvalidWordSize =>
+                write!(f, "output length is not a multiple of the word size"),
+            CatenaError::OutputLengthMismatch { function, expected, actual } =>
+                write!(f, "output of {} has {} bytes, but {} bytes are expected",
+                       function, actual, expected),
+            CatenaError::StoredLengthMismatch { max, actual } =>
+                write!(f, "stored hash has {} bytes, but the instance produces \
+                           1 to {} bytes", actual, max),
+            CatenaError::PasswordMismatch =>
+                write!(f, "password does not match the stored hash"),
+            CatenaError::MacMismatch =>
+                write!(f, "MAC of the stored hash does not match"),
+            CatenaError::MacKeyTooLong { max, actual } =>
+                write!(f, "MAC key has {} bytes, but at most {} bytes are \
+                           allowed", actual, max),
+            CatenaError::InvalidJson =>
+                write!(f, "test vectors are not a JSON array"),
+            CatenaError::InvalidTestVector { index, field } =>
+                write!(f, "test vector {}: field \"{}\" is missing or invalid",
+                       index, field),
+            CatenaError::InitialValueTooLarge { blocks, max } =>
+                write!(f, "initial value needs {} hashes, but at most {} are \
+                           allowed", blocks, max),
+            CatenaError::InvalidParameters { k, n } =>
+                write!(f, "k = {} is not a positive multiple of n = {}", k, n),
+            CatenaError::OutputTooLong { requested, max } =>
+                write!(f, "output of {} bytes requested, but at most {} bytes \
+                           are supported", requested, max),
+            CatenaError::EmptySalt =>
+                write!(f, "salt is empty"),
+            CatenaError::UnsupportedOption { option } =>
+                write!(f, "option {} is not supported here", option),
+            CatenaError::IoFailed { kind } =>
+                write!(f, "reading or writing failed ({:?})", kind),
+        }
+    }
+}
+
+impl error::Error for CatenaError {
+    fn description(&self) -> &str {
+        "Catena error"
+    }
+}
+
+/// A part of the first input `H(t || pwd || salt)` of Catena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputPart {
+    /// The tweak `t`.
+    Tweak,
+    /// The password.
+    Password,
+    /// The salt.
+    Salt,
+}
+
+/// The order of the parts of the first input `H(t || pwd || salt)` of Catena:
+/// the tweak, the password and the salt.
+///
+/// `hash` and `client_prep` both build the first input in this order. Server
+/// relief relies on it, as `server_final` of the output of `client_prep`
+/// only equals `hash` if both paths agree.
+pub const INITIAL_INPUT_ORDER: [InputPart; 3] =
+    [InputPart::Tweak, InputPart::Password, InputPart::Salt];
+
+/// The result of `Catena::verify_min_garlic`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyResult {
+    /// The password matches and the hash was computed with at least the
+    /// minimum garlic.
+    Ok,
+    /// The password matches, but the hash was computed with a garlic below
+    /// the minimum and should be upgraded, e.g. with
+    /// `Catena::client_independent_update`.
+    OkNeedsUpgrade,
+    /// The password does not match.
+    Failed,
+}
+
+/// The length of the salt generated by `Catena::hash_password_phc`.
+pub const PHC_SALT_LENGTH: usize = 16;
+
+/// The garlic at which `Catena::self_test` hashes its fixed input.
+const SELF_TEST_GARLIC: u8 = 8;
+
+/// Known answers of `Catena::self_test` for the predefined instances.
+const SELF_TEST_VECTORS: [(&'static str, &'static str); 13] = [
+    ("Dragonfly",
+     "4bb6b36656bc86f213cfcc1d6eaa8346e527efa473536e08d49e0ef70de15d68\
+      a42c912fa55b250cb0dce75edddf7216a51fab3fe19a9288018f8823ac36e596"),
+    ("Dragonfly-Full",
+     "efa565a853c7fbeba4894074b396253716a70a42da342cf1d474599337acf23e\
+      c7f904d0f0853f012a46447473a628d5bfa223e6f98e9e88580f90992b21b397"),
+    ("Butterfly",
+     "01abc44fb3fc2d141bb88f3918e67dd8bcfe1ca2a66909304729793fd2a3c3a3\
+      da2731e85beb8804bd2564c7293895dfe3fcd79492e3d29438d81161da75e40f"),
+    ("Butterfly-Full",
+     "90c1d80f8aa242422457cf17ba9c69da1aa67a75dc223718141b5276173ef7d9\
+      23c6a30e9af5a18d939156dd701ad62fbdcd5ce792d4220c12a321e1512e4943"),
+    ("Stonefly",
+     "88fef132f557797dbbb059ace32e0732dae1cecf5bde9f9f21b98f4a8dd1bc59\
+      b3b17787653cd6e71c8b40fb979872d63619491c22e36fba2d5b6f540ef3c2f3"),
+    ("Stonefly-Full",
+     "7c319f13287093f975e3bbf5f6055802ab5854253786a7d875fd4fb7d71bf199\
+      be3298601181eb132a47d044a66dd19726a74361e19f6ad62ecb075c8eefc976"),
+    ("Horsefly",
+     "5dab9d5ab8e4933cb1dbc160672ae379b43cecd21343f6f69e513dd98131928d\
+      2d65c225d390a6c284a6c2cada5d16762d8e8e95e510ea8531a5e0ba7c49a516"),
+    ("Horsefly-Full",
+     "b4dbb84f29e30ff08c182d9c85197af1fc4972c45191c123c7d79140acf7cb8b\
+      f0eac3478bfddfeaef612568387b0c5f5619cafd3117f7db9a6ebaeb612a7470"),
+    ("Mydasfly",
+     "832495924f0e98a1533b28201b3660e9c25fa7f9bca123898091aa2efc2f1a92\
+      0840f0b700b4c160af8110f8e6a3d92b4b1f7d655ae1eb7f343ce29bb8c13a19"),
+    ("Mydasfly-Full",
+     "ae14c14970029f5c9a7be7dc3e0bef19340b89b1cc55d46e2719534a6b5c3392\
+      ed82b51449c35a27b4fd87f4790d6d78d81ad5d9059e1eedca04b59206978167"),
+    ("Lanternfly",
+     "5c2357f575b146e0c6be0903330edbb9971fb5119194d161494c07210ce61f9f\
+      b2a81745fdf6dca482badc6185cef0b79972cf176d183b8de6c53c851f4a79f3"),
+    ("Lanternfly-Full",
+     "839913dbffe1472f8088273036e1a214ebb10f588fdfc9510840138c2f17611b\
+      571f41fed44073b84141c7237d862f3a3a99766a5bb34ee4166e87d0ac868926"),
+    ("Dragonfly-CTA",
+     "a07b197fa5bda1a03751511327881f5f30067d3a0487b2081c1330e2140ed239\
+      0320f5e162272981f0644e7bd31aad9b765635631acb60265e2cb0d1b9c294a9")];
+
+/// Known answers of all predefined instances as pairs of version ID and
+/// output.
+///
+/// Each output is the hash of the password `password`, the salt `salt`, the
+/// associated data `data` and γ `gamma` with an output length of `n` bytes and
+/// `g_low = g_high = 8`. These are the values checked by `Catena::self_test`.
+/// Users storing hashes can compare them with the outputs of the linked
+/// version of this crate to make sure the outputs did not change.
+///
+/// The outputs were recorded from this crate and are not taken from the
+/// reference implementation of Catena, whose vectors in `test/test_vectors`
+/// use other inputs and are not shipped with every checkout. They detect a
+/// change of the outputs, but not a deviation from the specification which
+/// already existed when they were recorded. Conformance is checked by the
+/// tests against the reference vectors.
+pub fn known_answers() -> Vec<(&'static str, Vec<u8>)> {
+    SELF_TEST_VECTORS.iter()
+        .map(|&(vid, expected)| (vid, expected.to_string().to_be_vec()))
+        .collect()
+}
+
+/// A coarse summary of the security properties of an instance, see
+/// `Catena::resistance_profile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResistanceProfile {
+    /// The kind of graph used by F.
+    pub graph: GraphKind,
+    /// F is one of the graphs of the Catena specification, which are all
+    /// memory-hard.
+    pub memory_hard: bool,
+    /// F is a (shifted or Gray-) bit-reversal graph, for which the
+    /// specification shows λ-memory-hardness.
+    pub lambda_memory_hard: bool,
+    /// The memory access pattern is independent of the password, see
+    /// `Catena::is_cta_resistant`.
+    pub cta_resistant: bool,
+    /// Φ reads words at password-dependent indices, which makes
+    /// time-memory trade-offs harder at the cost of cache-timing resistance.
+    pub password_dependent_phi: bool,
+    /// H' processes words of at least 1024 bytes like the Argon2 compression
+    /// function of the ASIC-resistant variants, so the memory bandwidth is
+    /// the bottleneck.
+    pub asic_resistant: bool,
+}
+
+/// The optional features this crate was built with, see `build_info`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The version of the crate.
+    pub version: &'static str,
+    /// The key expansion of Catena-KG can run on several threads, feature
+    /// `parallel`.
+    pub parallel: bool,
+    /// Hex strings are encoded without branches, feature `constant-time`.
+    pub constant_time: bool,
+}
+
+/// Report the version and the optional features of this build. Integrators
+/// can log this next to `known_answers` to track which implementation
+/// produced stored hashes.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        parallel: cfg!(feature = "parallel"),
+        constant_time: cfg!(feature = "constant-time"),
+    }
+}
+
+/// A Blake2b Merkle root over a list of stored hashes, e.g. as a single
+/// commitment to all hashes of a database for audits.
+///
+/// The leaves are `H(0x00 || h_i)` for the hashes `h_i` in order and an
+/// inner node is `H(0x01 || left || right)`. On a level with an odd number
+/// of nodes, the last node is moved up unchanged. The root of a single hash
+/// is thus `H(0x00 || h_0)` and the root of an empty list is `H` of the empty
+/// string.
+///
+/// The prefixes separate leaves from inner nodes as in RFC 6962. Without
+/// them, a stored hash `0x01 || left || right` of 129 bytes would have the
+/// same leaf as an inner node, so a different list would have the same root.
+/// Hashes of any length are therefore accepted.
+pub fn commit_hashes(hashes: &[Vec<u8>]) -> [u8; 64] {
+    let mut level: Vec<Vec<u8>> = hashes.iter()
+        .map(|hash| ::components::hash::blake2b::hash(
+            &[&[0x00][..], &hash[..]].concat()))
+        .collect();
+    if level.is_empty() {
+        level.push(::components::hash::blake2b::hash(&Vec::new()));
+    }
+
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| if pair.len() == 2 {
+                ::components::hash::blake2b::hash(
+                    &[&[0x01][..], &pair[0][..], &pair[1][..]].concat())
+            } else {
+                pair[0].clone()
+            })
+            .collect();
+    }
+
+    let mut root = [0u8; 64];
+    root.copy_from_slice(&level[0]);
+    root
+}
+
+/// The byte orders used by this crate, for ports and debugging.
+///
+/// Every choice is part of the outputs, so changing one changes all stored
+/// hashes. `bytes::Bytes` pins the byte order of the integer conversions.
+pub fn endianness_report() -> &'static str {
+    "\
+tweak: output length and salt length as 2 bytes, little endian
+flap: garlic in H(g || x) as 1 byte
+H_first: index i in H(i || w_0) as 1 byte
+Catena-KG: counter and key size as 2 bytes, little endian
+SaltMix: seed H(s) || H(H(s)) read as 64-bit words, little endian
+Blake2b-1: message and output words, little endian
+Argon2 compression function: rows read as 64-bit words, little endian; \
+permutation output and columns as 64-bit words, big endian, reversed to \
+little endian before the final XOR
+config fingerprint: length of the version ID and of the ad_hash output as 2 \
+bytes, n, k and the k_schedule length and words as 8 bytes, little endian
+bytes::Bytes: to_be_vec big endian, to_le_vec little endian; \
+hex strings of integers big endian
+"
+}
+
 /// Defines a Catena instance.
 #[derive(Clone, Debug)]
 pub struct Catena <T: Algorithms> {
@@ -32,8 +512,45 @@ pub struct Catena <T: Algorithms> {
     pub g_high: u8,
     /// The depth of the graph structure.
     pub lambda: u8,
+    /// Optional, non-standard modifications of the construction.
+    pub options: Options,
+}
+
+/// Optional modifications of Catena which are not part of the
+/// specification. The default is the construction of the specification.
+/// `parallelism` and `require_gamma` do not change the output, all other
+/// options do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// An identifier of H appended to the tweak, see `Catena::with_h_id`.
+    pub h_id: Option<u8>,
+    /// Include the salt in the last hash of Catena, see
+    /// `Catena::with_bind_salt_in_final`.
+    pub bind_salt_in_final: bool,
+    /// Include the garlic again at the end of the last hash of Catena, see
+    /// `Catena::with_bind_garlic`.
+    pub bind_garlic: bool,
+    /// The number of threads of the parallel functions, see
+    /// `Catena::set_parallelism`. `None` uses the available parallelism.
+    pub parallelism: Option<usize>,
+    /// Reject an empty γ, see `Catena::with_require_gamma`.
+    pub require_gamma: bool,
+    /// The hash of the associated data in the tweak instead of H, see
+    /// `Catena::with_ad_hash`.
+    pub ad_hash: Option<fn(&[u8]) -> Vec<u8>>,
+    /// The word size of flap for some garlics instead of `k`, see
+    /// `Catena::with_k_schedule`.
+    pub k_schedule: Option<&'static [(u8, usize)]>,
+    /// The largest number of hashes of the initial value of flap, see
+    /// `Catena::with_max_init_blocks`. `None` uses
+    /// `DEFAULT_MAX_INIT_BLOCKS`.
+    pub max_init_blocks: Option<usize>,
 }
 
+/// The default cap on the number of hashes `2k/n` of the initial value of
+/// flap, see `Catena::with_max_init_blocks`.
+pub const DEFAULT_MAX_INIT_BLOCKS: usize = 1024;
+
 /// These functions are the variable algorithms of Catena instances. These can
 /// either be implemented by users or the implementations from
 /// `catena::components` can be used.
@@ -59,21 +576,261 @@ pub trait Algorithms {
     fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
         -> Vec<u8>;
 
+    /// Γ with a cache of the seed of SaltMix, which only depends on γ.
+    ///
+    /// Catena creates a new cache for each computation, passes it to every
+    /// call of flap and drops it afterwards, so the seed is only computed
+    /// once per hash. The default ignores the cache and calls `gamma`.
+    fn gamma_cached(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                    k: usize, _cache: &mut SeedCache) -> Vec<u8> {
+        self.gamma(garlic, state, gamma, k)
+    }
+
     /// The graph-based hash function F of the Catena specification.
     /// Graph-based hash function can be found in `catena::components::graph`.
+    /// Returns `CatenaError::AllocationFailed` if the memory for a new state
+    /// can not be reserved.
     fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-        -> Vec<u8>;
+        -> Result<Vec<u8>, CatenaError>;
+
+    /// The kind of graph used by F. This is `GraphKind::Unknown` unless it is
+    /// implemented by the instance.
+    fn graph_kind(&self) -> GraphKind {
+        GraphKind::Unknown
+    }
 
     /// The optional password-dependent random layer Φ of the Catena
     /// specification. Possible functions can be found in
     /// `catena::components::phi`.
     fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8>;
+
+    /// Whether Γ uses γ. This is `true` unless it is implemented by the
+    /// instance, so an instance is only exempt from the check of
+    /// `Catena::with_require_gamma` if it explicitly states that Γ is the
+    /// identity.
+    fn uses_gamma(&self) -> bool {
+        true
+    }
+
+    /// Whether Φ reads words of the state at password-dependent indices, like
+    /// `catena::components::phi::lsb`. The memory access pattern of such an
+    /// instance depends on the password, which makes it vulnerable to
+    /// cache-timing attacks. This is `false` unless it is implemented by the
+    /// instance.
+    fn phi_is_data_dependent(&self) -> bool {
+        false
+    }
 }
 
 /// These are the algorithms of Catena. They are generated with the
 /// implementations from `algorithms`.
 impl<T: Algorithms> Catena <T> {
 
+    /// Create an instance with the construction of the specification, i.e.
+    /// with `Options::default()`. The options can be changed with the
+    /// builder methods like `with_h_id`.
+    pub fn new(
+        algorithms: T,
+        vid: &'static str,
+        n: usize,
+        k: usize,
+        g_low: u8,
+        g_high: u8,
+        lambda: u8
+    ) -> Catena<T> {
+        Catena {
+            algorithms: algorithms,
+            vid: vid,
+            n: n,
+            k: k,
+            g_low: g_low,
+            g_high: g_high,
+            lambda: lambda,
+            options: Options::default(),
+        }
+    }
+
+    /// Create an instance and check the output lengths of H and H'.
+    ///
+    /// H is called on the empty input and has to return `n` bytes. H' is
+    /// reset and called on two words of `k` bytes and has to return `k`
+    /// bytes. Otherwise `CatenaError::OutputLengthMismatch` is returned.
+    /// Also, `k` has to be a positive multiple of `n`, otherwise
+    /// `CatenaError::InvalidParameters` is returned, and `g_low` must not
+    /// exceed `g_high`. Unlike creating the struct directly, this runs H and
+    /// H', so a wrong `n` or `k` of a custom instance is found before it
+    /// silently produces wrong hashes.
+    pub fn new_checked(
+        mut algorithms: T,
+        vid: &'static str,
+        n: usize,
+        k: usize,
+        g_low: u8,
+        g_high: u8,
+        lambda: u8
+    ) -> Result<Catena<T>, CatenaError> {
+        if g_low > g_high {
+            return Err(CatenaError::InvalidGarlic);
+        }
+        if n == 0 || k == 0 || k % n != 0 {
+            return Err(CatenaError::InvalidParameters { k: k, n: n });
+        }
+
+        let h_len = algorithms.h(&Vec::new()).len();
+        if h_len != n {
+            return Err(CatenaError::OutputLengthMismatch {
+                function: "H", expected: n, actual: h_len });
+        }
+
+        algorithms.reset_h_prime();
+        let h_prime_len = algorithms.h_prime(&vec![0u8; 2 * k]).len();
+        algorithms.reset_h_prime();
+        if h_prime_len != k {
+            return Err(CatenaError::OutputLengthMismatch {
+                function: "H'", expected: k, actual: h_prime_len });
+        }
+
+        Ok(Catena::new(algorithms, vid, n, k, g_low, g_high, lambda))
+    }
+
+    /// Append an identifier of H to the tweak.
+    ///
+    /// The tweak contains `H(vid)`, but nothing identifies H itself, so two
+    /// instances with the same version ID and different choices of H could
+    /// be confused. With an identifier, the tweak is `H(vid) || d || λ || m
+    /// || |s| || H(AD) || h_id`. This is defense in depth against an
+    /// accidental change of H. It is not part of the specification and
+    /// changes all outputs, so it is disabled by default.
+    pub fn with_h_id(mut self, h_id: u8) -> Catena<T> {
+        self.options.h_id = Some(h_id);
+        self
+    }
+
+    /// Include the salt again in the last hash of Catena.
+    ///
+    /// The last iteration of Catena computes `H(g || flap(g, x, γ) || s)`
+    /// instead of `H(g || flap(g, x, γ))`, which binds the output to the salt
+    /// a second time. This is not part of the specification and changes all
+    /// outputs, so the tweak uses the effective version ID `<vid>+s` to keep
+    /// the hashes apart from those of the standard construction. The server
+    /// of the server relief does not know the salt and a bound hash can not
+    /// be continued with a higher garlic, so `client_prep`, `server_final`
+    /// and the client-independent updates return
+    /// `CatenaError::UnsupportedOption` if this is set.
+    pub fn with_bind_salt_in_final(mut self, bind: bool) -> Catena<T> {
+        self.options.bind_salt_in_final = bind;
+        self
+    }
+
+    /// Bind the output to the garlic a second time.
+    ///
+    /// The last iteration of Catena computes `H(g || flap(g, x, γ) || 'g' ||
+    /// g)` instead of `H(g || flap(g, x, γ))`, after the salt if
+    /// `with_bind_salt_in_final` is set as well. A stored hash then only
+    /// verifies with the garlic it was computed with, so a server which
+    /// checks stored hashes at its configured garlic detects a hash that was
+    /// substituted by a cheaper one of a lower garlic. This is not part of
+    /// the specification and changes all outputs, so the tweak uses the
+    /// effective version ID `<vid>+g`. `server_final` binds its output to
+    /// `g_high` in the same way. A bound hash can not be continued with a
+    /// higher garlic, so the client-independent updates return
+    /// `CatenaError::UnsupportedOption` if this is set.
+    pub fn with_bind_garlic(mut self, bind: bool) -> Catena<T> {
+        self.options.bind_garlic = bind;
+        self
+    }
+
+    /// Hash the associated data of the tweak with `ad_hash` instead of H.
+    ///
+    /// The tweak is `H(vid) || d || λ || m || |s| || ad_hash(AD)`, H is still
+    /// used for the version ID. The associated data is public, so a cheaper
+    /// hash can be used. This is not part of the specification: unless
+    /// `ad_hash` equals H, the tweak and all outputs differ from other
+    /// implementations, and an output length other than `n` also changes the
+    /// length of the tweak.
+    pub fn with_ad_hash(mut self, ad_hash: fn(&[u8]) -> Vec<u8>) -> Catena<T> {
+        self.options.ad_hash = Some(ad_hash);
+        self
+    }
+
+    /// Reject an empty γ.
+    ///
+    /// SaltMix seeds its PRNG with `H(γ) || H(H(γ))`, so with an empty γ the
+    /// seed is the same for every hash, which is most likely a
+    /// misconfiguration. If set, `hash` and the other functions which compute
+    /// Catena return `CatenaError::EmptyGamma` if γ is empty and the
+    /// instance uses it, see `Algorithms::uses_gamma`. `hash_unchecked`
+    /// panics instead. The output is not changed.
+    pub fn with_require_gamma(mut self, require: bool) -> Catena<T> {
+        self.options.require_gamma = require;
+        self
+    }
+
+    /// Use a different word size `k` for flap with some garlics.
+    ///
+    /// `schedule` maps a garlic to the word size of flap with this garlic,
+    /// all garlics which are not listed use `k`, see `k_for_garlic`. A hybrid
+    /// instance can use large words at low garlics and smaller words later.
+    /// The schedule is a static slice to keep `Options` `Copy`.
+    ///
+    /// This is not part of the specification and no other implementation
+    /// supports it. H' has to output words of the scheduled size, which the
+    /// algorithms of this crate only do for their own `k`, so it is meant
+    /// for research with custom algorithms. Otherwise `hash` and the other
+    /// functions which compute Catena return
+    /// `CatenaError::OutputLengthMismatch`, `hash_unchecked` panics. A
+    /// schedule which maps every garlic to `k` does not change the output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a scheduled word size is zero or not a multiple of `n`.
+    pub fn with_k_schedule(mut self, schedule: &'static [(u8, usize)])
+        -> Catena<T> {
+        for &(garlic, k) in schedule {
+            assert!(k > 0 && k % self.n == 0,
+                    "k = {} for garlic {} is not a positive multiple of n = {}",
+                    k, garlic, self.n);
+        }
+        self.options.k_schedule = Some(schedule);
+        self
+    }
+
+    /// Set the largest number of hashes of the initial value of flap.
+    ///
+    /// The initial words v_(-2) and v_(-1) of flap are built from `2k/n`
+    /// hashes of H. A huge word size, e.g. from a custom instance or a
+    /// schedule of `with_k_schedule`, makes this loop and the words grow
+    /// without bound, so flap returns `CatenaError::InitialValueTooLarge`
+    /// if `2k/n` exceeds `max`. The default is `DEFAULT_MAX_INIT_BLOCKS`,
+    /// which is far above the 2 hashes of the predefined instances. The
+    /// output is not changed.
+    pub fn with_max_init_blocks(mut self, max: usize) -> Catena<T> {
+        self.options.max_init_blocks = Some(max);
+        self
+    }
+
+    /// The word size of flap with the given garlic: the scheduled word size
+    /// if `with_k_schedule` lists the garlic and `k` otherwise.
+    pub fn k_for_garlic(&self, garlic: u8) -> usize {
+        self.options.k_schedule
+            .and_then(|schedule| schedule.iter().find(|&&(g, _)| g == garlic))
+            .map_or(self.k, |&(_, k)| k)
+    }
+
+    /// Set the number of threads used by the parallel functions like
+    /// `generate_key_parallel`.
+    ///
+    /// The work is split into consecutive chunks which are joined in order,
+    /// so the output does not depend on the number of threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    pub fn set_parallelism(&mut self, threads: usize) {
+        assert!(threads > 0, "parallelism must be at least one thread");
+        self.options.parallelism = Some(threads);
+    }
+
     /// Password scrambling function of Catena
     ///
     /// # Inputs
@@ -86,6 +843,28 @@ impl<T: Algorithms> Catena <T> {
     ///
     /// For more information about the input values, consider the Catena
     /// specification.
+    ///
+    /// The parameters are checked before any hash is computed, see
+    /// `check_parameters`: `k` has to be a positive multiple of `n`, the
+    /// output can not be empty or longer than `n` bytes and the salt can not
+    /// be empty. Otherwise `CatenaError::InvalidParameters`,
+    /// `CatenaError::ZeroOutputLength`, `CatenaError::OutputTooLong` or
+    /// `CatenaError::EmptySalt` is returned. The predefined instances always
+    /// have valid parameters.
+    ///
+    /// The errors of the computation itself are returned as well: the memory
+    /// for the state can not be reserved, `g_low` exceeds `g_high`, γ is
+    /// empty but required, the initial value exceeds the cap of
+    /// `with_max_init_blocks` or H' returns a word of the wrong length.
+    ///
+    /// The salt can have any length. It is only used in the first input
+    /// `H(t || pwd || salt)`, which is one copy of the salt in memory next
+    /// to the state. Its length is encoded in the tweak as a 2-byte value, so
+    /// for salts of 65536 bytes or more only the length modulo 65536 is
+    /// encoded. The salt itself is still hashed completely.
+    ///
+    /// The output is a `PasswordHash`, which can not be confused with a key
+    /// of `generate_key`, see `catena::inputs`.
     pub fn hash (
         &mut self,
         pwd: &Vec<u8>,
@@ -93,297 +872,480 @@ impl<T: Algorithms> Catena <T> {
         associated_data: &Vec<u8>,
         output_length: u16,
         gamma: &Vec<u8>
-    ) -> Vec<u8> {
+    ) -> Result<PasswordHash, CatenaError> {
+        self.check_parameters(salt, output_length)?;
 
         let tweak = self.compute_tweak(
             Domain::PasswordScrambling,
             output_length, salt.len() as u16,
             &associated_data);
 
-        let g_low: u8;
-        let g_high: u8;
+        self.hash_with_tweak(pwd, &tweak, salt, output_length, gamma)
+            .map(PasswordHash)
+    }
+
+    /// Check the parameters of the instance and the inputs of `hash`.
+    ///
+    /// Returns `CatenaError::InvalidParameters` if `k` is not a positive
+    /// multiple of `n`, `CatenaError::ZeroOutputLength` if `output_length` is
+    /// zero, `CatenaError::OutputTooLong` if it exceeds `n` and
+    /// `CatenaError::EmptySalt` if the salt is empty.
+    pub fn check_parameters(&self, salt: &[u8], output_length: u16)
+        -> Result<(), CatenaError> {
+        self.check_instance()?;
+        self.check_output_length(output_length)?;
+        if salt.is_empty() {
+            return Err(CatenaError::EmptySalt);
+        }
+        Ok(())
+    }
 
+    /// Check that `k` is a positive multiple of `n`.
+    fn check_instance(&self) -> Result<(), CatenaError> {
+        let n: usize;
+        let k: usize;
         {
-            g_low = self.g_low;
-            g_high = self.g_high;
+            n = self.n;
+            k = self.k;
         }
 
-        self.catena(
-            &pwd,
-            &tweak,
-            salt,
-            g_low,
-            g_high,
-            output_length,
-            &gamma)
+        if n == 0 || k == 0 || k % n != 0 {
+            return Err(CatenaError::InvalidParameters { k: k, n: n });
+        }
+        Ok(())
     }
 
-    /// Compute an encrypted hash for a given password.
-    ///
-    /// # Inputs
-    ///
-    /// - pwd: The password to be hashed.
-    /// - salt: The salt value.
-    /// - associated_data: Associated data of the user and/or the host.
-    /// - output_length: The length of the final hash in bytes.
-    /// - gamma: A public and password-independent input
-    /// - g_high: The maximum garlic.
-    /// - server_key: The key which is used to encrypt the output of Catena.
-    ///
-    /// For more information about the input values, consider the Catena
-    /// specification.
-    pub fn keyed_hashing (
-        &mut self,
-        user_pwd: Vec<u8>,
-        salt: Vec<u8>,
-        a_data: &Vec<u8>,
-        output_length: u16,
-        gamma: &Vec<u8>,
-        user_id: Vec<u8>,
-        g_high: u8,
-        server_key: &Vec<u8>
-    ) -> Vec<u8> {
-        let keystream = self.compute_keystream(
-                &server_key,
-                &user_id,
-                g_high,
-                output_length as usize);
+    /// Check that an output of `output_length` bytes can be produced, i.e.
+    /// that it is neither zero nor longer than `n`.
+    fn check_output_length(&self, output_length: u16)
+        -> Result<(), CatenaError> {
+        let n: usize;
+        {
+            n = self.n;
+        }
 
-            ::helpers::vectors::xor(
-                self.hash(&user_pwd, &salt, a_data, output_length, gamma),
-                keystream)
+        if output_length == 0 {
+            return Err(CatenaError::ZeroOutputLength);
         }
+        if output_length as usize > n {
+            return Err(CatenaError::OutputTooLong {
+                requested: output_length as usize,
+                max: n,
+            });
+        }
+        Ok(())
+    }
 
-    /// Key-Derivation function Catena-KG
+    /// Password scrambling function of Catena without the checks of `hash`,
+    /// for callers which validated the parameters themselves.
     ///
-    /// For more information about the input values, consider the Catena
-    /// specification.
-    pub fn generate_key (
+    /// The inputs are the same as for `hash`. An empty salt is hashed like
+    /// any other, an `output_length` above `n` yields `n` bytes. Invalid
+    /// parameters of the instance and every error of `hash` panic somewhere
+    /// in the computation. Use `hash` for anything else.
+    pub fn hash_unchecked (
         &mut self,
-        pwd: Vec<u8>,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
         associated_data: &Vec<u8>,
-        salt: Vec<u8>,
         output_length: u16,
-        gamma: Vec<u8>,
-        key_size: u16,
-        key_identifier: Vec<u8>
+        gamma: &Vec<u8>
     ) -> Vec<u8> {
+
         let tweak = self.compute_tweak(
-            Domain::KeyDerivation,
-            output_length,
-            salt.len() as u16,
-            associated_data);
+            Domain::PasswordScrambling,
+            output_length, salt.len() as u16,
+            &associated_data);
 
-        let g_low: u8;
-        let g_high: u8;
+        self.hash_with_tweak(pwd, &tweak, salt, output_length, gamma)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Password scrambling function of Catena with an output of `n` bytes.
+    ///
+    /// This is `hash` with `output_length = n`, the natural output length of
+    /// password scrambling. For longer outputs, e.g. keys, use
+    /// `generate_key` instead.
+    pub fn password_hash (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Result<PasswordHash, CatenaError> {
+        let n: usize;
 
         {
-            g_low = self.g_low;
-            g_high = self.g_high;
+            n = self.n;
         }
 
-        self.key_generation(
-            pwd,
-            tweak,
-            salt,
-            g_low,
-            g_high,
-            output_length,
-            gamma,
-            key_size,
-            key_identifier)
+        self.hash(pwd, salt, associated_data, n as u16, gamma)
     }
 
-    /// Compute the new hash with `g_high = old_g_high` for an updated security
-    /// parameter `new_g_high` independent from the client.
-    /// The value for `new_g_high` has to be bigger than `old_g_high`.
-    pub fn client_independent_update (
+    /// Check a password against a stored hash.
+    ///
+    /// The hash is recomputed with an output length of `hash.len()` and
+    /// compared in constant time. A stored hash which is empty or longer than
+    /// `n` bytes can not match and yields `Ok(false)`. The other errors of
+    /// `hash` are returned.
+    pub fn verify (
         &mut self,
-        old_hash: Vec<u8>,
-        old_g_high: u8,
-        new_g_high: u8,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
         gamma: &Vec<u8>,
-        output_length: u16
-    ) -> Vec<u8> {
-
+        hash: &PasswordHash
+    ) -> Result<bool, CatenaError> {
+        let hash = hash.as_bytes();
         let n: usize;
-
         {
             n = self.n;
         }
 
-        if old_g_high >= new_g_high {
-            panic!("new_g_high has to be bigger than old_g_high");
+        if hash.is_empty() || hash.len() > n {
+            return Ok(false);
         }
+        let computed = self.hash(
+            pwd, salt, associated_data, hash.len() as u16, gamma)?;
+        Ok(::helpers::vectors::ct_eq(computed.as_bytes(), hash))
+    }
 
-        let mut new_hash: Vec<u8> = old_hash.clone();
-
-        for g in old_g_high + 1 .. new_g_high + 1 {
-            if new_hash.len() < n {
-                new_hash = ::helpers::vectors::zero_padding(
-                    new_hash.clone(), n - output_length as usize);
-            }
-
-            // compute flap(g, h || 0^∗ , γ)
-            let flap =
-                self.flap(
-                    g,
-                    new_hash,
-                    gamma);
-
-            // compute H(g || flap(g, h || 0^∗ , γ))
-            new_hash = self.h2(
-                &g.to_le_bytes(),
-                &flap);
-
-            // compute truncate(H(g || flap(g, h || 0^∗ , γ)), m)
-            new_hash.truncate(output_length as usize);
+    /// Check a password against a stored hash like `verify`, but distinguish
+    /// a misconfigured instance from a wrong password.
+    ///
+    /// The output of Catena is at most `n` bytes long, so a stored hash which
+    /// is empty or longer was not computed with the parameters of this
+    /// instance and yields `StoredLengthMismatch`. Otherwise a hash which does
+    /// not match yields `PasswordMismatch`. Note that other wrong parameters,
+    /// e.g. the garlic, cannot be told apart from a wrong password.
+    pub fn validate_against_hash (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>,
+        stored: &PasswordHash
+    ) -> Result<(), CatenaError> {
+        let n: usize;
+        {
+            n = self.n;
         }
 
-        new_hash
+        if stored.0.is_empty() || stored.0.len() > n {
+            return Err(CatenaError::StoredLengthMismatch {
+                max: n,
+                actual: stored.0.len(),
+            });
+        }
+        if self.verify(pwd, salt, associated_data, gamma, stored)? {
+            Ok(())
+        } else {
+            Err(CatenaError::PasswordMismatch)
+        }
     }
 
-    /// Compute the new encrypted hash with `g_high = old_g_high` for an updated
-    /// security parameter `new_g_high` independent from the client for an
-    /// encrypted hash.
-    /// The value for `new_g_high` has to be bigger than `old_g_high`.
-    pub fn keyed_client_independent_update (
+    /// Password scrambling function of Catena with a MAC which binds the hash
+    /// to its inputs other than the password and the parameters of the
+    /// instance.
+    ///
+    /// Returns the output of `hash` and the MAC, keyed Blake2b with
+    /// `mac_key` over `fp || |s| || s || |γ| || γ || |h| || h || AD`, where
+    /// `fp` is the output of `config_fingerprint`, which covers the options,
+    /// and the lengths of the salt `s`, of γ and of the hash `h` are 2 bytes
+    /// in little endian. Store both, so that `verify_authenticated` detects a
+    /// modification of the stored hash, the salt, γ or the associated data
+    /// before the password is checked. A `mac_key` longer than 64 bytes
+    /// returns `CatenaError::MacKeyTooLong`.
+    pub fn hash_authenticated (
         &mut self,
-        old_encrypted_hash: Vec<u8>,
-        old_g_high: u8,
-        new_g_high: u8,
-        gamma: &Vec<u8>,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
         output_length: u16,
-        server_key: &Vec<u8>,
-        user_id: &Vec<u8>
-    ) -> Vec<u8> {
-        let keystream = self.compute_keystream(
-            &server_key,
-            &user_id,
-            old_g_high,
-            output_length as usize);
-
-        let old_hash = ::helpers::vectors::xor(
-            old_encrypted_hash, keystream);
-
-        let new_hash = self.client_independent_update(
-            old_hash,
-            old_g_high,
-            new_g_high,
-            gamma,
-            output_length);
-
-        let new_keystream = self.compute_keystream(
-            &server_key,
-            &user_id,
-            new_g_high,
-            output_length as usize);
-
-        ::helpers::vectors::xor(new_hash, new_keystream)
+        gamma: &Vec<u8>,
+        mac_key: &[u8]
+    ) -> Result<(PasswordHash, Vec<u8>), CatenaError> {
+        check_mac_key(mac_key)?;
+        let hash = self.hash(pwd, salt, associated_data, output_length, gamma)?;
+        let mac = self.hash_mac(
+            salt, gamma, hash.as_bytes(), associated_data, mac_key)?;
+        Ok((hash, mac))
     }
 
-    /// The client-side computation for the server relief.
-    pub fn client_prep (
+    /// Check a hash and MAC of `hash_authenticated`.
+    ///
+    /// The MAC is checked first and in constant time. A MAC which does not
+    /// match yields `MacMismatch` without computing Catena. Otherwise the
+    /// password is checked as by `validate_against_hash`.
+    pub fn verify_authenticated (
         &mut self,
-        pwd: Vec<u8>,
-        salt: Vec<u8>,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
         associated_data: &Vec<u8>,
-        output_length: u16,
-        gamma: &Vec<u8>
-    ) -> Vec<u8> {
-
-        let tweak = self.compute_tweak(
-            Domain::PasswordScrambling,
-            output_length,
-            salt.len() as u16,
-            associated_data);
+        gamma: &Vec<u8>,
+        stored: &PasswordHash,
+        mac: &[u8],
+        mac_key: &[u8]
+    ) -> Result<(), CatenaError> {
+        let expected = self.hash_mac(salt, gamma, stored.as_bytes(),
+                                     associated_data, mac_key)?;
+        if !::helpers::vectors::ct_eq(&expected, mac) {
+            return Err(CatenaError::MacMismatch);
+        }
+        self.validate_against_hash(pwd, salt, associated_data, gamma, stored)
+    }
 
-        let mut x = self.h3(&tweak, &pwd, &salt);
+    /// The MAC of `hash_authenticated`.
+    fn hash_mac(
+        &self,
+        salt: &[u8],
+        gamma: &[u8],
+        hash: &[u8],
+        associated_data: &[u8],
+        mac_key: &[u8]
+    ) -> Result<Vec<u8>, CatenaError> {
+        check_mac_key(mac_key)?;
+        let input = [&self.config_fingerprint()[..],
+                     &(salt.len() as u16).to_le_vec()[..],
+                     salt,
+                     &(gamma.len() as u16).to_le_vec()[..],
+                     gamma,
+                     &(hash.len() as u16).to_le_vec()[..],
+                     hash,
+                     associated_data].concat();
+        Ok(::components::hash::blake2b::mac(mac_key, &input))
+    }
 
+    /// Check a password against a hash computed with the garlic `stored_g`
+    /// and report whether it has to be upgraded to the garlic `min_g`.
+    ///
+    /// The hash is verified as by `verify` with `g_high = stored_g`. `g_low`
+    /// is kept unless it is larger than `stored_g`. Both are restored
+    /// afterwards, also if `verify` returns an error.
+    pub fn verify_min_garlic (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>,
+        stored: &PasswordHash,
+        stored_g: u8,
+        min_g: u8
+    ) -> Result<VerifyResult, CatenaError> {
         let g_low: u8;
         let g_high: u8;
-        let n: usize;
 
         {
             g_low = self.g_low;
             g_high = self.g_high;
-            n = self.n;
         }
 
-        x = self.flap((g_low + 1) / 2, x, &gamma);
-        x = self.algorithms.h(&x);
+        self.g_low = g_low.min(stored_g);
+        self.g_high = stored_g;
+        let verified = self.verify(pwd, salt, associated_data, gamma, stored);
+        self.g_low = g_low;
+        self.g_high = g_high;
 
-        // normal iterations
-        if g_high > g_low {
-            for g in g_low .. g_high {
-                if x.len() < n {
-                    x = ::helpers::vectors::zero_padding(x, n - output_length as usize);
-                }
-                x = self.flap(g, x, &gamma);
-                x = self.h2(&g.to_le_bytes(), &x);
-                x.truncate(output_length as usize);
-            }
+        if !verified? {
+            Ok(VerifyResult::Failed)
+        } else if stored_g < min_g {
+            Ok(VerifyResult::OkNeedsUpgrade)
+        } else {
+            Ok(VerifyResult::Ok)
         }
+    }
 
-        // omit the last invocation of H
-        if x.len() < n {
-            x = ::helpers::vectors::zero_padding(x, n - output_length as usize);
+    /// Hash a password with a random salt and return it as a PHC string.
+    ///
+    /// A salt of `PHC_SALT_LENGTH` bytes is generated, the password is hashed
+    /// with an output of `n` bytes and the result is encoded together with
+    /// the header of `encode_header`, see `catena::phc`. The PHC string can be
+    /// checked with `catena::verify_phc`. Returns
+    /// `CatenaError::UnsupportedOption` if an option which changes the output
+    /// is set, as the header can not describe it. Only available with the
+    /// default feature `rand`.
+    #[cfg(feature = "rand")]
+    pub fn hash_password_phc (
+        &mut self,
+        pwd: &[u8],
+        gamma: &[u8]
+    ) -> Result<String, CatenaError> {
+        let n: usize;
+
+        {
+            n = self.n;
         }
-        x = self.flap(g_high, x, &gamma);
 
-        x
+        let header = self.encode_header()?;
+        let salt = ::inputs::gen_salt(PHC_SALT_LENGTH)?;
+        let hash = self.hash(
+            &pwd.to_vec(), &salt, &Vec::new(), n as u16, &gamma.to_vec())?;
+        Ok(::phc::encode(&header, &salt, hash.as_bytes()))
     }
 
-    /// The server-side computation for the server-relief.
-    pub fn server_final (
+    /// Password scrambling function of Catena with a custom garlic schedule.
+    ///
+    /// Instead of iterating over `g_low..g_high + 1`, flap is computed for
+    /// each garlic of `schedule` in the given order, so `hash` is the same as
+    /// `hash_schedule` with the schedule `g_low, g_low + 1, ..., g_high`. The
+    /// other inputs are the same as for `hash`.
+    ///
+    /// Hashes computed with any other schedule are not compatible with other
+    /// implementations of Catena. An empty schedule returns
+    /// `CatenaError::InvalidGarlic`.
+    pub fn hash_schedule (
         &mut self,
-        client_output: Vec<u8>,
-        output_length: u16
-    ) -> Vec<u8> {
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>,
+        schedule: &[u8]
+    ) -> Result<PasswordHash, CatenaError> {
+        self.check_parameters(salt, output_length)?;
 
-        let mut x = client_output.clone();
-        let g = self.g_high.to_le_bytes();
-        x = self.h2(&g, &x);
-        x.truncate(output_length as usize);
-        x
+        let tweak = self.compute_tweak(
+            Domain::PasswordScrambling,
+            output_length, salt.len() as u16,
+            &associated_data);
+
+        self.try_catena_schedule(
+            &pwd,
+            &tweak,
+            salt,
+            schedule,
+            output_length,
+            &gamma).map(PasswordHash)
     }
 
-    /// Server side of Catena proof of work mode.
+    /// Password scrambling function of Catena which runs on the given CPUs.
     ///
-    /// # Inputs
+    /// The CPU affinity of the current thread is set to `cpu_set` for the
+    /// duration of the computation and restored afterwards, also if the
+    /// computation panics. Restricting the CPUs to a single NUMA node keeps
+    /// the large state in node-local memory.
+    /// The other inputs are the same as for `hash`.
     ///
-    /// - pwd: the password to be hashed
-    /// - salt: the salt value
-    /// - associated_data: associated data of the user
-    /// - gamma: a public and password-independent input
-    /// - output_length: length of the final hash in bytes
-    /// - p: number of secret bits <= 64
-    /// - mode:
-    ///     - 0: salt mode
-    ///     - 1: password mode
-    ///
-    /// # Returns
-    ///
-    /// - password
-    /// - salt
-    /// - associated data
-    /// - gamma
-    /// - output length
-    /// - output hash
-    /// - p
-    /// - mode (0 = salt; 1 = password)
-
-    pub fn proof_of_work_server(
+    /// This is only available on Linux with the feature `affinity`. An error
+    /// is returned if the affinity can not be set, e.g. if `cpu_set` is empty
+    /// or contains no CPU the thread may run on.
+    #[cfg(all(target_os = "linux", feature = "affinity"))]
+    pub fn hash_pinned (
         &mut self,
         pwd: &Vec<u8>,
-        salt: &mut Vec<u8>,
+        salt: &Vec<u8>,
         associated_data: &Vec<u8>,
+        output_length: u16,
         gamma: &Vec<u8>,
-        output_len: u16,
-        p: usize,
-        mode: u8
-    ) -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>, u16, Vec<u8>, usize, u8) {
+        cpu_set: &[usize]
+    ) -> Result<PasswordHash, CatenaError> {
+        let _pinned = ::helpers::affinity::pin(cpu_set)?;
+
+        self.hash(pwd, salt, associated_data, output_length, gamma)
+    }
+
+    /// Password scrambling function of Catena for several output lengths with
+    /// a single computation.
+    ///
+    /// Catena is computed once with the largest of `lengths` as output length
+    /// and the result is truncated to each of `lengths`, in the given order.
+    /// The output length is part of the tweak and of every iteration over the
+    /// garlic, so only the output for the largest length equals `hash` with
+    /// that length. The shorter outputs are prefixes of it and differ from
+    /// `hash` called with their own length. The other inputs and the errors
+    /// are the same as for `hash`, every length has to be valid for it.
+    pub fn hash_multi_len (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>,
+        lengths: &[u16]
+    ) -> Result<Vec<PasswordHash>, CatenaError> {
+        let output_length = match lengths.iter().max() {
+            Some(&max) => max,
+            None => return Ok(Vec::new()),
+        };
+        for &length in lengths {
+            self.check_parameters(salt, length)?;
+        }
+
+        let hash = self.hash(pwd, salt, associated_data, output_length, gamma)?;
+
+        Ok(lengths.iter()
+            .map(|&length| PasswordHash(
+                hash.as_bytes()[..length as usize].to_vec()))
+            .collect())
+    }
+
+    /// Password scrambling function of Catena for a batch of passwords which
+    /// share the salt, the associated data, the output length and γ.
+    ///
+    /// The tweak only depends on the shared inputs, so it is computed once
+    /// for the batch. The outputs are in the order of `pwds` and equal those
+    /// of `hash` for each password. The first error of `hash` is returned.
+    pub fn hash_batch (
+        &mut self,
+        pwds: &[&[u8]],
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<PasswordHash>, CatenaError> {
+        self.check_parameters(salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::PasswordScrambling,
+            output_length, salt.len() as u16,
+            &associated_data);
+
+        pwds.iter()
+            .map(|pwd| self.hash_with_tweak(
+                pwd, &tweak, salt, output_length, gamma).map(PasswordHash))
+            .collect()
+    }
+
+    /// Password scrambling function of Catena with a precomputed tweak.
+    fn hash_with_tweak (
+        &mut self,
+        pwd: &[u8],
+        tweak: &Vec<u8>,
+        salt: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+        let g_low: u8;
+        let g_high: u8;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+        }
+
+        self.try_catena(
+            &pwd.to_vec(), tweak, salt, g_low, g_high, output_length, gamma)
+    }
+
+    /// Password scrambling function of Catena which also reports the cost of
+    /// the computation.
+    ///
+    /// The output is the same as that of `hash`. The cost is computed from
+    /// the parameters of the instance, not measured, so it is cheap enough to
+    /// be reported for every hash. See `HashCost`.
+    pub fn hash_with_cost (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<(PasswordHash, HashCost), CatenaError> {
+        let hash = self.hash(pwd, salt, associated_data, output_length, gamma)?;
+        Ok((hash, self.hash_cost()))
+    }
 
+    /// The cost of `hash` with the current parameters, see `hash_with_cost`.
+    pub fn hash_cost(&self) -> HashCost {
         let g_low: u8;
         let g_high: u8;
 
@@ -392,864 +1354,3991 @@ impl<T: Algorithms> Catena <T> {
             g_high = self.g_high;
         }
 
-        let tweak = self.compute_tweak(
-            Domain::ProofOfWork,
-            output_len,
-            salt.len() as u16,
-            associated_data);
+        // the first flap uses (g_low + 1) / 2, then one flap for each garlic
+        let garlics: Vec<u8> = Some((g_low + 1) / 2).into_iter()
+            .chain(g_low..g_high + 1)
+            .collect();
+        let memory_bytes = garlics.iter()
+            .map(|&g| self.memory_bytes(g))
+            .max()
+            .unwrap_or(0);
+        let h_prime_calls = garlics.iter()
+            .map(|&g| self.h_prime_invocations(g))
+            .fold(Some(0), |sum, calls| match (sum, calls) {
+                (Some(sum), Some(calls)) => Some(sum + calls),
+                _ => None,
+            });
+
+        HashCost {
+            memory_bytes: memory_bytes,
+            garlic_low: g_low,
+            garlic_high: g_high,
+            h_prime_calls: h_prime_calls,
+        }
+    }
 
-        let hash = self.catena(
-            &pwd,
-            &tweak,
-            &salt,
-            g_low,
-            g_high,
-            output_len,
-            &gamma);
+    /// Password scrambling function of Catena which writes the output to
+    /// `out`.
+    ///
+    /// The output is the same as that of `hash`, which is written with a
+    /// single `write_all`. The errors of `hash` are returned, and errors of
+    /// `out` as `CatenaError::IoFailed`. The other inputs are the same as for
+    /// `hash`.
+    pub fn hash_to_writer<W: Write> (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>,
+        out: &mut W
+    ) -> Result<(), CatenaError> {
+        let hash = self.hash(pwd, salt, associated_data, output_length, gamma)?;
+        out.write_all(hash.as_bytes())
+            .map_err(|e| CatenaError::IoFailed { kind: e.kind() })
+    }
 
+    /// Password scrambling function of Catena with H replaced by `h` for this
+    /// call.
+    ///
+    /// `h` is used for the tweak, the first hash of the password and the hash
+    /// after each flap. H' stays that of the instance, and Γ, F and Φ are
+    /// computed by the algorithms of the instance, so SaltMix or the first
+    /// layer of a double-butterfly graph still use the H of the instance.
+    /// `h` has to return `n` bytes. Unless `h` is the H of the instance, the
+    /// output differs from `hash`, so this is meant for migrations between
+    /// choices of H and for testing. The other inputs are the same as for
+    /// `hash`.
+    pub fn hash_with_h (
+        &mut self,
+        h: &dyn Fn(&[u8]) -> Vec<u8>,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<PasswordHash, CatenaError> {
+        let mut with_h = Catena {
+            algorithms: WithH {
+                inner: &mut self.algorithms,
+                h: h,
+            },
+            vid: self.vid,
+            n: self.n,
+            k: self.k,
+            g_low: self.g_low,
+            g_high: self.g_high,
+            lambda: self.lambda,
+            options: self.options,
+        };
+        with_h.hash(pwd, salt, associated_data, output_length, gamma)
+    }
 
-        if mode == 0 {
-            let p_bits: u64 = (1 << (8 * ((p / 8) + 1))) - (1 << p);
-            let mut mask = p_bits.to_be_bytes();
+    /// Password scrambling function of Catena with the output split into
+    /// words of `word_size` bytes.
+    ///
+    /// The concatenation of the words equals the output of `hash`. Returns
+    /// `CatenaError::InvalidWordSize` if `output_length` is not a multiple of
+    /// `word_size` or `word_size` is zero. The other inputs are the same as for
+    /// `hash`.
+    pub fn hash_words (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>,
+        word_size: usize
+    ) -> Result<Vec<Vec<u8>>, CatenaError> {
+        if word_size == 0 || output_length as usize % word_size != 0 {
+            return Err(CatenaError::InvalidWordSize);
+        }
 
-            // remove preceding 0-bytes
-            while mask[0] == 0  && mask.len() > 1{
-                let _ = mask.remove(0);
-            }
+        let hash = self.hash(
+            pwd, salt, associated_data, output_length, gamma)?;
 
-            let salt_len = salt.len();
-            let mask_len = mask.len();
+        Ok((0..hash.0.len() / word_size)
+            .map(|i| hash.0.get_word(word_size, i))
+            .collect())
+    }
 
-            for (i, ..) in mask.iter().enumerate() {
-                let salt_byte = salt[salt_len - (i + 1)];
-                let mask_byte = mask[mask_len - (i + 1)];
-                salt[salt_len - (i + 1)] = mask_byte & salt_byte;
-            }
+    /// Password scrambling function of Catena with the associated data given
+    /// as multiple parts.
+    ///
+    /// The parts are hashed as their concatenation, so the result equals
+    /// `hash` called with the concatenated associated data.
+    ///
+    /// # Inputs
+    ///
+    /// - pwd: The password to be hashed.
+    /// - salt: The salt value.
+    /// - ad_parts: Parts of the associated data of the user and/or the host.
+    /// - output_length: The length of the final hash in bytes.
+    /// - gamma: A public and password-independent input
+    pub fn hash_ad_parts (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        ad_parts: &[&[u8]],
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<PasswordHash, CatenaError> {
+        let associated_data = ad_parts.concat();
+
+        self.hash(
+            pwd,
+            salt,
+            &associated_data,
+            output_length,
+            gamma)
+    }
+
+    /// Password scrambling function of Catena with typed salt and γ.
+    ///
+    /// This is the same as `hash`, but the distinct types of `salt` and
+    /// `gamma` prevent passing them in the wrong order. See `catena::inputs`
+    /// for the roles of both inputs.
+    pub fn hash_typed (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Salt,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Gamma
+    ) -> Result<PasswordHash, CatenaError> {
+        self.hash(
+            pwd,
+            &salt.0,
+            associated_data,
+            output_length,
+            &gamma.0)
+    }
+
+    /// Compute an encrypted hash for a given password.
+    ///
+    /// # Inputs
+    ///
+    /// - pwd: The password to be hashed.
+    /// - salt: The salt value.
+    /// - associated_data: Associated data of the user and/or the host.
+    /// - output_length: The length of the final hash in bytes.
+    /// - gamma: A public and password-independent input
+    /// - g_high: The maximum garlic.
+    /// - server_key: The key which is used to encrypt the output of Catena.
+    ///
+    /// For more information about the input values, consider the Catena
+    /// specification. The parameters are checked like in `hash`.
+    pub fn keyed_hashing (
+        &mut self,
+        user_pwd: Vec<u8>,
+        salt: Vec<u8>,
+        a_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>,
+        user_id: Vec<u8>,
+        g_high: u8,
+        server_key: &Vec<u8>
+    ) -> Result<PasswordHash, CatenaError> {
+        let hash = self.hash(&user_pwd, &salt, a_data, output_length, gamma)?;
+        let keystream = self.compute_keystream(
+                &server_key,
+                &user_id,
+                g_high,
+                output_length as usize);
+
+            Ok(PasswordHash(::helpers::vectors::xor(hash.0, keystream)))
+        }
+
+    /// Password scrambling function of Catena with a secret pepper.
+    ///
+    /// The pepper is a secret of the server which is stored apart from the
+    /// hashes and salts. It is appended to the password with its length as
+    /// 2 bytes in little endian, so the first hash of Catena is
+    /// `H(t || pwd || pepper || |pepper| || s)`. An empty pepper appends
+    /// nothing, which equals `hash`. The other inputs are the same as for
+    /// `hash`.
+    ///
+    /// Unlike `keyed_hashing`, which encrypts the output of Catena with a
+    /// keystream of the server key and can be decrypted to update the key,
+    /// the pepper is mixed into the input of the memory-hard computation. A
+    /// hash can only be checked with the pepper, and changing the pepper
+    /// requires the passwords.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pepper is longer than 65535 bytes.
+    pub fn hash_peppered (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        pepper: &[u8],
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<PasswordHash, CatenaError> {
+        if pepper.is_empty() {
+            return self.hash(
+                pwd, salt, associated_data, output_length, gamma);
+        }
+        assert!(pepper.len() <= u16::max_value() as usize,
+                "pepper has {} bytes, at most 65535 are supported",
+                pepper.len());
+
+        let peppered = [&pwd[..], pepper,
+                        &(pepper.len() as u16).to_le_vec()[..]].concat();
+        self.hash(&peppered, salt, associated_data, output_length, gamma)
+    }
+
+    /// Key-Derivation function Catena-KG
+    ///
+    /// For more information about the input values, consider the Catena
+    /// specification. The parameters are checked like in `hash`. The output
+    /// is a `DerivedKey`, which can not be checked as a password hash.
+    pub fn generate_key (
+        &mut self,
+        pwd: Vec<u8>,
+        associated_data: &Vec<u8>,
+        salt: Vec<u8>,
+        output_length: u16,
+        gamma: Vec<u8>,
+        key_size: u16,
+        key_identifier: Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        self.check_parameters(&salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::KeyDerivation,
+            output_length,
+            salt.len() as u16,
+            associated_data);
+
+        let g_low: u8;
+        let g_high: u8;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+        }
+
+        self.key_generation(
+            pwd,
+            tweak,
+            salt,
+            g_low,
+            g_high,
+            output_length,
+            gamma,
+            key_size,
+            key_identifier).map(DerivedKey)
+    }
+
+    /// The output of Catena in the key-derivation domain before the expansion
+    /// of Catena-KG.
+    ///
+    /// This is `hash` with the domain byte of the tweak set to
+    /// key derivation, i.e. the value `generate_key` expands with
+    /// `CounterExpand`. The inputs and the errors are the same as for `hash`.
+    /// The output is as secret as the key, so it is a `DerivedKey`.
+    pub fn hash_kdf_domain (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        self.check_parameters(salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::KeyDerivation,
+            output_length,
+            salt.len() as u16,
+            associated_data);
+
+        let g_low: u8;
+        let g_high: u8;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+        }
+
+        self.try_catena(pwd, &tweak, salt, g_low, g_high, output_length, gamma)
+            .map(DerivedKey)
+    }
+
+    /// Chain this instance with the instance `kdf` of Catena-KG, see
+    /// `ChainedCatena`.
+    pub fn then_kdf<K: Algorithms>(self, kdf: Catena<K>) -> ChainedCatena<T, K> {
+        ChainedCatena {
+            hasher: self,
+            kdf: kdf,
+        }
+    }
+
+    /// The output of Catena in Catena-KG, i.e. the memory-hard part of
+    /// `generate_key` before the key expansion.
+    ///
+    /// It can be stored as a checkpoint and expanded to the key with
+    /// `generate_key_resumable`. It must be kept as secret as the key. This
+    /// is `hash_kdf_domain` with the arguments in the order of
+    /// `generate_key`. The errors are the same as for `generate_key`.
+    pub fn generate_key_state (
+        &mut self,
+        pwd: Vec<u8>,
+        associated_data: &Vec<u8>,
+        salt: Vec<u8>,
+        output_length: u16,
+        gamma: Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        self.hash_kdf_domain(
+            &pwd, &salt, associated_data, output_length, &gamma)
+    }
+
+    /// Expand the output `state` of `generate_key_state` to at most `blocks`
+    /// blocks of the key of `key_size` bytes, starting at block `start_block`.
+    ///
+    /// Blocks have `n` bytes and are counted from 0, the last block of the
+    /// key is truncated. Returns the bytes of the blocks and the block to
+    /// resume from, which equals the number of blocks of the key when the key
+    /// is complete. The concatenation of the outputs of consecutive calls
+    /// equals `generate_key` with the same inputs, so an interrupted
+    /// derivation of a large key does not have to recompute Catena.
+    pub fn generate_key_resumable (
+        &mut self,
+        state: &DerivedKey,
+        key_size: u16,
+        key_identifier: Vec<u8>,
+        start_block: u16,
+        blocks: u16
+    ) -> (Vec<u8>, u16) {
+        let expansion = CounterExpand {
+            key_identifier: key_identifier,
+            n: self.n,
+        };
+        let end = (start_block as usize + blocks as usize)
+            .min(expansion.blocks(key_size as usize));
+        let key = expansion.expand_blocks(
+            &self.algorithms, &state.0, key_size as usize,
+            start_block as usize, end);
+
+        (key, end.max(start_block as usize) as u16)
+    }
+
+    /// Key-Derivation function Catena-KG for keys larger than 65535 bytes.
+    ///
+    /// This is the same as `generate_key`, but the key size is a `usize` and
+    /// the block counter of the key expansion is a `u64`, so it does not wrap
+    /// for keys of more than 65535 blocks. The counter and the key size are
+    /// encoded as 8-byte little-endian values instead of 2-byte values, so the
+    /// keys differ from those of `generate_key` even for small key sizes. The
+    /// errors are the same as for `generate_key`, and
+    /// `CatenaError::AllocationFailed` is returned if the key can not be
+    /// allocated.
+    pub fn generate_key_large (
+        &mut self,
+        pwd: Vec<u8>,
+        associated_data: &Vec<u8>,
+        salt: Vec<u8>,
+        output_length: u16,
+        gamma: Vec<u8>,
+        key_size: usize,
+        key_identifier: Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        self.check_parameters(&salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::KeyDerivation,
+            output_length,
+            salt.len() as u16,
+            associated_data);
+
+        let g_low: u8;
+        let g_high: u8;
+        let n: usize;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+            n = self.n;
+        }
+
+        let x = self.try_catena(
+            &pwd, &tweak, &salt, g_low, g_high, output_length, &gamma)?;
+        let mut k: Vec<u8> = Vec::new();
+        ::helpers::vectors::try_reserve(&mut k, key_size)?;
+
+        let blocks = (key_size / n + if key_size % n == 0 { 0 } else { 1 })
+            as u64;
+        let key_size_bytes = (key_size as u64).to_le_vec();
+
+        for i in 1..blocks + 1 {
+            let block = self.h4(
+                &i.to_le_vec(),
+                &key_identifier,
+                &key_size_bytes,
+                &x);
+            // the last block is truncated to the remaining bytes of the key
+            let remaining = (key_size - k.len()).min(block.len());
+            k.extend_from_slice(&block[..remaining]);
+        }
+
+        Ok(DerivedKey(k))
+    }
+
+    /// Derive `n_keys` keys of `key_size` bytes with a forward-secret ratchet.
+    ///
+    /// The ratchet starts with `c_0`, the key of `generate_key` with an output
+    /// length and a key size of `n` and an empty key identifier, so the
+    /// memory-hard computation only runs once. It is advanced by
+    /// `c_(i+1) = H(c_i || i)`, where `i` is encoded as an 8-byte
+    /// little-endian value. The key `k_i` is `c_i` truncated to `key_size`
+    /// bytes, so the ratchet always keeps the full `n` bytes. As H is one-way,
+    /// a compromised key does not reveal the keys before it.
+    ///
+    /// Returns `CatenaError::ZeroOutputLength` if `key_size` is zero,
+    /// `CatenaError::OutputTooLong` if it is larger than the output length `n`
+    /// of H, and the errors of `generate_key`.
+    pub fn key_ratchet (
+        &mut self,
+        pwd: Vec<u8>,
+        associated_data: &Vec<u8>,
+        salt: Vec<u8>,
+        gamma: Vec<u8>,
+        n_keys: usize,
+        key_size: u16
+    ) -> Result<Vec<DerivedKey>, CatenaError> {
+        let n: usize;
+
+        {
+            n = self.n;
+        }
+
+        if key_size == 0 {
+            return Err(CatenaError::ZeroOutputLength);
+        }
+        if key_size as usize > n {
+            return Err(CatenaError::OutputTooLong {
+                requested: key_size as usize,
+                max: n,
+            });
+        }
+
+        let mut keys: Vec<DerivedKey> = Vec::with_capacity(n_keys);
+        if n_keys == 0 {
+            return Ok(keys);
+        }
+
+        let mut chain = self.generate_key(
+            pwd, associated_data, salt, n as u16, gamma, n as u16, Vec::new())?
+            .0;
+        for i in 0..n_keys {
+            if i > 0 {
+                chain = self.h2(&chain, &((i - 1) as u64).to_le_vec());
+            }
+            keys.push(DerivedKey(chain[..key_size as usize].to_vec()));
+        }
+        Ok(keys)
+    }
+
+    /// Compute the new hash with `g_high = old_g_high` for an updated security
+    /// parameter `new_g_high` independent from the client.
+    ///
+    /// Returns `CatenaError::InvalidGarlic` if `new_g_high` is not bigger
+    /// than `old_g_high` and the errors of `update_one_step`.
+    pub fn client_independent_update (
+        &mut self,
+        old_hash: Vec<u8>,
+        old_g_high: u8,
+        new_g_high: u8,
+        gamma: &Vec<u8>,
+        output_length: u16
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        if old_g_high >= new_g_high {
+            return Err(CatenaError::InvalidGarlic);
+        }
+
+        let mut new_hash: Vec<u8> = old_hash.clone();
+        let mut g: u8 = old_g_high;
+
+        while g < new_g_high {
+            let (hash, next_g) = self.update_one_step(
+                new_hash, g, gamma, output_length)?;
+            new_hash = hash;
+            g = next_g;
+        }
+
+        Ok(new_hash)
+    }
+
+    /// Advance a hash computed with `g_high = current_g` by exactly one garlic.
+    ///
+    /// Returns the new hash and the new garlic `current_g + 1`. Calling this
+    /// repeatedly gives the same result as `client_independent_update`, so a
+    /// server can store the hash after each step and continue later.
+    ///
+    /// Returns `CatenaError::InvalidGarlic` if `current_g + 1` exceeds
+    /// `cli::MAX_GARLIC`, `CatenaError::ZeroOutputLength` or
+    /// `CatenaError::OutputTooLong` if `output_length` is zero or exceeds
+    /// `n`, `CatenaError::UnsupportedOption` if the last hash of Catena is
+    /// bound to the salt or the garlic, see `with_bind_salt_in_final` and
+    /// `with_bind_garlic`, and the errors of flap.
+    pub fn update_one_step (
+        &mut self,
+        hash: Vec<u8>,
+        current_g: u8,
+        gamma: &Vec<u8>,
+        output_length: u16
+    ) -> Result<(Vec<u8>, u8), CatenaError> {
+
+        let n: usize;
+
+        {
+            n = self.n;
+        }
+
+        self.check_update()?;
+        self.check_output_length(output_length)?;
+
+        let g = match current_g.checked_add(1) {
+            Some(g) if g <= ::cli::MAX_GARLIC => g,
+            _ => return Err(CatenaError::InvalidGarlic),
+        };
+        let mut new_hash = hash;
+
+        if new_hash.len() < n {
+            new_hash = ::helpers::vectors::zero_padding(
+                new_hash.clone(), n - output_length as usize);
+        }
+
+        // compute flap(g, h || 0^∗ , γ)
+        let flap =
+            self.try_flap(
+                g,
+                new_hash,
+                gamma)?;
+
+        // compute H(g || flap(g, h || 0^∗ , γ))
+        new_hash = self.h2(
+            &g.to_le_vec(),
+            &flap);
+
+        // compute truncate(H(g || flap(g, h || 0^∗ , γ)), m)
+        new_hash.truncate(output_length as usize);
+
+        Ok((new_hash, g))
+    }
+
+    /// Compute the new encrypted hash with `g_high = old_g_high` for an updated
+    /// security parameter `new_g_high` independent from the client for an
+    /// encrypted hash.
+    ///
+    /// The errors are those of `client_independent_update`.
+    pub fn keyed_client_independent_update (
+        &mut self,
+        old_encrypted_hash: Vec<u8>,
+        old_g_high: u8,
+        new_g_high: u8,
+        gamma: &Vec<u8>,
+        output_length: u16,
+        server_key: &Vec<u8>,
+        user_id: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+        let keystream = self.compute_keystream(
+            &server_key,
+            &user_id,
+            old_g_high,
+            output_length as usize);
+
+        let old_hash = ::helpers::vectors::xor(
+            old_encrypted_hash, keystream);
+
+        let new_hash = self.client_independent_update(
+            old_hash,
+            old_g_high,
+            new_g_high,
+            gamma,
+            output_length)?;
+
+        let new_keystream = self.compute_keystream(
+            &server_key,
+            &user_id,
+            new_g_high,
+            output_length as usize);
+
+        Ok(::helpers::vectors::xor(new_hash, new_keystream))
+    }
+
+    /// The client-side computation for the server relief.
+    ///
+    /// The inputs and the errors are the same as for `hash`. Additionally,
+    /// `CatenaError::UnsupportedOption` is returned if the last hash of
+    /// Catena is bound to the salt, which the server does not know, see
+    /// `with_bind_salt_in_final`.
+    pub fn client_prep (
+        &mut self,
+        pwd: Vec<u8>,
+        salt: Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.check_parameters(&salt, output_length)?;
+        self.check_final_step()?;
+        self.check_gamma(gamma)?;
+
+        let tweak = self.compute_tweak(
+            Domain::PasswordScrambling,
+            output_length,
+            salt.len() as u16,
+            associated_data);
+
+        let mut x = self.algorithms.h(&initial_input(&tweak, &pwd, &salt));
+
+        let g_low: u8;
+        let g_high: u8;
+        let n: usize;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+            n = self.n;
+        }
+
+        if g_low > g_high {
+            return Err(CatenaError::InvalidGarlic);
+        }
+
+        let mut cache = SeedCache::default();
+        x = self.try_flap_cached((g_low + 1) / 2, x, &gamma, &mut cache)?;
+        x = self.algorithms.h(&x);
+
+        // normal iterations
+        if g_high > g_low {
+            for g in g_low .. g_high {
+                if x.len() < n {
+                    x = ::helpers::vectors::zero_padding(x, n - output_length as usize);
+                }
+                x = self.try_flap_cached(g, x, &gamma, &mut cache)?;
+                x = self.h2(&g.to_le_vec(), &x);
+                x.truncate(output_length as usize);
+            }
+        }
+
+        // omit the last invocation of H
+        if x.len() < n {
+            x = ::helpers::vectors::zero_padding(x, n - output_length as usize);
+        }
+        self.try_flap_cached(g_high, x, &gamma, &mut cache)
+    }
+
+    /// The server-side computation for the server-relief.
+    ///
+    /// The client output is checked by `validate_client_output` and the
+    /// output length like in `hash`, otherwise their errors are returned.
+    /// As for `client_prep`, `CatenaError::UnsupportedOption` is returned if
+    /// the last hash of Catena is bound to the salt. The binding to the
+    /// garlic of `with_bind_garlic` is applied.
+    pub fn server_final (
+        &mut self,
+        client_output: Vec<u8>,
+        output_length: u16
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.check_final_step()?;
+        self.validate_client_output(&client_output)?;
+        self.check_output_length(output_length)?;
+
+        let g_high: u8;
+
+        {
+            g_high = self.g_high;
+        }
+
+        let garlic_part = if self.options.bind_garlic {
+            vec![b'g', g_high]
+        } else {
+            Vec::new()
+        };
+        let mut x = self.h4(
+            &g_high.to_le_vec(), &client_output, &Vec::new(), &garlic_part);
+        x.truncate(output_length as usize);
+        Ok(x)
+    }
+
+    /// Returns `CatenaError::UnsupportedOption` if the last hash of Catena
+    /// needs an input which the server relief and the client-independent
+    /// updates do not have.
+    fn check_final_step(&self) -> Result<(), CatenaError> {
+        if self.options.bind_salt_in_final {
+            return Err(CatenaError::UnsupportedOption {
+                option: "bind_salt_in_final",
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns `CatenaError::UnsupportedOption` if a stored hash can not be
+    /// continued with a higher garlic, i.e. as `check_final_step` and if the
+    /// last hash of Catena is bound to the garlic.
+    fn check_update(&self) -> Result<(), CatenaError> {
+        self.check_final_step()?;
+        if self.options.bind_garlic {
+            return Err(CatenaError::UnsupportedOption {
+                option: "bind_garlic",
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the output of `client_prep` received by the server before
+    /// `server_final` spends a call of H on it.
+    ///
+    /// The client output is the last word of flap with `g_high`, so it has to
+    /// have `k` bytes, otherwise `CatenaError::OutputLengthMismatch` is
+    /// returned. This rejects malformed submissions, it can not detect a
+    /// wrong password.
+    pub fn validate_client_output(&self, client_output: &[u8])
+        -> Result<(), CatenaError>
+    {
+        let expected = self.k_for_garlic(self.g_high);
+        if client_output.len() != expected {
+            return Err(CatenaError::OutputLengthMismatch {
+                function: "flap",
+                expected: expected,
+                actual: client_output.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Catena in the proof-of-work mode with `g_low` and `g_high` of the
+    /// instance, the hash of the proof of work in `catena::pow`.
+    ///
+    /// The errors are the same as for `hash`, except that an empty salt is
+    /// allowed: in the password mode of the proof of work the salt may be
+    /// empty.
+    pub fn proof_of_work_hash(
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>,
+        output_len: u16
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.check_instance()?;
+        self.check_output_length(output_len)?;
+
+        let g_low: u8;
+        let g_high: u8;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+        }
+
+        let tweak = self.compute_tweak(
+            Domain::ProofOfWork,
+            output_len,
+            salt.len() as u16,
+            associated_data);
+
+        self.try_catena(pwd, &tweak, salt, g_low, g_high, output_len, gamma)
+    }
+
+    /// Check the instance by hashing a fixed input at a low garlic.
+    ///
+    /// For the predefined instances the output is compared against a known
+    /// answer. For instances with an unknown version ID it is only checked
+    /// that the output has length `n` and is not all-zero. The garlic of the
+    /// instance is not changed.
+    pub fn self_test(&mut self) -> Result<(), CatenaError> {
+        let output = self.self_test_output()?;
+
+        if self.self_test_passes(&output) {
+            Ok(())
+        } else {
+            Err(CatenaError::SelfTestFailed)
+        }
+    }
+
+    /// Check that the instance computes the same output for the same input.
+    ///
+    /// This is `self_test` with the fixed input hashed a second time and
+    /// compared with the first output. There is no accelerated
+    /// implementation of H' yet. Once there is, its output is compared
+    /// against the reference implementation here as well. Both failures are
+    /// reported as `CatenaError::NotDeterministic`.
+    pub fn assert_deterministic(&mut self) -> Result<(), CatenaError> {
+        let output = self.self_test_output()?;
+        let repeated = self.self_test_output()?;
+
+        if output == repeated && self.self_test_passes(&output) {
+            Ok(())
+        } else {
+            Err(CatenaError::NotDeterministic)
+        }
+    }
+
+    /// The hash of the fixed input of `self_test` with `g_low = g_high =
+    /// SELF_TEST_GARLIC`, computed without changing the garlic of the
+    /// instance.
+    fn self_test_output(&mut self) -> Result<Vec<u8>, CatenaError> {
+        let n: u16;
+
+        {
+            n = self.n as u16;
+        }
+
+        let pwd = b"password".to_vec();
+        let salt = b"salt".to_vec();
+        self.check_parameters(&salt, n)?;
+
+        let tweak = self.compute_tweak(
+            Domain::PasswordScrambling, n, salt.len() as u16,
+            &b"data".to_vec());
+
+        self.try_catena(&pwd, &tweak, &salt, SELF_TEST_GARLIC,
+                        SELF_TEST_GARLIC, n, &b"gamma".to_vec())
+    }
+
+    /// Whether `output` is the known answer of `self_test`, or for an unknown
+    /// version ID, has length `n` and is not all-zero.
+    fn self_test_passes(&self, output: &Vec<u8>) -> bool {
+        match SELF_TEST_VECTORS.iter().find(|v| v.0 == self.vid) {
+            Some(&(_, expected)) => output.to_hex_string() == expected,
+            None => output.len() == self.n && output.iter().any(|&b| b != 0),
+        }
+    }
+
+    /// Encode the parameters of the instance as a header which can be stored
+    /// next to a hash.
+    ///
+    /// The format is `catena-<vid>$g=<g_high>,l=<lambda>,n=<n>` with the
+    /// version ID in lower case. If `g_low` differs from `g_high`, it is
+    /// added as `gl=<g_low>` after `g`.
+    ///
+    /// The header only describes the construction of the specification. If an
+    /// option which changes the output is set, see `output_option`,
+    /// `CatenaError::UnsupportedOption` is returned instead of a header which
+    /// would claim the standard construction.
+    pub fn encode_header(&self) -> Result<Vec<u8>, CatenaError> {
+        if let Some(option) = self.output_option() {
+            return Err(CatenaError::UnsupportedOption { option: option });
+        }
+
+        let mut params = format!("g={}", self.g_high);
+        if self.g_low != self.g_high {
+            params.push_str(&format!(",gl={}", self.g_low));
+        }
+        params.push_str(&format!(",l={},n={}", self.lambda, self.n));
+
+        Ok(format!("catena-{}${}", self.vid.to_lowercase(), params)
+            .into_bytes())
+    }
+
+    /// The name of the first option of `Options` which is set and changes the
+    /// output, i.e. `h_id`, `bind_salt_in_final`, `bind_garlic`, `ad_hash` or
+    /// `k_schedule`. `None` for the construction of the specification.
+    pub fn output_option(&self) -> Option<&'static str> {
+        let options = &self.options;
+        if options.h_id.is_some() {
+            Some("h_id")
+        } else if options.bind_salt_in_final {
+            Some("bind_salt_in_final")
+        } else if options.bind_garlic {
+            Some("bind_garlic")
+        } else if options.ad_hash.is_some() {
+            Some("ad_hash")
+        } else if options.k_schedule.is_some() {
+            Some("k_schedule")
+        } else {
+            None
+        }
+    }
+
+    /// Check whether a stored header was produced by this instance.
+    ///
+    /// `header` is either the header alone or a stored hash starting with the
+    /// header followed by `$`. This only compares the encoded parameters, it
+    /// does not verify the hash. An instance without a header, see
+    /// `encode_header`, matches no header.
+    pub fn matches_header(&self, header: &[u8]) -> bool {
+        let own = match self.encode_header() {
+            Ok(own) => own,
+            Err(_) => return false,
+        };
+        if header.len() == own.len() {
+            header == own.as_slice()
+        } else {
+            header.starts_with(&own) && header[own.len()] == b'$'
+        }
+    }
+
+    /// The parameters of the instance, e.g. to log them next to the
+    /// algorithms chosen through a `DynCatena`.
+    pub fn params(&self) -> CatenaParams {
+        CatenaParams {
+            vid: self.vid,
+            n: self.n,
+            k: self.k,
+            g_low: self.g_low,
+            g_high: self.g_high,
+            lambda: self.lambda,
+        }
+    }
+
+    /// A fingerprint of the parameters of the instance for audit logs.
+    ///
+    /// This is `H(|V| || V || n || k || g_low || g_high || λ || O)` with the
+    /// length of the version ID `V` as 2 bytes and `n` and `k` as 8 bytes in
+    /// little endian, truncated or padded with zeros to 32 bytes. `V` is the
+    /// version ID of the tweak, i.e. with the suffixes of
+    /// `bind_salt_in_final` and `bind_garlic`. `O` encodes the other options
+    /// which change the output: `h_id`, the hash of the empty associated data
+    /// by `ad_hash` and the entries of `k_schedule`, each prefixed by a byte
+    /// which tells whether the option is set. The algorithms of the instance
+    /// are only covered through the version ID and H.
+    pub fn config_fingerprint(&self) -> [u8; 32] {
+        let vid = self.tweak_vid();
+        let mut input = [&(vid.len() as u16).to_le_vec()[..],
+                         &vid[..],
+                         &(self.n as u64).to_le_vec()[..],
+                         &(self.k as u64).to_le_vec()[..],
+                         &[self.g_low, self.g_high, self.lambda]].concat();
+
+        match self.options.h_id {
+            Some(h_id) => input.extend_from_slice(&[1, h_id]),
+            None => input.push(0),
+        }
+        match self.options.ad_hash {
+            Some(ad_hash) => {
+                let had = ad_hash(&[]);
+                input.push(1);
+                input.extend_from_slice(&(had.len() as u16).to_le_vec());
+                input.extend_from_slice(&had);
+            }
+            None => input.push(0),
+        }
+        match self.options.k_schedule {
+            Some(schedule) => {
+                input.push(1);
+                input.extend_from_slice(&(schedule.len() as u64).to_le_vec());
+                for &(g, k) in schedule {
+                    input.push(g);
+                    input.extend_from_slice(&(k as u64).to_le_vec());
+                }
+            }
+            None => input.push(0),
+        }
+        let hash = self.algorithms.h(&input);
+
+        let mut fingerprint = [0u8; 32];
+        let len = hash.len().min(32);
+        fingerprint[..len].copy_from_slice(&hash[..len]);
+        fingerprint
+    }
+
+    /// The smallest and the largest output length in bytes of `hash`, i.e.
+    /// `(1, n)`.
+    ///
+    /// `hash` truncates the output of H, so a larger `output_length` returns
+    /// `CatenaError::OutputTooLong`. Longer outputs require the key derivation
+    /// function
+    /// `generate_key`, which expands the output with `CounterExpand` to up to
+    /// `u16::MAX` bytes.
+    pub fn output_bounds(&self) -> (usize, usize) {
+        (1, self.n)
+    }
+
+    /// Whether the memory access pattern of the instance is independent of
+    /// the password, so it resists cache-timing attacks.
+    ///
+    /// This is the case if F is one of the graphs of this crate, which are
+    /// all password-independent, and Φ is not data-dependent. Γ only depends
+    /// on the public γ. For a graph which is not part of this crate no
+    /// guarantee can be made, so this is `false`.
+    #[doc(alias = "is_memory_access_oblivious")]
+    pub fn is_cta_resistant(&self) -> bool {
+        self.algorithms.graph_kind() != GraphKind::Unknown
+            && !self.algorithms.phi_is_data_dependent()
+    }
+
+    /// A summary of the security properties of the instance, derived from
+    /// `Algorithms::graph_kind`, `Algorithms::phi_is_data_dependent` and the
+    /// word size `k`. This only reflects the choice of components, not the
+    /// garlic.
+    pub fn resistance_profile(&self) -> ResistanceProfile {
+        let graph = self.algorithms.graph_kind();
+        let lambda_memory_hard = match graph {
+            GraphKind::BitReversal
+                | GraphKind::ShiftedBitReversal { .. }
+                | GraphKind::GrayReversal { .. } => true,
+            _ => false,
+        };
+
+        ResistanceProfile {
+            graph: graph,
+            memory_hard: lambda_memory_hard
+                || graph == GraphKind::DoubleButterfly,
+            lambda_memory_hard: lambda_memory_hard,
+            cta_resistant: self.is_cta_resistant(),
+            password_dependent_phi: self.algorithms.phi_is_data_dependent(),
+            asic_resistant: self.k >= 1024,
+        }
+    }
+
+    /// The number of bytes of memory needed to compute flap with the given
+    /// garlic. This is the state of `2^garlic` words of `k` bytes plus the
+    /// buffer of the same size used by the graph-based hash function F.
+    pub fn memory_bytes(&self, garlic: u8) -> usize {
+        2 * (1 << garlic) * self.k_for_garlic(garlic)
+    }
+
+    /// The number of calls of H' made by flap with the given garlic, for
+    /// cost modeling.
+    ///
+    /// These are the `2^g` calls initializing the state, `2^ceil(3g/4)` calls
+    /// of SaltMix if Γ is used, the calls of F and `2^g` calls of the Φ layer
+    /// if Φ is data-dependent. The first word of every layer of F is
+    /// computed with H, so each layer makes `2^g - 1` calls. BRH and GRH have
+    /// `λ` layers, DBH has `λ(2g - 1)` and the sponge of `sponge_graph` does
+    /// not use H'. The count is `None` for a graph which is not part of this
+    /// crate and if the calls of SaltMix do not fit into 64 bits.
+    pub fn h_prime_invocations(&self, garlic: u8) -> Option<u64> {
+        let g = garlic as u64;
+        let words = 1u64 << g;
+        let lambda = self.lambda as u64;
+
+        let layers = match self.algorithms.graph_kind() {
+            GraphKind::BitReversal
+                | GraphKind::ShiftedBitReversal { .. }
+                | GraphKind::GrayReversal { .. } => lambda,
+            GraphKind::DoubleButterfly => lambda * (2 * g).saturating_sub(1),
+            GraphKind::Sponge => 0,
+            GraphKind::Unknown => return None,
+        };
+
+        let mut invocations = words + layers * (words - 1);
+        if self.algorithms.uses_gamma() {
+            invocations += ::components::gamma::saltmix::saltmix_iterations(
+                garlic).ok()?;
+        }
+        if self.algorithms.phi_is_data_dependent() {
+            invocations += words;
+        }
+        Some(invocations)
+    }
+
+    /// The initial values `(v_(-2), v_(-1))` of flap computed from `x`.
+    ///
+    /// `2k/n` calls `H(i || x)` are concatenated and split into two halves
+    /// of `k` bytes. This only depends on `x`, `n` and `k` and allows to check
+    /// the expansion against reference vectors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `2k/n` exceeds the cap of `with_max_init_blocks`.
+    ///
+    /// ```
+    /// use catena::catena::Algorithms;
+    ///
+    /// let mut dragonfly = catena::default_instances::dragonfly::new();
+    /// dragonfly.g_low = 9;
+    /// dragonfly.g_high = 9;
+    ///
+    /// let x = b"x".to_vec();
+    /// let (vminus2, vminus1) = dragonfly.h_init_public(x.clone());
+    /// assert_eq!(vminus2.len(), dragonfly.k);
+    /// assert_eq!(vminus1.len(), dragonfly.k);
+    /// assert_eq!(vminus2, dragonfly.algorithms.h(&[&[0u8][..], &x].concat()));
+    /// assert_eq!(vminus1, dragonfly.algorithms.h(&[&[1u8][..], &x].concat()));
+    /// ```
+    pub fn h_init_public(&mut self, x: Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+        let k: usize;
+        {
+            k = self.k;
+        }
+        self.h_init(x, k).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Password-scrambling function of Catena which returns an error if the
+    /// state can not be allocated.
+    ///
+    /// Returns `CatenaError::InvalidGarlic` if `g_low` exceeds `g_high`.
+    fn try_catena (
+        &mut self,
+        pwd: &Vec<u8>,
+        t: &Vec<u8>,
+        s: &Vec<u8>,
+        g_low: u8,
+        g_high: u8,
+        m: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        if g_low > g_high {
+            return Err(CatenaError::InvalidGarlic);
+        }
+        self.try_catena_garlics(pwd, t, s, g_low, g_low..=g_high, m, gamma)
+    }
+
+    /// Password-scrambling function of Catena which runs flap for each garlic
+    /// of `schedule` in order. The first flap uses `(g + 1) / 2` for the
+    /// smallest garlic `g` of the schedule.
+    ///
+    /// Returns `CatenaError::InvalidGarlic` if the schedule is empty.
+    fn try_catena_schedule (
+        &mut self,
+        pwd: &Vec<u8>,
+        t: &Vec<u8>,
+        s: &Vec<u8>,
+        schedule: &[u8],
+        m: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        let g_min = *schedule.iter().min().ok_or(CatenaError::InvalidGarlic)?;
+        self.try_catena_garlics(
+            pwd, t, s, g_min, schedule.iter().cloned(), m, gamma)
+    }
+
+    /// The loop of Catena over `garlics`, where `g_min` is the smallest of
+    /// them.
+    fn try_catena_garlics<I: Iterator<Item = u8>> (
+        &mut self,
+        pwd: &Vec<u8>,
+        t: &Vec<u8>,
+        s: &Vec<u8>,
+        g_min: u8,
+        garlics: I,
+        m: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        let n: usize;
+
+        {
+            n = self.n;
+        }
+
+        self.check_gamma(gamma)?;
+
+        // the seed of SaltMix is only kept for this computation
+        let mut cache = SeedCache::default();
+        let mut x = self.algorithms.h(&initial_input(t, pwd, s));
+        x = self.try_flap_cached((g_min + 1) / 2, x, &gamma, &mut cache)?;
+        x = self.algorithms.h(&x);
+        let mut garlics = garlics.peekable();
+        while let Some(g) = garlics.next() {
+            if x.len() < n {
+                x = ::helpers::vectors::zero_padding(x, n - m as usize);
+            }
+            x = self.try_flap_cached(g, x, &gamma, &mut cache)?;
+            // the optional parts of the last hash are empty otherwise
+            let last = garlics.peek().is_none();
+            let salt_part = if last && self.options.bind_salt_in_final {
+                s.clone()
+            } else {
+                Vec::new()
+            };
+            let garlic_part = if last && self.options.bind_garlic {
+                vec![b'g', g]
+            } else {
+                Vec::new()
+            };
+            x = self.h4(&g.to_le_vec(), &x, &salt_part, &garlic_part);
+            x = Truncate.finalize(&self.algorithms, x, m as usize);
+        }
+        Ok(x)
+    }
+
+    /// Returns `CatenaError::EmptyGamma` if γ is empty, but required, see
+    /// `with_require_gamma`.
+    fn check_gamma(&self, gamma: &Vec<u8>) -> Result<(), CatenaError> {
+        if self.options.require_gamma && gamma.is_empty()
+            && self.algorithms.uses_gamma() {
+            return Err(CatenaError::EmptyGamma);
+        }
+        Ok(())
+    }
+
+    /// Flap function of Catena
+    #[cfg(any(test, feature = "debug-trace"))]
+    fn flap(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Vec<u8> {
+        self.try_flap(garlic, x, gamma)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Flap function of Catena which returns an error if the state can not be
+    /// allocated.
+    ///
+    /// The state is reserved here, the graph layer F reserves its second
+    /// buffer of the same size itself and returns the error of F.
+    fn try_flap(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.try_flap_cached(garlic, x, gamma, &mut SeedCache::default())
+    }
+
+    /// `try_flap` with the seed of SaltMix taken from `cache`, see
+    /// `Algorithms::gamma_cached`.
+    fn try_flap_cached(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>,
+        cache: &mut SeedCache
+    ) -> Result<Vec<u8>, CatenaError> {
+        let k = self.k_for_garlic(garlic);
+
+        let v = self.try_flap_state(garlic, x, gamma, cache)?;
+
+        // only the last state word is used
+        Ok(v.get_word(k, v.len() / k - 1))
+    }
+
+    /// The state of `2^garlic` words of flap after the graph layer F, before
+    /// Φ.
+    fn try_flap_state_f(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>,
+        cache: &mut SeedCache
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        let n: usize;
+
+        {
+            n = self.n;
+        }
+        let k = self.k_for_garlic(garlic);
+
+        let g: usize = match 1usize.checked_shl(garlic as u32) {
+            Some(g) => g,
+            None => return Err(
+                CatenaError::AllocationFailed { bytes: usize::max_value() }),
+        };
+        let state_bytes = (g + 2).checked_mul(k).unwrap_or(usize::max_value());
+
+        let (vminus2, vminus1) = self.h_init(x, k)?;
+
+        let mut v: Vec<u8> = Vec::new();
+        ::helpers::vectors::try_reserve(&mut v, state_bytes)?;
+
+        self.algorithms.reset_h_prime();
+
+        // v_(-2) and v_(-1) are only needed to compute the first two words
+        let mut previous2 = vminus2;
+        let mut previous1 = vminus1;
+        for _ in 0..g {
+            let state_i = self.h_prime2(previous1.clone(), previous2);
+            if state_i.len() != k {
+                return Err(CatenaError::OutputLengthMismatch {
+                    function: "H'",
+                    expected: k,
+                    actual: state_i.len(),
+                });
+            }
+            v.extend_from_slice(&state_i);
+            previous2 = previous1;
+            previous1 = state_i;
+        }
+
+        self.algorithms.reset_h_prime();
+        v = self.algorithms.gamma_cached(garlic, v, gamma, k, cache);
+        self.algorithms.reset_h_prime();
+        v = self.algorithms.f(&garlic, &mut v, self.lambda, n, k)?;
+        self.algorithms.reset_h_prime();
+
+        Ok(v)
+    }
+
+    /// The state of `2^garlic` words after the last layer Φ of flap.
+    fn try_flap_state(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>,
+        cache: &mut SeedCache
+    ) -> Result<Vec<u8>, CatenaError> {
+        let k = self.k_for_garlic(garlic);
+
+        let v = self.try_flap_state_f(garlic, x, gamma, cache)?;
+
+        // last state word as mu
+        let mu = v.get_word(k, v.len() / k - 1);
+        Ok(self.algorithms.phi(garlic, v, &mu, k))
+    }
+
+    fn h_init (
+        &mut self,
+        x: Vec<u8>,
+        k: usize
+    ) -> Result<(Vec<u8>, Vec<u8>), CatenaError> {
+        let n: usize;
+        let max: usize;
+
+        {
+            n = self.n;
+            max = self.options.max_init_blocks
+                .unwrap_or(DEFAULT_MAX_INIT_BLOCKS);
+        }
+
+        let l: usize = 2 * k / n;
+        if l > max {
+            return Err(CatenaError::InitialValueTooLarge {
+                blocks: l,
+                max: max,
+            });
+        }
+        let mut w: Vec<u8> = Vec::new();
+        for i in 0..l {
+            w = [&w[..],
+                 &self.h2(&vec![i as u8], &x)].concat();
+        }
+        let vminus2 = [&w[0..(w.len() / 2)]].concat();
+        let vminus1 = [&w[(w.len() / 2)..]].concat();
+
+        Ok((vminus2,vminus1))
+    }
+
+    /// Compute the tweak for a given domain.
+    ///
+    /// # Inputs
+    ///
+    /// - mode: The domain for which Catena is used.
+    /// - output_len: The output length of the final hash.
+    /// - salt_len: The length of the salt.
+    /// - a_data: Associated data.
+    fn compute_tweak(
+        &self,
+        mode: Domain,
+        output_len: u16,
+        salt_len: u16,
+        a_data: &Vec<u8>)
+    -> Vec<u8> {
+
+        let d: u8;
+        match mode {
+            Domain::PasswordScrambling => d = 0,
+            Domain::KeyDerivation => d = 1,
+            Domain::ProofOfWork => d = 2,
+        }
+
+        // compute H(V)
+        let hv = self.algorithms.h(&self.tweak_vid());
+
+        // compute H(AD)
+        let had = match self.options.ad_hash {
+            Some(ad_hash) => ad_hash(a_data),
+            None => self.algorithms.h(a_data),
+        };
+
+        let mut tweak = [&hv[..], &[d, self.lambda], &output_len.to_le_vec()[..],
+        &salt_len.to_le_vec()[..], &had[..]].concat();
+
+        if let Some(h_id) = self.options.h_id {
+            tweak.push(h_id);
+        }
+
+        tweak
+    }
+
+    /// The version ID hashed into the tweak. The binding options append
+    /// `+s` and `+g` so that their outputs are separated from the
+    /// specification.
+    fn tweak_vid(&self) -> Vec<u8> {
+        let mut vid = self.vid.as_bytes().to_vec();
+        if self.options.bind_salt_in_final {
+            vid.extend_from_slice(b"+s");
+        }
+        if self.options.bind_garlic {
+            vid.extend_from_slice(b"+g");
+        }
+        vid
+    }
+
+    /// Compute h(a || b)
+    fn h2(&mut self, a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
+        let input = [&a[..], &b[..]].concat();
+        self.algorithms.h(&input)
+    }
+
+    /// Compute h(a || b || c || d)
+    fn h4(&mut self, a: &Vec<u8>, b: &Vec<u8>, c: &Vec<u8>, d: &Vec<u8>)
+        -> Vec<u8> {
+        let input = [&a[..], &b[..], &c[..], &d[..]].concat();
+        self.algorithms.h(&input)
+    }
+
+    /// Compute h_prime(a || b)
+    fn h_prime2(&mut self, a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+        let input = [&a[..], &b[..]].concat();
+        self.algorithms.h_prime(&input)
+    }
+
+    /// Key-Derivation Function Catena-KG
+    fn key_generation (
+        &mut self,
+        pwd: Vec<u8>,
+        tweak: Vec<u8>,
+        salt: Vec<u8>,
+        g_low: u8,
+        g_high: u8,
+        m: u16,
+        gamma: Vec<u8>,
+        key_size: u16,
+        key_identifier: Vec<u8>
+    ) -> Result<Vec<u8>, CatenaError> {
+        let n: usize;
+
+        {
+            n = self.n;
+        }
+
+        let x = self.try_catena(&pwd, &tweak, &salt, g_low, g_high, m, &gamma)?;
+
+        let mut expansion = CounterExpand {
+            key_identifier: key_identifier,
+            n: n,
+        };
+        Ok(expansion.finalize(&self.algorithms, x, key_size as usize))
+    }
+
+    /// Compute Keystream for keyed hashing
+    fn compute_keystream(
+        &mut self,
+        server_key: &Vec<u8>,
+        user_id: &Vec<u8>,
+        g_high: u8,
+        output_length: usize
+    ) -> Vec<u8> {
+            let mut keystream = self.h4(
+                server_key,
+                user_id,
+                &g_high.to_le_vec(),
+                server_key);
+
+            keystream.truncate(output_length);
+
+            keystream
+    }
+}
+
+/// The algorithms of an instance with H replaced, see `Catena::hash_with_h`.
+struct WithH<'a, T: Algorithms + 'a> {
+    inner: &'a mut T,
+    h: &'a dyn Fn(&[u8]) -> Vec<u8>,
+}
+
+impl<'a, T: Algorithms> Algorithms for WithH<'a, T> {
+    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+        (self.h)(x)
+    }
+
+    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+        self.inner.h_prime(x)
+    }
+
+    fn reset_h_prime(&mut self) {
+        self.inner.reset_h_prime();
+    }
+
+    fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+        -> Vec<u8> {
+        self.inner.gamma(garlic, state, gamma, k)
+    }
+
+    fn gamma_cached(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                    k: usize, cache: &mut SeedCache) -> Vec<u8> {
+        self.inner.gamma_cached(garlic, state, gamma, k, cache)
+    }
+
+    fn uses_gamma(&self) -> bool {
+        self.inner.uses_gamma()
+    }
+
+    fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, CatenaError> {
+        self.inner.f(garlic, state, lambda, n, k)
+    }
+
+    fn graph_kind(&self) -> GraphKind {
+        self.inner.graph_kind()
+    }
+
+    fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+        self.inner.phi(garlic, state, mu, k)
+    }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        self.inner.phi_is_data_dependent()
+    }
+}
+
+/// An object-safe interface to Catena instances, so that instances with
+/// different algorithms can be used as `Box<dyn DynCatena>`.
+pub trait DynCatena {
+    /// Password scrambling function of Catena, see `Catena::hash`.
+    fn hash(
+        &mut self,
+        pwd: &[u8],
+        salt: &[u8],
+        associated_data: &[u8],
+        output_length: u16,
+        gamma: &[u8]
+    ) -> Result<PasswordHash, CatenaError>;
+
+    /// The version ID of the instance.
+    fn vid(&self) -> &str;
+
+    /// The minimum and maximum garlic.
+    fn garlic(&self) -> (u8, u8);
+
+    /// Set the minimum and maximum garlic.
+    fn set_garlic(&mut self, g_low: u8, g_high: u8);
+
+    /// The kind of graph used by F, see `Algorithms::graph_kind`.
+    fn graph_kind(&self) -> GraphKind;
+
+    /// Whether the instance resists cache-timing attacks, see
+    /// `Catena::is_cta_resistant`.
+    fn is_cta_resistant(&self) -> bool;
+
+    /// Check a password against a stored hash, see `Catena::verify`.
+    fn verify(
+        &mut self,
+        pwd: &[u8],
+        salt: &[u8],
+        associated_data: &[u8],
+        gamma: &[u8],
+        hash: &PasswordHash
+    ) -> Result<bool, CatenaError>;
+
+    /// Check whether a stored header was produced by this instance, see
+    /// `Catena::matches_header`.
+    fn matches_header(&self, header: &[u8]) -> bool;
+
+    /// The parameters of the instance, see `Catena::params`.
+    fn params(&self) -> CatenaParams;
+
+    /// The memory needed to compute flap with the given garlic, see
+    /// `Catena::memory_bytes`.
+    fn memory_bytes(&self, garlic: u8) -> usize;
+}
+
+impl<T: Algorithms> DynCatena for Catena <T> {
+    fn hash(
+        &mut self,
+        pwd: &[u8],
+        salt: &[u8],
+        associated_data: &[u8],
+        output_length: u16,
+        gamma: &[u8]
+    ) -> Result<PasswordHash, CatenaError> {
+        Catena::hash(
+            self,
+            &pwd.to_vec(),
+            &salt.to_vec(),
+            &associated_data.to_vec(),
+            output_length,
+            &gamma.to_vec())
+    }
+
+    fn vid(&self) -> &str {
+        self.vid
+    }
+
+    fn garlic(&self) -> (u8, u8) {
+        (self.g_low, self.g_high)
+    }
+
+    fn set_garlic(&mut self, g_low: u8, g_high: u8) {
+        self.g_low = g_low;
+        self.g_high = g_high;
+    }
+
+    fn graph_kind(&self) -> GraphKind {
+        self.algorithms.graph_kind()
+    }
+
+    fn is_cta_resistant(&self) -> bool {
+        Catena::is_cta_resistant(self)
+    }
+
+    fn verify(
+        &mut self,
+        pwd: &[u8],
+        salt: &[u8],
+        associated_data: &[u8],
+        gamma: &[u8],
+        hash: &PasswordHash
+    ) -> Result<bool, CatenaError> {
+        Catena::verify(
+            self,
+            &pwd.to_vec(),
+            &salt.to_vec(),
+            &associated_data.to_vec(),
+            &gamma.to_vec(),
+            hash)
+    }
+
+    fn matches_header(&self, header: &[u8]) -> bool {
+        Catena::matches_header(self, header)
+    }
+
+    fn params(&self) -> CatenaParams {
+        Catena::params(self)
+    }
+
+    fn memory_bytes(&self, garlic: u8) -> usize {
+        Catena::memory_bytes(self, garlic)
+    }
+}
+
+/// The parameters of a Catena instance without its algorithms, as returned
+/// by `Catena::params`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CatenaParams {
+    /// The version ID.
+    pub vid: &'static str,
+    /// Output length of H in bytes.
+    pub n: usize,
+    /// Output length of H' in bytes.
+    pub k: usize,
+    /// The minimum garlic.
+    pub g_low: u8,
+    /// The maximum garlic.
+    pub g_high: u8,
+    /// The depth of the graph.
+    pub lambda: u8,
+}
+
+/// A Catena instance followed by Catena-KG of a second instance.
+///
+/// `hash` computes the password hash with `hasher` and derives a key from it
+/// with `kdf`, the hash taking the place of the password. Each instance can
+/// be configured for its purpose, e.g. a memory-hard `hasher` at a high
+/// garlic and a cheap `kdf`.
+///
+/// This separates authentication from key derivation: passwords are
+/// verified against hashes of `hasher`, while keys come from `kdf`, whose
+/// tweak uses the key-derivation domain. A key never equals a password hash,
+/// and a leaked key does not reveal the hash it was derived from. The
+/// intermediate hash is as secret as the key, so the verifier has to be
+/// computed with a different salt, not stored from this chain.
+#[derive(Clone, Debug)]
+pub struct ChainedCatena<T: Algorithms, K: Algorithms> {
+    /// The instance hashing the password.
+    pub hasher: Catena<T>,
+    /// The instance deriving the key from the hash.
+    pub kdf: Catena<K>,
+}
+
+impl<T: Algorithms, K: Algorithms> ChainedCatena<T, K> {
+    /// Hash `pwd` with `hasher` and derive a key of `key_size` bytes from the
+    /// hash with Catena-KG of `kdf`.
+    ///
+    /// Both instances use the same salt, associated data, output length and
+    /// γ. The key is `kdf.generate_key(hasher.hash(pwd, ..), ..)`, the
+    /// errors of both are returned.
+    pub fn hash (
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>,
+        key_size: u16,
+        key_identifier: &Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        let hash = self.hasher.hash(
+            pwd, salt, associated_data, output_length, gamma)?;
+        self.kdf.generate_key(
+            hash.0,
+            associated_data,
+            salt.clone(),
+            output_length,
+            gamma.clone(),
+            key_size,
+            key_identifier.clone())
+    }
+}
+
+/// The cost of computing Catena derived from the parameters, as reported by
+/// `Catena::hash_with_cost`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashCost {
+    /// The memory of the largest flap, see `Catena::memory_bytes`. This is
+    /// `memory_bytes(g_high)` unless the word size depends on the garlic.
+    pub memory_bytes: usize,
+    /// The lowest garlic.
+    pub garlic_low: u8,
+    /// The highest garlic.
+    pub garlic_high: u8,
+    /// The number of calls of H' of all flaps, see
+    /// `Catena::h_prime_invocations`. `None` if F is not part of this crate.
+    pub h_prime_calls: Option<u64>,
+}
+
+/// The cost of computing Catena, as reported by `Catena::profile`.
+#[derive(Clone, Copy, Debug)]
+pub struct Profile {
+    /// The time needed to compute the hash.
+    pub elapsed: Duration,
+    /// The memory needed for the state, see `Catena::memory_bytes`.
+    pub peak_bytes: usize,
+    /// The number of calls of H, including those inside of Γ, F and Φ.
+    pub h_calls: u64,
+    /// The number of calls of H', including those inside of Γ, F and Φ. This
+    /// is the sum of `Catena::h_prime_invocations` over both flaps.
+    pub h_prime_calls: u64,
+}
+
+impl<T: Algorithms + Clone> Catena <T> {
+    /// Compute Catena once with `g_low = g_high = garlic` and report the cost
+    /// instead of the output.
+    ///
+    /// The calls are counted with `Recomposed`, so the calls of H and H'
+    /// inside of Γ, F and Φ are included, except for those of an F with
+    /// `GraphKind::Unknown`. The instance itself is not modified. The errors
+    /// are the same as for `hash`.
+    pub fn profile(
+        &mut self,
+        garlic: u8,
+        pwd: &Vec<u8>,
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Result<Profile, CatenaError> {
+        let mut counting = Catena {
+            algorithms: Recomposed::new(self.algorithms.clone()),
+            vid: self.vid,
+            n: self.n,
+            k: self.k,
+            g_low: garlic,
+            g_high: garlic,
+            lambda: self.lambda,
+            options: self.options,
+        };
+        let n = self.n as u16;
+
+        let start = Instant::now();
+        let _ = counting.hash(pwd, salt, associated_data, n, gamma)?;
+        let elapsed = start.elapsed();
+
+        let counts = counting.algorithms.counts();
+
+        Ok(Profile {
+            elapsed: elapsed,
+            peak_bytes: self.memory_bytes(garlic),
+            h_calls: counts.h,
+            h_prime_calls: counts.h_prime,
+        })
+    }
+}
+
+impl<T: Algorithms> Catena <T> {
+    /// Wrap the algorithms in `Ablated`, so Γ, F and Φ can be disabled with
+    /// `disable_gamma`, `disable_f` and `disable_phi`.
+    ///
+    /// Without disabled components the output is unchanged. Disabling a
+    /// component changes all outputs and is only meant for studying the
+    /// contribution of the components, never for hashing passwords.
+    pub fn ablated(self) -> Catena<Ablated<T>> {
+        Catena {
+            algorithms: Ablated::new(self.algorithms),
+            vid: self.vid,
+            n: self.n,
+            k: self.k,
+            g_low: self.g_low,
+            g_high: self.g_high,
+            lambda: self.lambda,
+            options: self.options,
+        }
+    }
+}
+
+impl<T: Algorithms> Catena <Ablated<T>> {
+    /// Replace Γ by the identity.
+    pub fn disable_gamma(mut self) -> Catena<Ablated<T>> {
+        self.algorithms.disable_gamma = true;
+        self
+    }
+
+    /// Replace F by the identity.
+    pub fn disable_f(mut self) -> Catena<Ablated<T>> {
+        self.algorithms.disable_f = true;
+        self
+    }
+
+    /// Replace Φ by the identity.
+    pub fn disable_phi(mut self) -> Catena<Ablated<T>> {
+        self.algorithms.disable_phi = true;
+        self
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Algorithms + Sync> Catena <T> {
+    /// Key-Derivation function Catena-KG with a parallel key expansion.
+    ///
+    /// The output is the same as that of `generate_key`. Only the expansion of
+    /// the output of Catena to `key_size` bytes is spread across threads, which
+    /// pays off for large keys. The call of Catena itself stays serial. The
+    /// errors are the same as for `generate_key`.
+    pub fn generate_key_parallel (
+        &mut self,
+        pwd: Vec<u8>,
+        associated_data: &Vec<u8>,
+        salt: Vec<u8>,
+        output_length: u16,
+        gamma: Vec<u8>,
+        key_size: u16,
+        key_identifier: Vec<u8>
+    ) -> Result<DerivedKey, CatenaError> {
+        self.check_parameters(&salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::KeyDerivation,
+            output_length,
+            salt.len() as u16,
+            associated_data);
+
+        let g_low: u8;
+        let g_high: u8;
+        let n: usize;
+
+        {
+            g_low = self.g_low;
+            g_high = self.g_high;
+            n = self.n;
+        }
+
+        let x = self.try_catena(
+            &pwd, &tweak, &salt, g_low, g_high, output_length, &gamma)?;
+
+        let blocks = (key_size as usize + n - 1) / n;
+        let indices: Vec<u16> = (1..blocks + 1).map(|i| i as u16).collect();
+        let threads = threads(&self.options);
+        let chunk_size = ((indices.len() + threads - 1) / threads).max(1);
+
+        let algorithms = &self.algorithms;
+        let key_identifier = &key_identifier;
+        let key_size_bytes = key_size.to_le_vec();
+        let key_size_bytes = &key_size_bytes;
+        let x = &x;
+
+        let mut k: Vec<u8> = Vec::new();
+        thread::scope(|scope| {
+            let handles: Vec<_> = indices.chunks(chunk_size).map(|chunk| {
+                scope.spawn(move || {
+                    chunk.iter().flat_map(|i| {
+                        algorithms.h(&[
+                            &i.to_le_vec()[..],
+                            &key_identifier[..],
+                            &key_size_bytes[..],
+                            &x[..]].concat())
+                    }).collect::<Vec<u8>>()
+                })
+            }).collect();
+
+            for handle in handles {
+                k.append(&mut handle.join().unwrap());
+            }
+        });
+
+        k.truncate(key_size as usize);
+        Ok(DerivedKey(k))
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Algorithms + Clone + Send> Catena <T> {
+    /// Password scrambling function of Catena for a batch of passwords with
+    /// the passwords spread across threads.
+    ///
+    /// The output is the same as that of `hash_batch`. Each thread computes
+    /// a consecutive chunk of the passwords with its own clone of the
+    /// instance, see `set_parallelism` for the number of threads. The errors
+    /// are the same as for `hash_batch`.
+    pub fn hash_batch_parallel (
+        &mut self,
+        pwds: &[&[u8]],
+        salt: &Vec<u8>,
+        associated_data: &Vec<u8>,
+        output_length: u16,
+        gamma: &Vec<u8>
+    ) -> Result<Vec<PasswordHash>, CatenaError> {
+        self.check_parameters(salt, output_length)?;
+
+        let tweak = self.compute_tweak(
+            Domain::PasswordScrambling,
+            output_length, salt.len() as u16,
+            &associated_data);
+
+        let threads = threads(&self.options);
+        let chunk_size = ((pwds.len() + threads - 1) / threads).max(1);
+        let tweak = &tweak;
+
+        let mut chunks: Vec<Result<Vec<PasswordHash>, CatenaError>> =
+            Vec::new();
+        thread::scope(|scope| {
+            let handles: Vec<_> = pwds.chunks(chunk_size).map(|chunk| {
+                let mut instance = self.clone();
+                scope.spawn(move || {
+                    chunk.iter()
+                        .map(|pwd| instance.hash_with_tweak(
+                            pwd, tweak, salt, output_length, gamma)
+                             .map(PasswordHash))
+                        .collect::<Result<Vec<PasswordHash>, CatenaError>>()
+                })
+            }).collect();
+
+            for handle in handles {
+                chunks.push(handle.join().unwrap());
+            }
+        });
+
+        let mut hashes: Vec<PasswordHash> = Vec::with_capacity(pwds.len());
+        for chunk in chunks {
+            hashes.append(&mut chunk?);
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(feature = "debug-trace")]
+impl<T: Algorithms> Catena <T> {
+    /// Find the first garlic at which flap differs from a reference.
+    ///
+    /// flap is computed on `x` and `gamma` for each garlic of `range` in
+    /// ascending order and compared with `reference(garlic)`, e.g. the flap
+    /// of a known-good instance. Returns `None` if all outputs agree. This
+    /// helps to pinpoint the garlic at which a custom component breaks.
+    pub fn first_divergent_garlic(
+        &mut self,
+        x: &Vec<u8>,
+        gamma: &Vec<u8>,
+        reference: &dyn Fn(u8) -> Vec<u8>,
+        range: Range<u8>
+    ) -> Option<u8> {
+        for garlic in range {
+            if self.flap(garlic, x.clone(), gamma) != reference(garlic) {
+                return Some(garlic);
+            }
+        }
+        None
+    }
+
+    /// All `2^garlic` words of the state of flap after the layer Φ, before
+    /// the last word is selected as the output. The last word equals the
+    /// output of flap. This allows to compare the full state with a
+    /// reference implementation.
+    pub fn flap_full_state(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Vec<Vec<u8>> {
+        let k = self.k_for_garlic(garlic);
+
+        let v = self.try_flap_state(
+            garlic, x, gamma, &mut SeedCache::default())
+            .unwrap_or_else(|e| panic!("{}", e));
+        v.chunks(k).map(|word| word.to_vec()).collect()
+    }
+
+    /// Check the state of flap after F for a broken H' or F.
+    ///
+    /// Returns `CatenaError::SuspiciousState` if more than half of the words
+    /// equal their predecessor, e.g. because H' ignores most of its input or
+    /// is not reset between the stages. A correct H' produces distinct words
+    /// with overwhelming probability. This is a diagnostic for authors of
+    /// custom variants, `hash` and the other functions never run it, so the
+    /// feature does not change their results.
+    pub fn check_flap_state(
+        &mut self,
+        garlic: u8,
+        x: Vec<u8>,
+        gamma: &Vec<u8>
+    ) -> Result<(), CatenaError> {
+        let k = self.k_for_garlic(garlic);
+
+        let v = self.try_flap_state_f(
+            garlic, x, gamma, &mut SeedCache::default())?;
+        if is_suspicious_state(&v, k) {
+            return Err(CatenaError::SuspiciousState);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::HexRepresentation;
+    use bytes::Bytes;
+    use helpers::files::JSONTests;
+    use helpers::fixtures::test_inputs;
+    use super::*;
+
+    fn h_init_test_from_json<T: Algorithms>(
+        mut catena: ::catena::Catena<T>, test_file: String)
+    {
+        let json = ::helpers::files::open_json(test_file);
+        let unwrapped_json = json.as_ref().unwrap();
+        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+
+        for i in 0..number_of_tests {
+            let ref inputs = unwrapped_json[i]["inputs"];
+            let ref outputs = unwrapped_json[i]["outputs"]["v"];
+            let expected_1_tmp = outputs[0].to_string();
+            let expected_2_tmp = outputs[1].to_string();
+            let expected_1 = expected_1_tmp.trim_matches('\"');
+            let expected_2 = expected_2_tmp.trim_matches('\"');
+
+            let x = inputs.parse_hex("x");
+
+            let output = catena.h_init(x, catena.k).unwrap();
+
+            assert_eq!(output.0.to_hex_string(),
+                       expected_1.to_string().to_be_vec().to_hex_string());
+            assert_eq!(output.1.to_hex_string(),
+                       expected_2.to_string().to_be_vec().to_hex_string());
+        }
+    }
+
+    #[test]
+    fn h_init_test() {
+        let catena_dff = ::default_instances::dragonfly_full::new();
+        h_init_test_from_json(
+            catena_dff, "test/test_vectors/hInitAnyFull.json".to_string());
+    }
+
+    fn compute_tweak_test_from_json<T: Algorithms>(
+        catena: ::catena::Catena<T>, file: &str)
+    {
+        let json = ::helpers::files::open_json(file.to_string());
+        let unwrapped_json = json.as_ref().unwrap();
+        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+
+        for i in 0..number_of_tests {
+            let ref inputs = unwrapped_json[i]["inputs"];
+
+            let out_length = inputs.parse_u16("outLen");
+            let salt_length = inputs.parse_u16("sLen");
+            let ad = inputs.parse_utf8string("aData");
+            let domain = inputs.parse_u64("d");
+
+            let ref outputs = unwrapped_json[i]["outputs"];
+            let expected = outputs.parse_string("res");
+
+            let mode: Domain;
+            if domain == 0 {
+                mode = Domain::PasswordScrambling;
+            } else if domain == 1 {
+                mode = Domain::KeyDerivation;
+            } else {
+                mode = Domain::ProofOfWork;
+            }
+
+            let output = catena.compute_tweak(
+                mode,
+                out_length,
+                salt_length,
+                &ad);
+
+            assert_eq!(output.to_hex_string(),
+                       expected);
+        }
+    }
+
+    #[test]
+    fn compute_tweak_test_dragonflyfull() {
+        let catena = ::default_instances::dragonfly_full::new();
+        compute_tweak_test_from_json(
+            catena, "test/test_vectors/tweakDragonflyFull.json");
+    }
+
+    #[test]
+    fn compute_tweak_test_dragonfly() {
+        let catena = ::default_instances::dragonfly::new();
+        compute_tweak_test_from_json(
+            catena, "test/test_vectors/tweakDragonfly.json");
+    }
+
+    #[test]
+    fn compute_tweak_test_butterfly() {
+        let catena = ::default_instances::butterfly::new();
+        compute_tweak_test_from_json(
+            catena, "test/test_vectors/tweakButterfly.json");
+    }
+
+    #[test]
+    fn compute_tweak_test_butterflyfull() {
+        let catena = ::default_instances::butterfly_full::new();
+        compute_tweak_test_from_json(
+            catena, "test/test_vectors/tweakButterflyFull.json");
+    }
+
+    fn catena_test_from_json<T: Algorithms>(
+        mut catena: ::catena::Catena<T>, file: &str)
+    {
+        let vectors = ::conformance::load_vectors(file)
+            .unwrap_or_else(|e| panic!("{}: {}", file, e));
+
+        for vector in vectors {
+            assert_eq!(
+                catena.hash(
+                    &vector.pwd,
+                    &vector.salt,
+                    &vector.associated_data,
+                    vector.output_length,
+                    &vector.gamma).unwrap().0,
+                vector.res);
+        }
+    }
+
+    #[test]
+    #[cfg(feature="fulltest")]
+    fn butterfly_test_from_json() {
+        let test_catena = ::default_instances::butterfly::new();
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaButterfly.json");
+    }
+
+    #[test]
+    fn butterfly_reduced_test_from_json() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaButterflyReduced.json");
+    }
+
+    #[test]
+    #[cfg(feature="fulltest")]
+    fn butterflyfull_test_from_json() {
+        let test_catena = ::default_instances::butterfly_full::new();
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaButterflyFull.json");
+    }
+
+    #[test]
+    fn butterflyfull_reduced_test_from_json() {
+        let mut test_catena = ::default_instances::butterfly_full::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaButterflyFullReduced.json");
+    }
+
+    #[test]
+    #[cfg(feature="fulltest")]
+    fn dragonfly_test_from_json() {
+        let test_catena = ::default_instances::dragonfly::new();
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaDragonfly.json");
+    }
+
+    #[test]
+    fn dragonfly_reduced_test_from_json() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        test_catena.g_low = 14;
+        test_catena.g_high = 14;
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaDragonflyReduced.json");
+    }
+
+    #[test]
+    #[cfg(feature="fulltest")]
+    fn dragonflyfull_test_from_json() {
+        let test_catena = ::default_instances::dragonfly_full::new();
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaDragonflyFull.json");
+    }
+
+    #[test]
+    fn dragonflyfull_reduced_test_from_json() {
+        let mut test_catena = ::default_instances::dragonfly_full::new();
+        test_catena.g_low = 14;
+        test_catena.g_high = 14;
+        catena_test_from_json(
+            test_catena, "test/test_vectors/catenaDragonflyFullReduced.json");
+    }
+
+    #[test]
+    fn hash_ad_parts_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+
+        let pwd = b"password".to_vec();
+        let salt = b"salt".to_vec();
+        let gamma = b"gamma".to_vec();
+        let ad = b"header-1header-2header-3".to_vec();
+        let ad_parts: [&[u8]; 3] = [b"header-1", b"header-2", b"header-3"];
+
+        assert_eq!(
+            test_catena.hash_ad_parts(&pwd, &salt, &ad_parts, 64, &gamma),
+            test_catena.hash(&pwd, &salt, &ad, 64, &gamma));
+    }
 
-            (pwd.to_vec(),
-             salt.to_vec(),
-             associated_data.to_vec(),
-             gamma.to_vec(),
-             output_len,
-             hash,
-             p,
-             mode)
-        } else if mode == 1 {
-            let bin_len =
-                (format!("{:b}", pwd[0])).len() + ((pwd.len() -1 ) * 8);
-            if bin_len != p {
-                panic!("pwd is not p bit long");
-            }
+    #[test]
+    fn hash_typed_test() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-            let empty_pwd: Vec<u8> = Vec::new();
-            (empty_pwd, salt.to_vec(), associated_data.to_vec(), gamma.to_vec(), output_len, hash, p, mode)
-        } else {
-            panic!("Invalid mode for proof of work");
-        }
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let typed = test_catena.hash_typed(
+            &pwd, &Salt::from(salt.clone()), &ad, 64, &Gamma::from(gamma.clone()))
+            .unwrap();
+        assert_eq!(typed, test_catena.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap());
+        assert_eq!(typed.to_hex_string(), typed.0.to_hex_string());
+        assert!(test_catena.verify(&pwd, &salt, &ad, &gamma, &typed)
+                .unwrap());
+
+        // salt and gamma have different roles
+        let swapped = test_catena.hash_typed(
+            &pwd, &Salt::from(gamma.clone()), &ad, 64, &Gamma::from(salt.clone()))
+            .unwrap();
+        assert_ne!(typed, swapped);
+
+        // a key is a different type, see the example of `DerivedKey`
+        let key = test_catena.generate_key(
+            pwd, &ad, salt, 64, gamma, 64, b"id".to_vec()).unwrap();
+        assert_eq!(key.as_bytes().len(), 64);
+        assert_eq!(key.to_hex_string(), key.0.to_hex_string());
+        assert_eq!(format!("{:?}", key), "DerivedKey(64 bytes)");
     }
 
-    /// Client side computation of proof of work
-    ///
-    /// # Inputs
-    ///
-    /// - pwd: the password to be hashed
-    /// - salt: the salt value
-    /// - associated_data: associated data of the user
-    /// - gamma: a public and password-independent input
-    /// - output_length: length of the final hash in bytes
-    /// - hash: hash to check if the computed password or salt is correct
-    /// - p: number of secret bits <= 64
-    /// - mode:
-    ///     - 0: salt mode
-    ///     - 1: password mode
-
-    pub fn proof_of_work_client(
-        &mut self,
-        pwd: Vec<u8>,
-        salt: Vec<u8>,
-        associated_data: Vec<u8>,
-        gamma: Vec<u8>,
-        output_len: u16,
-        hash: Vec<u8>,
-        p: usize,
-        mode: u8
-    ) -> Vec<u8> {
+    #[test]
+    fn encode_header_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        assert_eq!(dragonfly.encode_header(),
+                   Ok(b"catena-dragonfly$g=21,l=2,n=64".to_vec()));
+
+        dragonfly.g_low = 18;
+        assert_eq!(dragonfly.encode_header(),
+                   Ok(b"catena-dragonfly$g=21,gl=18,l=2,n=64".to_vec()));
+
+        let bound = dragonfly.with_bind_garlic(true);
+        assert_eq!(bound.encode_header(),
+                   Err(CatenaError::UnsupportedOption {
+                       option: "bind_garlic" }));
+        assert!(!bound.matches_header(
+            b"catena-dragonfly$g=21,gl=18,l=2,n=64"));
+    }
 
-        let g_low: u8;
-        let g_high: u8;
+    #[test]
+    fn matches_header_test() {
+        let dragonfly = ::default_instances::dragonfly::new();
+        let butterfly = ::default_instances::butterfly::new();
 
-        {
-            g_low = self.g_low;
-            g_high = self.g_high;
-        }
+        let dragonfly_header = dragonfly.encode_header().unwrap();
+        let butterfly_header = butterfly.encode_header().unwrap();
 
-        let tweak = self.compute_tweak(
-            Domain::ProofOfWork,
-            output_len,
-            salt.len() as u16,
-            &associated_data);
+        assert!(dragonfly.matches_header(&dragonfly_header));
+        assert!(!dragonfly.matches_header(&butterfly_header));
+        assert!(!butterfly.matches_header(&dragonfly_header));
 
-        let border: u64 = 1 << p;
-        let rand_num = rand::thread_rng().gen_range(0, (1 << p) - 1);
+        let mut stored = dragonfly_header.clone();
+        stored.extend_from_slice(b"$c2FsdA$aGFzaA");
+        assert!(dragonfly.matches_header(&stored));
 
-        if mode == 0 {
+        let mut longer = dragonfly_header.clone();
+        longer.push(b'0');
+        assert!(!dragonfly.matches_header(&longer));
+    }
 
-            for i in 0..border {
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn generate_key_parallel_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-                let mut new_vec = ((i + rand_num) % (border)).to_be_bytes();
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key_identifier = b"key".to_vec();
 
-                while new_vec[0] == 0 && new_vec.len() > 1{
-                    let _ = new_vec.remove(0);
-                }
+        for key_size in [1u16, 64, 65, 300, 1000].iter() {
+            let serial = test_catena.generate_key(
+                pwd.clone(), &ad, salt.clone(), 64, gamma.clone(), *key_size,
+                key_identifier.clone()).unwrap();
+            let parallel = test_catena.generate_key_parallel(
+                pwd.clone(), &ad, salt.clone(), 64, gamma.clone(), *key_size,
+                key_identifier.clone()).unwrap();
 
-                let len = salt.len();
-                let len_new_vec = new_vec.len();
+            assert_eq!(parallel.as_bytes().len(), *key_size as usize);
+            assert_eq!(parallel, serial);
+        }
+    }
 
-                let mut tmp_salt = salt.clone();
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallelism_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-                for (i, _) in new_vec.iter().enumerate() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key_identifier = b"key".to_vec();
+
+        // known answer of the serial key expansion
+        let expected = concat!(
+            "72bc307b1fc58af157b4943d37f09aa868c91515313a583bb3f4868dbeb305fb",
+            "87b3a0756dfee1ed5dd2b50ba8604b80d91b39af985ad32ab632ea1972aab5f0",
+            "7f58a5b0a4761dfab25f30573c7b45d944cc27b34b902c8d110a1378a7e4bcd1",
+            "15f4d5d5c2bdd815fe31bf5c7a4e47f7599b22d916e66aace197efecb0a68393",
+            "757f5bf7461c23bacb6599ad34aa8735f110a89fdf6a2257b8719fcb4d9dc245",
+            "86e3d65d68d8f1d91d37e7721833adb9404826f9b03cf3e8c97aa7844016c205",
+            "d8d97fb5bd7bd49a").to_string().to_be_vec();
+        for threads in [1usize, 2, 3, 4, 16].iter() {
+            test_catena.set_parallelism(*threads);
+            let key = test_catena.generate_key_parallel(
+                pwd.clone(), &ad, salt.clone(), 64, gamma.clone(), 200,
+                key_identifier.clone());
+            assert_eq!(key, Ok(DerivedKey(expected.clone())));
+        }
+    }
 
-                    let tmp = tmp_salt[len - (i + 1)];
-                    tmp_salt[len - (i + 1)] = new_vec[len_new_vec - (i + 1)] 
-                        | tmp;
-                }
+    #[test]
+    fn try_flap_allocation_failed_test() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        let garlic: u8 = 50;
+        let bytes = ((1usize << garlic) + 2) * test_catena.k;
 
-                let hash_to_test = self.catena(
-                    &pwd,
-                    &tweak,
-                    &tmp_salt,
-                    g_low,
-                    g_high,
-                    output_len,
-                    &gamma);
-
-                if hash == hash_to_test {
-                    return tmp_salt
-                }
-            }
+        assert_eq!(
+            test_catena.try_flap(garlic, vec![0; 64], &b"gamma".to_vec()),
+            Err(CatenaError::AllocationFailed { bytes: bytes }));
 
-            panic!("No salt found");
+        assert_eq!(
+            test_catena.try_flap(100, vec![0; 64], &b"gamma".to_vec()),
+            Err(CatenaError::AllocationFailed { bytes: usize::max_value() }));
+    }
 
-        } else if mode == 1 {
+    /// F which fails like a graph whose state can not be reserved.
+    #[derive(Clone, Copy, Debug)]
+    struct UnreservableF {
+        inner: ::default_instances::dragonfly::DragonflyAlgorithms,
+    }
 
-            for i in 0..border+1 {
+    impl Algorithms for UnreservableF {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h(x)
+        }
 
-                let mut new_vec = ((i + rand_num) % (border)).to_be_bytes();
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h_prime(x)
+        }
 
-                while (new_vec[0] == 0) & (new_vec.len() > 1){
-                    let _ = new_vec.remove(0);
-                }
+        fn gamma (&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                  k: usize) -> Vec<u8> {
+            self.inner.gamma(garlic, state, gamma, k)
+        }
 
-                let hash_to_test = self.catena(
-                    &new_vec,
-                    &tweak,
-                    &salt,
-                    g_low,
-                    g_high,
-                    output_len,
-                    &gamma);
-
-                if hash == hash_to_test {
-                    return new_vec;
-                }
+        fn f (&mut self, _garlic: &u8, state: &mut Vec<u8>, _lambda: u8,
+              _n: usize, _k: usize) -> Result<Vec<u8>, CatenaError> {
+            Err(CatenaError::AllocationFailed { bytes: state.len() })
+        }
 
-            }
-            panic!("No password found");
-        } else {
-            panic!("Invalid mode for proof of work");
+        fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            self.inner.phi(garlic, state, mu, k)
         }
     }
 
-    /// Password-scrambling function of Catena
-    fn catena (
-        &mut self,
-        pwd: &Vec<u8>,
-        t: &Vec<u8>,
-        s: &Vec<u8>,
-        g_low: u8,
-        g_high: u8,
-        m: u16,
-        gamma: &Vec<u8>
-    ) -> Vec<u8> {
+    #[test]
+    fn try_flap_graph_allocation_failed_test() {
+        let algorithms = UnreservableF {
+            inner: ::default_instances::dragonfly::new().algorithms,
+        };
+        let mut test_catena = Catena::new(
+            algorithms, "Dragonfly", 64, 64, 9, 9, 2);
+
+        // the first flap is with garlic (g_low + 1) / 2 = 5
+        assert_eq!(
+            test_catena.hash(&b"password".to_vec(), &b"salt".to_vec(),
+                             &b"data".to_vec(), 64, &b"gamma".to_vec()),
+            Err(CatenaError::AllocationFailed { bytes: (1 << 5) * 64 }));
+    }
 
-        let n: usize;
+    #[test]
+    fn profile_test() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        let garlic: u8 = 9;
+
+        let profile = test_catena.profile(
+            garlic,
+            &b"password".to_vec(),
+            &b"salt".to_vec(),
+            &b"data".to_vec(),
+            &b"gamma".to_vec()).unwrap();
+
+        // one flap with (garlic + 1) / 2 and one with garlic, including the
+        // calls of H' in SaltMix and the bit-reversal graph
+        let expected = test_catena.h_prime_invocations((garlic + 1) / 2)
+            .unwrap() + test_catena.h_prime_invocations(garlic).unwrap();
+        assert_eq!(profile.h_prime_calls, expected);
+        // 2^5 + 2 * (2^5 - 1) + 2^4 and 2^9 + 2 * (2^9 - 1) + 2^7
+        assert_eq!(profile.h_prime_calls, 110 + 1662);
+        assert!(profile.h_calls > 0);
+        assert_eq!(profile.peak_bytes, 2 * (1 << garlic) * test_catena.k);
+
+        assert_eq!(test_catena.g_low, 21);
+        assert_eq!(test_catena.g_high, 21);
+    }
 
-        {
-            n = self.n;
-        }
+    #[test]
+    fn hash_schedule_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 7;
+        test_catena.g_high = 9;
 
-        let mut x = self.algorithms.h(
-            &[&t[..], &pwd[..], &s[..]].concat());
-        x = self.flap((g_low + 1) / 2, x, &gamma);
-        x = self.algorithms.h(&x);
-        for g in g_low..g_high + 1 {
-            if x.len() < n {
-                x = ::helpers::vectors::zero_padding(x, n - m as usize);
-            }
-            x = self.flap(g, x, &gamma);
-            x = self.h2(&g.to_le_bytes(), &x);
-            x.truncate(m as usize);
-        }
-        x
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let hash = test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_eq!(
+            test_catena.hash_schedule(&pwd, &salt, &ad, 64, &gamma, &[7, 8, 9]),
+            Ok(hash.clone()));
+        assert_ne!(
+            test_catena.hash_schedule(&pwd, &salt, &ad, 64, &gamma, &[7, 9]),
+            Ok(hash));
+        assert_eq!(
+            test_catena.hash_schedule(&pwd, &salt, &ad, 64, &gamma, &[]),
+            Err(CatenaError::InvalidGarlic));
+
+        test_catena.g_low = 10;
+        assert_eq!(test_catena.hash(&pwd, &salt, &ad, 64, &gamma),
+                   Err(CatenaError::InvalidGarlic));
     }
 
-    /// Flap function of Catena
-    fn flap(
-        &mut self,
-        garlic: u8,
-        x: Vec<u8>,
-        gamma: &Vec<u8>
-    ) -> Vec<u8> {
+    #[cfg(all(target_os = "linux", feature = "affinity"))]
+    #[test]
+    fn hash_pinned_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-        let n: usize;
-        let k: usize;
+        let (pwd, salt, ad, gamma) = test_inputs();
 
-        {
-            n = self.n;
-            k = self.k;
-        }
+        let original = ::helpers::affinity::cpus(
+            &::helpers::affinity::get().unwrap());
 
-        let (vminus2, vminus1) = self.h_init(x);
+        assert_eq!(
+            test_catena.hash_pinned(
+                &pwd, &salt, &ad, 64, &gamma, &original[..1]),
+            Ok(test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap()));
+        assert_eq!(
+            ::helpers::affinity::cpus(&::helpers::affinity::get().unwrap()),
+            original);
 
-        let g: usize = 1 << garlic;
+        assert!(test_catena.hash_pinned(&pwd, &salt, &ad, 64, &gamma, &[])
+                .is_err());
+        assert_eq!(
+            ::helpers::affinity::cpus(&::helpers::affinity::get().unwrap()),
+            original);
+    }
 
-        let mut state: Vec<Vec<u8>> = Vec::with_capacity(g + 2);
-        state.push(vminus2);
-        state.push(vminus1);
+    #[test]
+    fn hash_multi_len_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-        self.algorithms.reset_h_prime();
+        let (pwd, salt, ad, gamma) = test_inputs();
 
-        for i in 2..(g + 2) {
-            let state_i = self.h_prime2(
-                state[i - 1].clone(), state[i - 2].clone());
-            state.push(state_i);
-        }
+        let outputs = test_catena.hash_multi_len(
+            &pwd, &salt, &ad, &gamma, &[32, 64, 16]).unwrap();
+        let hash_64 = test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let hash_32 = test_catena.hash(&pwd, &salt, &ad, 32, &gamma).unwrap();
 
-        // remove v_(-2) and v_(-1)
-        state = state[2..].to_vec();
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[1], hash_64);
+        assert_eq!(outputs[0].as_bytes(), &hash_64.as_bytes()[..32]);
+        assert_eq!(outputs[2].as_bytes(), &hash_64.as_bytes()[..16]);
 
-        // Append all k-byte values
-        let mut v: Vec<u8> = Vec::with_capacity((g + 2) * k);
-        for element in &state {
-            v.append(&mut element.clone());
-        }
+        // the output length is part of the tweak
+        assert_ne!(outputs[0], hash_32);
 
-        self.algorithms.reset_h_prime();
-        v = self.algorithms.gamma(garlic, v, gamma, k);
-        self.algorithms.reset_h_prime();
-        v = self.algorithms.f(&garlic, &mut v, self.lambda, n, k);
-        self.algorithms.reset_h_prime();
+        assert_eq!(test_catena.hash_multi_len(&pwd, &salt, &ad, &gamma, &[]),
+                   Ok(Vec::new()));
+        assert_eq!(test_catena.hash_multi_len(&pwd, &salt, &ad, &gamma, &[0]),
+                   Err(CatenaError::ZeroOutputLength));
+    }
 
-        // last state word as mu
-        let mu = v.get_word(k, g - 1);
-        v = self.algorithms.phi(garlic, v, &mu, k);
+    #[test]
+    fn generate_key_large_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+        let n = test_catena.n;
+
+        // two blocks more than a 16-bit counter can address
+        let key_size = ((1 << 16) + 1) * n;
+        let key = test_catena.generate_key_large(
+            b"password".to_vec(), &b"data".to_vec(), b"salt".to_vec(), 64,
+            b"gamma".to_vec(), key_size, b"key".to_vec()).unwrap();
+        let key = key.as_bytes();
+
+        assert_eq!(key.len(), key_size);
+        // block 65537 would repeat block 1 if the counter wrapped
+        assert_ne!(key[(1 << 16) * n..].to_vec(), key[..n].to_vec());
+
+        // the number of blocks and the capacity do not overflow
+        assert_eq!(
+            test_catena.generate_key_large(
+                b"password".to_vec(), &b"data".to_vec(), b"salt".to_vec(), 64,
+                b"gamma".to_vec(), usize::max_value(), b"key".to_vec()),
+            Err(CatenaError::AllocationFailed { bytes: usize::max_value() }));
+    }
 
-        // only the last state word is used
-        v.get_word(k, g - 1)
+    #[test]
+    fn known_answers_test() {
+        let known_answers = known_answers();
+        assert_eq!(known_answers.len(), ::registry::Variant::all().len());
+
+        for (vid, expected) in known_answers {
+            let mut instance = ::registry::Variant::from_name(vid)
+                .unwrap()
+                .instance();
+            instance.set_garlic(SELF_TEST_GARLIC, SELF_TEST_GARLIC);
+
+            let output = instance.hash(
+                b"password", b"salt", b"data", expected.len() as u16, b"gamma");
+            assert_eq!(output, Ok(PasswordHash(expected)), "{} changed", vid);
+        }
     }
 
-    fn h_init (
-        &mut self,
-        x: Vec<u8>
-    ) ->  (Vec<u8>, Vec<u8>){
-        let n: usize;
-        let k: usize;
+    fn password_hash_test_instance<T: Algorithms>(mut catena: Catena<T>) {
+        catena.g_low = 9;
+        catena.g_high = 9;
 
-        {
-            n = self.n;
-            k = self.k;
-        }
+        let (pwd, salt, ad, gamma) = test_inputs();
 
-        let l: usize = 2 * k / n;
-        let mut w: Vec<u8> = Vec::new();
-        for i in 0..l {
-            w = [&w[..],
-                 &self.h2(&vec![i as u8], &x)].concat();
-        }
-        let vminus2 = [&w[0..(w.len() / 2)]].concat();
-        let vminus1 = [&w[(w.len() / 2)..]].concat();
+        let hash = catena.password_hash(&pwd, &salt, &ad, &gamma).unwrap();
+        assert_eq!(hash.as_bytes().len(), catena.n);
+        assert_eq!(hash, catena.hash(&pwd, &salt, &ad, catena.n as u16, &gamma)
+                   .unwrap());
+    }
 
-        (vminus2,vminus1)
+    #[test]
+    fn password_hash_test() {
+        password_hash_test_instance(::default_instances::dragonfly::new());
+        password_hash_test_instance(::default_instances::dragonfly_full::new());
+        password_hash_test_instance(::default_instances::butterfly::new());
+        password_hash_test_instance(::default_instances::butterfly_full::new());
     }
 
-    /// Compute the tweak for a given domain.
-    ///
-    /// # Inputs
-    ///
-    /// - mode: The domain for which Catena is used.
-    /// - output_len: The output length of the final hash.
-    /// - salt_len: The length of the salt.
-    /// - a_data: Associated data.
-    fn compute_tweak(
-        &self,
-        mode: Domain,
-        output_len: u16,
-        salt_len: u16,
-        a_data: &Vec<u8>)
-    -> Vec<u8> {
+    #[test]
+    fn update_one_step_test() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        test_catena.g_low = 14;
+        test_catena.g_high = 14;
 
-        let d: u8;
-        match mode {
-            Domain::PasswordScrambling => d = 0,
-            Domain::KeyDerivation => d = 1,
-            Domain::ProofOfWork => d = 2,
+        let gamma = b"gamma".to_vec();
+        let old_hash = test_catena.hash(
+            &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(), 64,
+            &gamma).unwrap().0;
+
+        let mut hash = old_hash.clone();
+        let mut g: u8 = 14;
+        while g < 17 {
+            let (new_hash, new_g) = test_catena.update_one_step(
+                hash, g, &gamma, 64).unwrap();
+            assert_eq!(new_g, g + 1);
+            hash = new_hash;
+            g = new_g;
         }
 
-        // compute H(V)
-        let hv = self.algorithms.h(&self.vid.as_bytes().to_vec());
-
-        // compute H(AD)
-        let had = self.algorithms.h(a_data);
-
-        let tweak = [&hv[..], &[d, self.lambda], &output_len.to_le_bytes()[..],
-        &salt_len.to_le_bytes()[..], &had[..]].concat();
-
-        tweak
-    }
+        assert_eq!(
+            hash,
+            test_catena.client_independent_update(old_hash, 14, 17, &gamma, 64)
+                .unwrap());
 
-    /// Compute h(a || b)
-    fn h2(&mut self, a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
-        let input = [&a[..], &b[..]].concat();
-        self.algorithms.h(&input)
+        test_catena.g_high = 17;
+        assert_eq!(
+            hash,
+            test_catena.hash(
+                &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(),
+                64, &gamma).unwrap().0);
     }
 
-    /// Compute h(a || b || c)
-    fn h3(&mut self, a: &Vec<u8>, b: &Vec<u8>, c: &Vec<u8>) -> Vec<u8> {
-        let input = [&a[..], &b[..], &c[..]].concat();
-        self.algorithms.h(&input)
+    #[test]
+    fn update_one_step_invalid_test() {
+        let mut test_catena = ::default_instances::dragonfly::new();
+        let gamma = b"gamma".to_vec();
+
+        assert_eq!(test_catena.update_one_step(vec![0; 64], 255, &gamma, 64),
+                   Err(CatenaError::InvalidGarlic));
+        assert_eq!(
+            test_catena.update_one_step(
+                vec![0; 64], ::cli::MAX_GARLIC, &gamma, 64),
+            Err(CatenaError::InvalidGarlic));
+        assert_eq!(test_catena.update_one_step(vec![0; 64], 14, &gamma, 65),
+                   Err(CatenaError::OutputTooLong { requested: 65, max: 64 }));
+        assert_eq!(test_catena.update_one_step(vec![0; 64], 14, &gamma, 0),
+                   Err(CatenaError::ZeroOutputLength));
     }
 
-    /// Compute h(a || b || c || d)
-    fn h4(&mut self, a: &Vec<u8>, b: &Vec<u8>, c: &Vec<u8>, d: &Vec<u8>)
-        -> Vec<u8> {
-        let input = [&a[..], &b[..], &c[..], &d[..]].concat();
-        self.algorithms.h(&input)
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn flap_full_state_test() {
+        let x = vec![0x42; 64];
+        let gamma = b"gamma".to_vec();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+
+        let state = dragonfly.flap_full_state(10, x.clone(), &gamma);
+        assert_eq!(state.len(), 1 << 10);
+        assert!(state.iter().all(|word| word.len() == 64));
+        assert_eq!(state[state.len() - 1], dragonfly.flap(10, x, &gamma));
     }
 
-    /// Compute h_prime(a || b)
-    fn h_prime2(&mut self, a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
-        let input = [&a[..], &b[..]].concat();
-        self.algorithms.h_prime(&input)
+    #[cfg(feature = "debug-trace")]
+    #[derive(Clone, Copy, Debug)]
+    struct BrokenPhi {
+        inner: ::default_instances::dragonfly::DragonflyAlgorithms,
+        from_garlic: u8,
     }
 
-    /// Key-Derivation Function Catena-KG
-    fn key_generation (
-        &mut self,
-        pwd: Vec<u8>,
-        tweak: Vec<u8>,
-        salt: Vec<u8>,
-        g_low: u8,
-        g_high: u8,
-        m: u16,
-        gamma: Vec<u8>,
-        key_size: u16,
-        key_identifier: Vec<u8>
-    ) -> Vec<u8> {
-        let n: usize;
+    #[cfg(feature = "debug-trace")]
+    impl Algorithms for BrokenPhi {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h(x)
+        }
 
-        {
-            n = self.n;
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h_prime(x)
         }
 
-        let x = self.catena(&pwd, &tweak, &salt, g_low, g_high, m, &gamma);
-        let mut k: Vec<u8> = Vec::new();
+        fn reset_h_prime(&mut self) {
+            self.inner.reset_h_prime();
+        }
 
-        let limit = (f32::ceil(key_size as f32 / n as f32) + 1.0) as u16;
+        fn gamma (&mut self, garlic:u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8> {
+            self.inner.gamma(garlic, state, gamma, k)
+        }
 
-        for i in 1..limit {
-            k.append(
-                &mut self.h4(
-                    &i.to_le_bytes(),
-                    &key_identifier,
-                    &key_size.to_le_bytes(),
-                    &x));
+        fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, CatenaError> {
+            self.inner.f(garlic, state, lambda, n, k)
         }
 
-        k.truncate(key_size as usize);
-        k
+        fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+            let mut state = self.inner.phi(garlic, state, mu, k);
+            if garlic >= self.from_garlic {
+                let last = state.len() - 1;
+                state[last] ^= 1;
+            }
+            state
+        }
     }
 
-    /// Compute Keystream for keyed hashing
-    fn compute_keystream(
-        &mut self,
-        server_key: &Vec<u8>,
-        user_id: &Vec<u8>,
-        g_high: u8,
-        output_length: usize
-    ) -> Vec<u8> {
-            let mut keystream = self.h4(
-                server_key,
-                user_id,
-                &g_high.to_le_bytes(),
-                server_key);
+    /// H' which returns the first word of its input, so every word of the
+    /// state equals its predecessor.
+    #[cfg(feature = "debug-trace")]
+    #[derive(Clone, Copy, Debug)]
+    struct IdentityHPrime;
 
-            keystream.truncate(output_length);
+    #[cfg(feature = "debug-trace")]
+    impl Algorithms for IdentityHPrime {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            ::components::hash::blake2b::hash(x)
+        }
 
-            keystream
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            x[..64].to_vec()
+        }
+
+        fn gamma (&mut self, _garlic: u8, state: Vec<u8>, _gamma: &Vec<u8>, _k: usize)
+        -> Vec<u8> {
+            state
+        }
+
+        fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, CatenaError> {
+            ::components::graph::generic_graph::bit_reversal_hash(
+                self, garlic, state, lambda, n, k)
+        }
+
+        fn phi (&mut self, _garlic: u8, state: Vec<u8>, _mu: &Vec<u8>, _k: usize)
+        -> Vec<u8> {
+            state
+        }
     }
-}
 
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn suspicious_state_test() {
+        let mut broken = Catena {
+            algorithms: IdentityHPrime,
+            vid: "Broken",
+            n: 64,
+            k: 64,
+            g_low: 9,
+            g_high: 9,
+            lambda: 2,
+            options: ::catena::Options::default(),
+        };
+        let x = vec![0x5a; 64];
+        let gamma = b"gamma".to_vec();
+        assert_eq!(broken.check_flap_state(9, x.clone(), &gamma),
+                   Err(CatenaError::SuspiciousState));
+
+        // the check is not part of hashing, so the feature keeps the result
+        assert!(broken.hash(&b"password".to_vec(), &b"salt".to_vec(),
+                            &b"data".to_vec(), 64, &gamma)
+                .is_ok());
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        assert_eq!(dragonfly.check_flap_state(9, x, &gamma), Ok(()));
+    }
 
-#[cfg(test)]
-mod tests {
-    use bytes::HexRepresentation;
-    use bytes::Bytes;
-    use helpers::files::JSONTests;
-    use super::*;
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn first_divergent_garlic_test() {
+        let x = vec![0x42; 64];
+        let gamma = b"gamma".to_vec();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        let expected: Vec<Vec<u8>> = (9..14)
+            .map(|garlic| dragonfly.flap(garlic, x.clone(), &gamma))
+            .collect();
+        let reference = |garlic: u8| expected[garlic as usize - 9].clone();
+
+        let mut broken = Catena {
+            algorithms: BrokenPhi {
+                inner: ::default_instances::dragonfly::new().algorithms,
+                from_garlic: 11,
+            },
+            vid: "Broken",
+            n: 64,
+            k: 64,
+            g_low: 9,
+            g_high: 13,
+            lambda: 2,
+            options: ::catena::Options::default(),
+        };
+        assert_eq!(
+            broken.first_divergent_garlic(&x, &gamma, &reference, 9..14),
+            Some(11));
+
+        assert_eq!(
+            dragonfly.first_divergent_garlic(&x, &gamma, &reference, 9..14),
+            None);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn hash_password_phc_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+
+        let phc = dragonfly.hash_password_phc(b"password", b"gamma").unwrap();
+        let parsed = ::phc::parse(&phc).unwrap();
+        assert_eq!(parsed.salt.len(), PHC_SALT_LENGTH);
+        assert_eq!(parsed.hash.len(), 64);
+
+        assert_eq!(::verify_phc(&phc, b"password", b"gamma"), Ok(true));
+        assert_eq!(::verify_phc(&phc, b"passw0rd", b"gamma"), Ok(false));
+        assert_ne!(dragonfly.hash_password_phc(b"password", b"gamma"), Ok(phc));
+    }
 
-    fn proof_of_work_server_test_from_json <T: Algorithms>(
-        mut catena: ::catena::Catena<T>, file: &str)
-    {
-        let json = ::helpers::files::open_json(file.to_string());
-        let unwrapped_json = json.as_ref().unwrap();
-        let numbers_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+    #[test]
+    fn initial_input_order_test() {
+        let t = vec![0x01; 3];
+        let pwd = vec![0x02; 2];
+        let s = vec![0x03; 4];
+        assert_eq!(initial_input(&t, &pwd, &s),
+                   [&t[..], &pwd[..], &s[..]].concat());
+    }
 
-        for i in 0..numbers_of_tests {
-            let ref inputs = unwrapped_json[i]["inputs"];
+    #[test]
+    fn server_relief_equals_hash_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let output_length: u16 = 64;
+
+        for &(g_low, g_high) in [(14, 14), (12, 14)].iter() {
+            let mut dragonfly = ::default_instances::dragonfly::new();
+            dragonfly.g_low = g_low;
+            dragonfly.g_high = g_high;
+
+            let expected = dragonfly.hash(
+                &pwd, &salt, &ad, output_length, &gamma).unwrap().0;
+            let client_output = dragonfly.client_prep(
+                pwd.clone(), salt.clone(), &ad, output_length, &gamma)
+                .unwrap();
+            assert_eq!(dragonfly.server_final(client_output, output_length),
+                       Ok(expected));
+        }
+    }
 
-            let pwd = inputs.parse_hex("pwd");
-            let mut salt = inputs.parse_hex("salt");
-            let ad = inputs.parse_hex("aData");
-            let gamma = inputs.parse_hex("gamma");
-            let out_len = inputs.parse_u16("outLen");
-            let p = inputs.parse_usize("p");
-            let mode = inputs.parse_u8("mode");
-
-            let (result_pwd,
-                 result_salt,
-                 result_ad,
-                 result_gamma,
-                 result_out_len,
-                 result_hash,
-                 result_p,
-                 result_mode) = catena.proof_of_work_server(
-                     &pwd,
-                     &mut salt,
-                     &ad,
-                     &gamma,
-                     out_len,
-                     p,
-                     mode);
+    #[test]
+    fn validate_client_output_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+
+        let client_output = dragonfly.client_prep(
+            b"password".to_vec(), b"salt".to_vec(), &b"data".to_vec(), 64,
+            &b"gamma".to_vec()).unwrap();
+        assert_eq!(dragonfly.validate_client_output(&client_output), Ok(()));
+
+        let result = dragonfly.validate_client_output(&client_output[..32]);
+        assert_eq!(result, Err(CatenaError::OutputLengthMismatch {
+            function: "flap",
+            expected: 64,
+            actual: 32,
+        }));
+        assert_eq!(format!("{}", result.unwrap_err()),
+                   "output of flap has 32 bytes, but 64 bytes are expected");
+        assert!(dragonfly.validate_client_output(&[]).is_err());
+        assert!(dragonfly.validate_client_output(&vec![0u8; 65]).is_err());
+
+        // server_final rejects what validate_client_output rejects
+        assert_eq!(dragonfly.server_final(client_output[..32].to_vec(), 64),
+                   Err(CatenaError::OutputLengthMismatch {
+                       function: "flap",
+                       expected: 64,
+                       actual: 32,
+                   }));
+        assert_eq!(dragonfly.server_final(client_output.clone(), 0),
+                   Err(CatenaError::ZeroOutputLength));
+        assert_eq!(dragonfly.server_final(client_output, 65),
+                   Err(CatenaError::OutputTooLong { requested: 65, max: 64 }));
+    }
 
-            let ref outputs = unwrapped_json[i]["outputs"];
+    #[test]
+    fn key_ratchet_test() {
+        let mut test_catena = ::default_instances::butterfly::new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
 
-            let expected_pwd = outputs.parse_hex("pwd");
-            let expected_salt = outputs.parse_hex("salt");
-            let expected_ad = outputs.parse_hex("aData");
-            let expected_gamma = outputs.parse_hex("gamma");
-            let expected_out_len = outputs.parse_u16("outLen");
-            let expected_hash = outputs.parse_hex("outHash");
-            let expected_p = outputs.parse_usize("p");
-            let expected_mode = outputs.parse_u8("mode");
+        let (pwd, salt, ad, gamma) = test_inputs();
 
-            assert_eq!(result_pwd.to_hex_string(), expected_pwd.to_hex_string());
-            assert_eq!(result_salt.to_hex_string(), expected_salt.to_hex_string());
-            assert_eq!(result_ad.to_hex_string(), expected_ad.to_hex_string());
-            assert_eq!(result_gamma.to_hex_string(), expected_gamma.to_hex_string());
-            assert_eq!(result_out_len, expected_out_len);
-            assert_eq!(result_hash.to_hex_string(), expected_hash.to_hex_string());
-            assert_eq!(result_p, expected_p);
-            assert_eq!(result_mode, expected_mode);
+        let keys = test_catena.key_ratchet(
+            pwd.clone(), &ad, salt.clone(), gamma.clone(), 4, 32).unwrap();
+        assert_eq!(keys.len(), 4);
+        for i in 0..keys.len() {
+            assert_eq!(keys[i].as_bytes().len(), 32);
+            for j in 0..i {
+                assert_ne!(keys[i], keys[j]);
+            }
         }
+
+        // the ratchet is advanced on the full 64 bytes, not on the keys
+        let chain = test_catena.generate_key(
+            pwd.clone(), &ad, salt.clone(), 64, gamma.clone(), 64, Vec::new())
+                .unwrap();
+        assert_eq!(keys[0].as_bytes(), &chain.as_bytes()[..32]);
+        let next = test_catena.algorithms.h(
+            &[chain.as_bytes(), &[0u8; 8][..]].concat());
+        assert_eq!(keys[1].as_bytes(), &next[..32]);
+        let next = test_catena.algorithms.h(
+            &[&next[..], &1u64.to_le_vec()[..]].concat());
+        assert_eq!(keys[2].as_bytes(), &next[..32]);
+
+        assert_eq!(
+            test_catena.key_ratchet(
+                pwd.clone(), &ad, salt.clone(), gamma.clone(), 4, 32),
+            Ok(keys));
+
+        assert_eq!(
+            test_catena.key_ratchet(
+                pwd.clone(), &ad, salt.clone(), gamma.clone(), 4, 65),
+            Err(CatenaError::OutputTooLong { requested: 65, max: 64 }));
+        assert_eq!(
+            test_catena.key_ratchet(
+                pwd.clone(), &ad, salt.clone(), gamma.clone(), 4, 0),
+            Err(CatenaError::ZeroOutputLength));
+        assert_eq!(
+            test_catena.key_ratchet(pwd, &ad, Vec::new(), gamma, 4, 32),
+            Err(CatenaError::EmptySalt));
     }
 
     #[test]
-    fn proof_of_work_server_salt_test_butterfly_reduced() {
-        let mut catena_bf = ::default_instances::butterfly::new();
-            catena_bf.g_low = 9;
-            catena_bf.g_high = 9;
-        proof_of_work_server_test_from_json(
-            catena_bf,
-            "test/test_vectors/proofOfWorkServerSaltButterflyReduced.json");
+    fn verify_min_garlic_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 13;
+        dragonfly.g_high = 13;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let stored = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+        assert_eq!(
+            dragonfly.verify_min_garlic(
+                &pwd, &salt, &ad, &gamma, &stored, 13, 13),
+            Ok(VerifyResult::Ok));
+        assert_eq!(
+            dragonfly.verify_min_garlic(
+                &pwd, &salt, &ad, &gamma, &stored, 13, 14),
+            Ok(VerifyResult::OkNeedsUpgrade));
+        assert_eq!(
+            dragonfly.verify_min_garlic(
+                &b"passw0rd".to_vec(), &salt, &ad, &gamma, &stored, 13, 14),
+            Ok(VerifyResult::Failed));
+        assert_eq!(
+            dragonfly.verify_min_garlic(
+                &pwd, &salt, &ad, &gamma, &stored, 12, 12),
+            Ok(VerifyResult::Failed));
+        assert_eq!((dragonfly.g_low, dragonfly.g_high), (14, 14));
+
+        // the garlic is restored if verify returns an error
+        assert_eq!(
+            dragonfly.verify_min_garlic(
+                &pwd, &Vec::new(), &ad, &gamma, &stored, 13, 13),
+            Err(CatenaError::EmptySalt));
+        assert_eq!((dragonfly.g_low, dragonfly.g_high), (14, 14));
     }
 
     #[test]
-    fn proof_of_work_server_pwd_test_butterfly_reduced() {
-        let mut catena_bf = ::default_instances::butterfly::new();
-            catena_bf.g_low = 9;
-            catena_bf.g_high = 9;
-        proof_of_work_server_test_from_json(
-            catena_bf,
-            "test/test_vectors/proofOfWorkServerPwdButterflyReduced.json");
+    fn assert_deterministic_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        assert_eq!(dragonfly.assert_deterministic(), Ok(()));
+        assert_eq!((dragonfly.g_low, dragonfly.g_high), (21, 21));
+
+        dragonfly.lambda = 1;
+        assert_eq!(dragonfly.assert_deterministic(),
+                   Err(CatenaError::NotDeterministic));
     }
 
     #[test]
-    #[should_panic]
-    fn proof_of_work_server_panic_test_1() {
-        let pwd: Vec<u8> = vec!(0, 0);
-        let mut salt: Vec<u8> = vec!(0, 0);
-        let ad: Vec<u8> = vec!(0, 0);
-        let gamma: Vec<u8> = vec!(0, 0);
-        let out_len: u16 = 64;
-        let p = 1;
-        let mode: u8 = 6;
+    fn build_info_test() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.parallel, cfg!(feature = "parallel"));
+        assert_eq!(info.constant_time, cfg!(feature = "constant-time"));
+    }
 
-        let mut catena_bf = ::default_instances::butterfly::new();
+    #[test]
+    fn commit_hashes_test() {
+        let hashes: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 64]).collect();
+        let h = |x: &[u8]| ::components::hash::blake2b::hash(&x.to_vec());
+        let leaf = |x: &[u8]| h(&[&[0x00][..], x].concat());
+        let node = |l: &[u8], r: &[u8]| h(&[&[0x01][..], l, r].concat());
+
+        assert_eq!(commit_hashes(&hashes[..1]).to_vec(), leaf(&hashes[0]));
+        assert_eq!(commit_hashes(&[]).to_vec(), h(&[]));
+        assert_eq!(commit_hashes(&hashes[..3]).to_vec(),
+                   node(&node(&leaf(&hashes[0]), &leaf(&hashes[1])),
+                        &leaf(&hashes[2])));
+
+        let root = commit_hashes(&hashes);
+        assert_eq!(root.to_vec(), commit_hashes(&hashes).to_vec());
+        let mut reordered = hashes.clone();
+        reordered.swap(1, 3);
+        assert_ne!(root.to_vec(), commit_hashes(&reordered).to_vec());
+        assert_ne!(root.to_vec(), commit_hashes(&hashes[..4]).to_vec());
+
+        // a hash which looks like an inner node is not a second preimage
+        let pair = &hashes[..2];
+        let inner = [&[0x01][..], &leaf(&pair[0]), &leaf(&pair[1])].concat();
+        assert_ne!(commit_hashes(&[inner]).to_vec(),
+                   commit_hashes(pair).to_vec());
+    }
 
-        let _result = catena_bf.proof_of_work_server(
-            &pwd,
-            &mut salt,
-            &ad,
-            &gamma,
-            out_len,
-            p,
-            mode);
+    #[test]
+    fn endianness_report_test() {
+        let report = endianness_report();
+        assert!(report.contains("tweak: output length and salt length as 2 \
+                                 bytes, little endian"));
+        assert!(report.contains("Catena-KG: counter and key size as 2 bytes, \
+                                 little endian"));
+
+        // the tweak of the report: output length 64, salt length 4
+        let dragonfly = ::default_instances::dragonfly::new();
+        let tweak = dragonfly.compute_tweak(
+            Domain::PasswordScrambling, 64, 4, &b"data".to_vec());
+        assert_eq!(tweak[66..70], [64, 0, 4, 0]);
     }
 
     #[test]
-    #[should_panic]
-    /// test for wrong password length panic
-    fn proof_of_work_server_panic_test_2() {
-        let pwd: Vec<u8> = vec!(0, 0);
-        let mut salt: Vec<u8> = vec!(0, 0);
-        let ad: Vec<u8> = vec!(0, 0);
-        let gamma: Vec<u8> = vec!(0, 0);
-        let out_len: u16 = 64;
-        let p = 1;
-        let mode: u8 = 1;
+    fn h_id_tweak_test() {
+        let ad = b"data".to_vec();
+        let tweak = ::default_instances::dragonfly::new()
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+        let tweak_1 = ::default_instances::dragonfly::new().with_h_id(1)
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+        let tweak_2 = ::default_instances::dragonfly::new().with_h_id(2)
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+
+        assert_eq!(tweak_1, [&tweak[..], &[1]].concat());
+        assert_ne!(tweak_1, tweak_2);
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+        let mut dragonfly_h_id = dragonfly.clone().with_h_id(1);
+        assert_ne!(
+            dragonfly.hash(&b"password".to_vec(), &b"salt".to_vec(), &ad, 64,
+                           &b"gamma".to_vec()).unwrap(),
+            dragonfly_h_id.hash(&b"password".to_vec(), &b"salt".to_vec(), &ad,
+                                64, &b"gamma".to_vec()).unwrap());
+    }
 
-        let mut catena_bf = ::default_instances::butterfly::new();
+    #[test]
+    fn bind_salt_in_final_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 13;
+        dragonfly.g_high = 14;
+        let mut bound = dragonfly.clone().with_bind_salt_in_final(true);
+
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let hash_bound = bound.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_ne!(hash, hash_bound);
+        assert_eq!(hash_bound, bound.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap());
+
+        // the server relief and the updates do not know the salt
+        let unsupported = Err(CatenaError::UnsupportedOption {
+            option: "bind_salt_in_final" });
+        let client_output = dragonfly.client_prep(
+            pwd.clone(), salt.clone(), &ad, 64, &gamma).unwrap();
+        assert_eq!(
+            bound.client_prep(pwd.clone(), salt.clone(), &ad, 64, &gamma),
+            unsupported);
+        assert_eq!(bound.server_final(client_output, 64), unsupported);
+        assert_eq!(bound.client_independent_update(
+                       hash_bound.0.clone(), 14, 15, &gamma, 64),
+                   unsupported);
+        assert_eq!(bound.update_one_step(hash_bound.0, 14, &gamma, 64),
+                   Err(CatenaError::UnsupportedOption {
+                       option: "bind_salt_in_final" }));
+
+        let mut unbound = bound.with_bind_salt_in_final(false);
+        assert_eq!(hash, unbound.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+    }
 
-        let _result = catena_bf.proof_of_work_server(
-            &pwd,
-            &mut salt,
-            &ad,
-            &gamma,
-            out_len,
-            p,
-            mode);
+    #[test]
+    fn bind_garlic_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+        let mut bound = dragonfly.clone().with_bind_garlic(true);
+
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let hash_bound = bound.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_ne!(hash, hash_bound);
+        assert_eq!(bound.verify(&pwd, &salt, &ad, &gamma, &hash_bound),
+                   Ok(true));
+
+        let mut raised = bound.clone();
+        raised.g_high = 15;
+        assert_eq!(raised.verify(&pwd, &salt, &ad, &gamma, &hash_bound),
+                   Ok(false));
+
+        // both bindings together differ from each of them
+        let mut both = bound.clone().with_bind_salt_in_final(true);
+        let hash_both = both.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_ne!(hash_both, hash_bound);
+        assert_ne!(hash_both, dragonfly.clone().with_bind_salt_in_final(true)
+                   .hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+
+        // the server relief applies the binding, the updates reject it
+        let client_output = bound.client_prep(
+            pwd.clone(), salt.clone(), &ad, 64, &gamma).unwrap();
+        assert_eq!(bound.server_final(client_output, 64),
+                   Ok(hash_bound.0.clone()));
+        let mut relieved = bound.clone();
+        relieved.g_low = 12;
+        let client_output = relieved.client_prep(
+            pwd.clone(), salt.clone(), &ad, 32, &gamma).unwrap();
+        assert_eq!(relieved.server_final(client_output, 32),
+                   relieved.hash(&pwd, &salt, &ad, 32, &gamma)
+                       .map(|hash| hash.0));
+        let unsupported = Err(CatenaError::UnsupportedOption {
+            option: "bind_garlic" });
+        assert_eq!(bound.client_independent_update(
+                       hash_bound.0.clone(), 14, 15, &gamma, 64),
+                   unsupported);
+        assert_eq!(bound.update_one_step(hash_bound.0, 14, &gamma, 64),
+                   Err(CatenaError::UnsupportedOption {
+                       option: "bind_garlic" }));
+
+        let mut unbound = bound.with_bind_garlic(false);
+        assert_eq!(hash, unbound.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
     }
 
-    fn proof_of_work_client_test_from_json <T: Algorithms>(
-        mut catena: ::catena::Catena<T>, file: &str)
-    {
-        let json = ::helpers::files::open_json(file.to_string());
-        let unwrapped_json = json.as_ref().unwrap();
-        let numbers_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+    #[test]
+    fn hash_words_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let words = dragonfly.hash_words(&pwd, &salt, &ad, 64, &gamma, 16)
+            .unwrap();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|word| word.len() == 16));
+        assert_eq!(words.concat(), hash.0);
+
+        assert_eq!(dragonfly.hash_words(&pwd, &salt, &ad, 64, &gamma, 24),
+                   Err(CatenaError::InvalidWordSize));
+        assert_eq!(dragonfly.hash_words(&pwd, &salt, &ad, 64, &gamma, 0),
+                   Err(CatenaError::InvalidWordSize));
+    }
 
-        for i in 0..numbers_of_tests {
-            let ref inputs = unwrapped_json[i]["inputs"];
+    /// Dragonfly which records the hits of the seed cache of SaltMix or
+    /// ignores the cache.
+    #[derive(Clone, Copy, Debug)]
+    struct SeedCacheHits {
+        inner: ::default_instances::dragonfly::DragonflyAlgorithms,
+        use_cache: bool,
+        hits: usize,
+    }
 
-            let pwd = inputs.parse_hex("pwd");
-            let salt = inputs.parse_hex("salt");
-            let ad = inputs.parse_hex("aData");
-            let gamma = inputs.parse_hex("gamma");
-            let out_len = inputs.parse_u16("outLen");
-            let hash = inputs.parse_hex("hash");
-            let p = inputs.parse_usize("p");
-            let mode = inputs.parse_u8("mode");
+    impl Algorithms for SeedCacheHits {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h(x)
+        }
 
-            let result = catena.proof_of_work_client(
-                pwd,
-                salt,
-                ad,
-                gamma,
-                out_len,
-                hash,
-                p,
-                mode);
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h_prime(x)
+        }
 
-            let ref outputs = unwrapped_json[i]["outputs"];
+        fn reset_h_prime(&mut self) {
+            self.inner.reset_h_prime();
+        }
+
+        fn gamma (&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                  k: usize) -> Vec<u8> {
+            self.inner.gamma(garlic, state, gamma, k)
+        }
+
+        fn gamma_cached (&mut self, garlic: u8, state: Vec<u8>,
+                         gamma: &Vec<u8>, k: usize, cache: &mut SeedCache)
+            -> Vec<u8> {
+            if !self.use_cache {
+                return self.inner.gamma(garlic, state, gamma, k);
+            }
+            let state = self.inner.gamma_cached(garlic, state, gamma, k, cache);
+            self.hits = cache.hits();
+            state
+        }
 
-            let expected = outputs.parse_hex("res");
+        fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8,
+              n: usize, k: usize) -> Result<Vec<u8>, CatenaError> {
+            self.inner.f(garlic, state, lambda, n, k)
+        }
 
-            assert_eq!(result.to_hex_string(), expected.to_hex_string());
+        fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            self.inner.phi(garlic, state, mu, k)
         }
     }
 
     #[test]
-    fn proof_of_work_client_salt_test_butterfly_reduced() {
-        let mut catena_bf = ::default_instances::butterfly::new();
-            catena_bf.g_low = 9;
-            catena_bf.g_high = 9;
-        proof_of_work_client_test_from_json(
-            catena_bf,
-            "test/test_vectors/proofOfWorkClientSaltButterflyReduced.json");
+    fn saltmix_cache_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let instance = |use_cache| Catena::new(
+            SeedCacheHits {
+                inner: ::default_instances::dragonfly::new().algorithms,
+                use_cache: use_cache,
+                hits: 0,
+            },
+            "Dragonfly", 64, 64, 13, 14, 2);
+        let mut cached = instance(true);
+        let mut uncached = instance(false);
+
+        // flap with 7, 13 and 14 computes the seed only once
+        let hash = cached.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_eq!(cached.algorithms.hits, 2);
+        assert_eq!(hash, uncached.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+        assert_eq!(uncached.algorithms.hits, 0);
+
+        // the cache is not kept from one hash to the next
+        assert_eq!(cached.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(), hash);
+        assert_eq!(cached.algorithms.hits, 2);
     }
 
     #[test]
-    fn proof_of_work_client_pwd_test_butterfly_reduced() {
-        let mut catena_bf = ::default_instances::butterfly::new();
-            catena_bf.g_low = 9;
-            catena_bf.g_high = 9;
-        proof_of_work_client_test_from_json(
-            catena_bf,
-            "test/test_vectors/proofOfWorkClientPwdButterflyReduced.json");
+    fn cta_resistance_test() {
+        let stonefly = ::variants::stonefly::new();
+        assert!(stonefly.algorithms.phi_is_data_dependent());
+        assert!(!stonefly.is_cta_resistant());
+
+        let dragonfly = ::default_instances::dragonfly::new();
+        assert!(!dragonfly.algorithms.phi_is_data_dependent());
+        assert!(dragonfly.is_cta_resistant());
+
+        assert!(!::variants::stonefly_full::new().is_cta_resistant());
+        assert!(::default_instances::butterfly::new().is_cta_resistant());
+        assert!(::variants::horsefly::new().is_cta_resistant());
+        // Φ with the lsb index function reads v_j with j from the state
+        assert!(!::variants::mydasfly::new().is_cta_resistant());
     }
 
-    #[test]
-    #[should_panic]
-    /// test for invalid mode
-    fn proof_of_work_client_panic_test_1() {
-        let pwd: Vec<u8> = vec!(0, 0);
-        let salt: Vec<u8> = vec!(0, 0);
-        let ad: Vec<u8> = vec!(0, 0);
-        let gamma: Vec<u8> = vec!(0, 0);
-        let out_len: u16 = 64;
-        let hash: Vec<u8> = vec!(0, 0);
-        let p = 1;
-        let mode: u8 = 3;
+    /// Dragonfly with a Blake2b truncated to 32 bytes as H.
+    #[derive(Clone, Debug)]
+    struct ShortH {
+        inner: ::default_instances::dragonfly::DragonflyAlgorithms,
+    }
 
-        let mut catena_bf = ::default_instances::butterfly::new();
+    impl Algorithms for ShortH {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h(x)[..32].to_vec()
+        }
 
-        let _result = catena_bf.proof_of_work_client(
-            pwd,
-            salt,
-            ad,
-            gamma,
-            out_len,
-            hash,
-            p,
-            mode);
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.inner.h_prime(x)
+        }
+
+        fn reset_h_prime(&mut self) {
+            self.inner.reset_h_prime();
+        }
+
+        fn gamma (&mut self, garlic:u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8> {
+            self.inner.gamma(garlic, state, gamma, k)
+        }
+
+        fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, CatenaError> {
+            self.inner.f(garlic, state, lambda, n, k)
+        }
+
+        fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+            self.inner.phi(garlic, state, mu, k)
+        }
     }
 
     #[test]
-    #[should_panic]
-    /// test for salt not found panic
-    fn proof_of_work_client_panic_test_2() {
-        let pwd: Vec<u8> = vec!(0, 0);
-        let salt: Vec<u8> = vec!(0, 0);
-        let ad: Vec<u8> = vec!(0, 0);
-        let gamma: Vec<u8> = vec!(0, 0);
-        let out_len: u16 = 64;
-        let hash: Vec<u8> = vec!(0, 0);
-        let p = 1;
-        let mode: u8 = 0;
+    fn new_checked_test() {
+        let algorithms = ::default_instances::dragonfly::new().algorithms;
+        let mut checked = Catena::new_checked(
+            algorithms.clone(), "Dragonfly", 64, 64, 21, 21, 2).unwrap();
+        assert_eq!(checked.self_test(), Ok(()));
+
+        let short = ShortH { inner: algorithms.clone() };
+        let error = Catena::new_checked(short, "Short", 64, 64, 21, 21, 2)
+            .unwrap_err();
+        assert_eq!(error, CatenaError::OutputLengthMismatch {
+            function: "H", expected: 64, actual: 32 });
+        assert_eq!(format!("{}", error),
+                   "output of H has 32 bytes, but 64 bytes are expected");
+
+        assert_eq!(
+            Catena::new_checked(algorithms.clone(), "Dragonfly", 64, 128, 21, 21, 2)
+                .unwrap_err(),
+            CatenaError::OutputLengthMismatch {
+                function: "H'", expected: 128, actual: 64 });
+        assert_eq!(
+            Catena::new_checked(
+                algorithms.clone(), "Dragonfly", 64, 96, 21, 21, 2)
+                .unwrap_err(),
+            CatenaError::InvalidParameters { k: 96, n: 64 });
+        assert_eq!(
+            Catena::new_checked(
+                algorithms.clone(), "Dragonfly", 0, 64, 21, 21, 2)
+                .unwrap_err(),
+            CatenaError::InvalidParameters { k: 64, n: 0 });
+        assert_eq!(
+            Catena::new_checked(algorithms, "Dragonfly", 64, 64, 22, 21, 2)
+                .unwrap_err(),
+            CatenaError::InvalidGarlic);
+    }
 
-        let mut catena_bf = ::default_instances::butterfly::new();
+    #[test]
+    fn generate_key_resumable_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key_identifier = b"key".to_vec();
+
+        // ten blocks, the last one truncated
+        let key = dragonfly.generate_key(
+            pwd.clone(), &ad, salt.clone(), 64, gamma.clone(), 600,
+            key_identifier.clone()).unwrap().0;
+        let state = dragonfly.generate_key_state(
+            pwd, &ad, salt, 64, gamma).unwrap();
+
+        let (first, next) = dragonfly.generate_key_resumable(
+            &state, 600, key_identifier.clone(), 0, 5);
+        assert_eq!(next, 5);
+        assert_eq!(first[..], key[..5 * 64]);
+
+        let (second, next) = dragonfly.generate_key_resumable(
+            &state, 600, key_identifier.clone(), next, 100);
+        assert_eq!(next, 10);
+        assert_eq!([first, second].concat(), key);
+
+        let (rest, next) = dragonfly.generate_key_resumable(
+            &state, 600, key_identifier, next, 5);
+        assert!(rest.is_empty());
+        assert_eq!(next, 10);
+    }
 
-        let _result = catena_bf.proof_of_work_client(
-            pwd,
-            salt,
-            ad,
-            gamma,
-            out_len,
-            hash,
-            p,
-            mode);
+    #[test]
+    fn require_gamma_test() {
+        let pwd = b"password".to_vec();
+        let salt = b"salt".to_vec();
+        let ad = b"data".to_vec();
+        let empty = Vec::new();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        assert!(dragonfly.hash(&pwd, &salt, &ad, 64, &empty).is_ok());
+
+        let mut dragonfly = dragonfly.with_require_gamma(true);
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 64, &empty),
+                   Err(CatenaError::EmptyGamma));
+        assert!(dragonfly.hash(&pwd, &salt, &ad, 64, &b"gamma".to_vec())
+                .is_ok());
+
+        let mut horsefly = ::variants::horsefly::new().with_require_gamma(true);
+        horsefly.g_low = 5;
+        horsefly.g_high = 5;
+        assert!(!horsefly.algorithms.uses_gamma());
+        assert!(horsefly.hash(&pwd, &salt, &ad, 64, &empty).is_ok());
     }
 
     #[test]
-    #[should_panic]
-    /// test for password not found panic
-    fn proof_of_work_client_panic_test_3() {
-        let pwd: Vec<u8> = vec!(0, 0);
-        let salt: Vec<u8> = vec!(0, 0);
-        let ad: Vec<u8> = vec!(0, 0);
-        let gamma: Vec<u8> = vec!(0, 0);
-        let out_len: u16 = 64;
-        let hash: Vec<u8> = vec!(0, 0);
-        let p = 1;
-        let mode: u8 = 1;
+    fn hash_with_h_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let blake2b = |x: &[u8]| ::components::hash::blake2b::hash(&x.to_vec());
+        assert_eq!(
+            dragonfly.hash_with_h(&blake2b, &pwd, &salt, &ad, 64, &gamma),
+            Ok(hash.clone()));
+
+        let reversed = |x: &[u8]| {
+            let mut h = ::components::hash::blake2b::hash(&x.to_vec());
+            h.reverse();
+            h
+        };
+        assert_ne!(
+            dragonfly.hash_with_h(&reversed, &pwd, &salt, &ad, 64, &gamma),
+            Ok(hash.clone()));
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(), hash);
+    }
 
-        let mut catena_bf = ::default_instances::butterfly::new();
+    #[test]
+    fn hash_to_writer_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+
+        let mut out: Vec<u8> = b"prefix".to_vec();
+        dragonfly.hash_to_writer(&pwd, &salt, &ad, 64, &gamma, &mut out)
+            .unwrap();
+        assert_eq!(out, [&b"prefix"[..],
+                         dragonfly.hash(&pwd, &salt, &ad, 64, &gamma)
+                             .unwrap().as_bytes()]
+                   .concat());
+
+        let mut full = [0u8; 16];
+        let mut writer = &mut full[..];
+        assert_eq!(
+            dragonfly.hash_to_writer(&pwd, &salt, &ad, 64, &gamma, &mut writer),
+            Err(CatenaError::IoFailed { kind: io::ErrorKind::WriteZero }));
+    }
 
-        let _result = catena_bf.proof_of_work_client(
-            pwd,
-            salt,
-            ad,
-            gamma,
-            out_len,
-            hash,
-            p,
-            mode);
+    fn blake2b(x: &[u8]) -> Vec<u8> {
+        ::components::hash::blake2b::hash(&x.to_vec())
     }
 
-    fn h_init_test_from_json<T: Algorithms>(
-        mut catena: ::catena::Catena<T>, test_file: String)
-    {
-        let json = ::helpers::files::open_json(test_file);
-        let unwrapped_json = json.as_ref().unwrap();
-        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+    fn blake2b_reversed(x: &[u8]) -> Vec<u8> {
+        let mut h = blake2b(x);
+        h.reverse();
+        h
+    }
 
-        for i in 0..number_of_tests {
-            let ref inputs = unwrapped_json[i]["inputs"];
-            let ref outputs = unwrapped_json[i]["outputs"]["v"];
-            let expected_1_tmp = outputs[0].to_string();
-            let expected_2_tmp = outputs[1].to_string();
-            let expected_1 = expected_1_tmp.trim_matches('\"');
-            let expected_2 = expected_2_tmp.trim_matches('\"');
+    #[test]
+    fn ad_hash_test() {
+        let ad = b"data".to_vec();
+        let tweak = ::default_instances::dragonfly::new()
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+        let same = ::default_instances::dragonfly::new().with_ad_hash(blake2b)
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+        assert_eq!(same, tweak);
+
+        let other = ::default_instances::dragonfly::new()
+            .with_ad_hash(blake2b_reversed)
+            .compute_tweak(Domain::PasswordScrambling, 64, 4, &ad);
+        assert_eq!(other[..tweak.len() - 64], tweak[..tweak.len() - 64]);
+        assert_ne!(other, tweak);
+    }
 
-            let x = inputs.parse_hex("x");
+    #[test]
+    fn zero_output_length_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 13;
+        dragonfly.g_high = 14;
+
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 0, &gamma),
+                   Err(CatenaError::ZeroOutputLength));
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 1, &gamma)
+                   .map(|hash| hash.0.len()),
+                   Ok(1));
+    }
 
-            let output = catena.h_init(x);
+    #[test]
+    fn hash_batch_test() {
+        let salt = b"salt".to_vec();
+        let ad = b"data".to_vec();
+        let gamma = b"gamma".to_vec();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 13;
+        dragonfly.g_high = 14;
+
+        let pwds: [&[u8]; 3] = [b"password", b"passw0rd", b""];
+        let hashes = dragonfly.hash_batch(&pwds, &salt, &ad, 64, &gamma)
+            .unwrap();
+        assert_eq!(hashes.len(), pwds.len());
+        for (pwd, hash) in pwds.iter().zip(hashes.iter()) {
+            assert_eq!(*hash,
+                       dragonfly.hash(&pwd.to_vec(), &salt, &ad, 64, &gamma)
+                           .unwrap());
+        }
+        assert_eq!(dragonfly.hash_batch(&[], &salt, &ad, 64, &gamma),
+                   Ok(Vec::new()));
+        assert_eq!(dragonfly.hash_batch(&pwds, &Vec::new(), &ad, 64, &gamma),
+                   Err(CatenaError::EmptySalt));
 
-            assert_eq!(output.0.to_hex_string(),
-                       expected_1.to_string().to_be_bytes().to_hex_string());
-            assert_eq!(output.1.to_hex_string(),
-                       expected_2.to_string().to_be_bytes().to_hex_string());
+        #[cfg(feature = "parallel")]
+        {
+            for threads in [1usize, 2, 4].iter() {
+                dragonfly.set_parallelism(*threads);
+                assert_eq!(
+                    dragonfly.hash_batch_parallel(&pwds, &salt, &ad, 64, &gamma),
+                    Ok(hashes.clone()));
+            }
         }
     }
 
     #[test]
-    fn h_init_test() {
-        let catena_dff = ::default_instances::dragonfly_full::new();
-        h_init_test_from_json(
-            catena_dff, "test/test_vectors/hInitAnyFull.json".to_string());
+    fn large_salt_test() {
+        let pwd = b"password".to_vec();
+        let ad = b"data".to_vec();
+        let gamma = b"gamma".to_vec();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+
+        let salt: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_eq!(hash.as_bytes().len(), 64);
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(), hash);
+
+        let mut other = salt.clone();
+        let last = other.len() - 1;
+        other[last] ^= 1;
+        assert_ne!(dragonfly.hash(&pwd, &other, &ad, 64, &gamma)
+                   .unwrap(), hash);
+
+        // the length is encoded modulo 2^16, but the salt is hashed
+        assert_ne!(dragonfly.hash(&pwd, &salt[..1 << 16].to_vec(), &ad, 64,
+                                  &gamma).unwrap(),
+                   hash);
+
+        // SaltMix hashes γ, which is often the salt
+        let hash_gamma = dragonfly.hash(&pwd, &salt, &ad, 64, &salt).unwrap();
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 64, &salt)
+                   .unwrap(), hash_gamma);
     }
 
-    fn compute_tweak_test_from_json<T: Algorithms>(
-        catena: ::catena::Catena<T>, file: &str)
-    {
-        let json = ::helpers::files::open_json(file.to_string());
-        let unwrapped_json = json.as_ref().unwrap();
-        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+    #[test]
+    fn resistance_profile_test() {
+        let dragonfly = ::default_instances::dragonfly::new().resistance_profile();
+        assert_eq!(dragonfly.graph, GraphKind::BitReversal);
+        assert!(dragonfly.memory_hard);
+        assert!(dragonfly.lambda_memory_hard);
+        assert!(dragonfly.cta_resistant);
+        assert!(!dragonfly.password_dependent_phi);
+        assert!(!dragonfly.asic_resistant);
+
+        let stonefly = ::variants::stonefly::new().resistance_profile();
+        assert!(stonefly.memory_hard);
+        assert!(stonefly.password_dependent_phi);
+        assert!(!stonefly.cta_resistant);
+        assert!(stonefly.asic_resistant);
+
+        let butterfly = ::default_instances::butterfly::new().resistance_profile();
+        assert!(butterfly.memory_hard);
+        assert!(!butterfly.lambda_memory_hard);
+    }
 
-        for i in 0..number_of_tests {
-            let ref inputs = unwrapped_json[i]["inputs"];
+    #[test]
+    fn validate_against_hash_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let stored = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_eq!(dragonfly.validate_against_hash(
+            &pwd, &salt, &ad, &gamma, &stored), Ok(()));
+
+        // content differs: wrong password
+        assert_eq!(dragonfly.validate_against_hash(
+            &b"wrong".to_vec(), &salt, &ad, &gamma, &stored),
+            Err(CatenaError::PasswordMismatch));
+
+        // no instance with n = 64 produces 80 bytes: wrong parameters
+        let long = PasswordHash([&stored.0[..], &stored.0[..16]].concat());
+        let result = dragonfly.validate_against_hash(
+            &pwd, &salt, &ad, &gamma, &long);
+        assert_eq!(result,
+                   Err(CatenaError::StoredLengthMismatch { max: 64, actual: 80 }));
+        assert_eq!(format!("{}", result.unwrap_err()),
+                   "stored hash has 80 bytes, but the instance produces 1 to 64 bytes");
+        assert_eq!(dragonfly.validate_against_hash(
+            &pwd, &salt, &ad, &gamma, &PasswordHash(Vec::new())),
+            Err(CatenaError::StoredLengthMismatch { max: 64, actual: 0 }));
+    }
 
-            let out_length = inputs.parse_u16("outLen");
-            let salt_length = inputs.parse_u16("sLen");
-            let ad = inputs.parse_utf8string("aData");
-            let domain = inputs.parse_u64("d");
+    #[test]
+    fn hash_authenticated_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key = b"mac key";
+
+        let (stored, mac) = dragonfly.hash_authenticated(
+            &pwd, &salt, &ad, 64, &gamma, key).unwrap();
+        assert_eq!(stored, dragonfly.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap());
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &stored, &mac, key), Ok(()));
+        assert_eq!(dragonfly.verify_authenticated(
+            &b"wrong".to_vec(), &salt, &ad, &gamma, &stored, &mac, key),
+            Err(CatenaError::PasswordMismatch));
+
+        // tampered associated data, hash, key or parameters
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &b"date".to_vec(), &gamma, &stored, &mac, key),
+            Err(CatenaError::MacMismatch));
+        let mut tampered = stored.clone();
+        tampered.0[0] ^= 1;
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &tampered, &mac, key),
+            Err(CatenaError::MacMismatch));
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &stored, &mac, b"other key"),
+            Err(CatenaError::MacMismatch));
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &b"sale".to_vec(), &ad, &gamma, &stored, &mac, key),
+            Err(CatenaError::MacMismatch));
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &b"gammb".to_vec(), &stored, &mac, key),
+            Err(CatenaError::MacMismatch));
+        let mut bound = dragonfly.clone().with_bind_salt_in_final(true);
+        assert_eq!(bound.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &stored, &mac, key),
+            Err(CatenaError::MacMismatch));
+        dragonfly.g_high = 10;
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &stored, &mac, key),
+            Err(CatenaError::MacMismatch));
+
+        let long_key = [0u8; 65];
+        assert_eq!(dragonfly.hash_authenticated(
+            &pwd, &salt, &ad, 64, &gamma, &long_key),
+            Err(CatenaError::MacKeyTooLong { max: 64, actual: 65 }));
+        assert_eq!(dragonfly.verify_authenticated(
+            &pwd, &salt, &ad, &gamma, &stored, &mac, &long_key),
+            Err(CatenaError::MacKeyTooLong { max: 64, actual: 65 }));
+    }
 
-            let ref outputs = unwrapped_json[i]["outputs"];
-            let expected = outputs.parse_string("res");
+    /// Algorithms whose H' outputs half of its input, so words of any size
+    /// can be used.
+    #[derive(Clone, Copy, Debug)]
+    struct HalfHPrime;
 
-            let mode: Domain;
-            if domain == 0 {
-                mode = Domain::PasswordScrambling;
-            } else if domain == 1 {
-                mode = Domain::KeyDerivation;
-            } else {
-                mode = Domain::ProofOfWork;
+    impl Algorithms for HalfHPrime {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            ::components::hash::blake2b::hash(x)
+        }
+
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            let mut output = Vec::new();
+            let mut block = x.clone();
+            while output.len() < x.len() / 2 {
+                block = ::components::hash::blake2b::hash(&block);
+                output.extend_from_slice(&block);
             }
+            output.truncate(x.len() / 2);
+            output
+        }
 
-            let output = catena.compute_tweak(
-                mode,
-                out_length,
-                salt_length,
-                &ad);
+        fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
+        }
 
-            assert_eq!(output.to_hex_string(),
-                       expected);
+        fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize,
+             k: usize) -> Result<Vec<u8>, CatenaError> {
+            ::components::graph::generic_graph::bit_reversal_hash(
+                self, garlic, state, lambda, n, k)
+        }
+
+        #[allow(unused_variables)]
+        fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            state
         }
     }
 
     #[test]
-    fn compute_tweak_test_dragonflyfull() {
-        let catena = ::default_instances::dragonfly_full::new();
-        compute_tweak_test_from_json(
-            catena, "test/test_vectors/tweakDragonflyFull.json");
+    fn k_schedule_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let expected = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+
+        // a schedule with k for every garlic is the fixed k
+        static CONSTANT: [(u8, usize); 2] = [(5, 64), (9, 64)];
+        let mut constant = dragonfly.clone().with_k_schedule(&CONSTANT);
+        assert_eq!(constant.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap(), expected);
+
+        // H' of Dragonfly only outputs 64 bytes
+        static HYBRID: [(u8, usize); 1] = [(5, 128)];
+        let mut hybrid = dragonfly.clone().with_k_schedule(&HYBRID);
+        assert_eq!(hybrid.k_for_garlic(5), 128);
+        assert_eq!(hybrid.k_for_garlic(9), 64);
+        assert_eq!(hybrid.memory_bytes(5), 2 * 32 * 128);
+        assert_eq!(hybrid.hash(&pwd, &salt, &ad, 64, &gamma),
+                   Err(CatenaError::OutputLengthMismatch {
+                       function: "H'", expected: 128, actual: 64 }));
+
+        let mut half = Catena {
+            algorithms: HalfHPrime,
+            vid: "HalfHPrime",
+            n: 64,
+            k: 64,
+            g_low: 9,
+            g_high: 9,
+            lambda: 2,
+            options: Options::default(),
+        };
+        let fixed = half.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let mut half_hybrid = half.with_k_schedule(&HYBRID);
+        let output = half_hybrid.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert_eq!(output.as_bytes().len(), 64);
+        assert!(output != fixed);
     }
 
     #[test]
-    fn compute_tweak_test_dragonfly() {
-        let catena = ::default_instances::dragonfly::new();
-        compute_tweak_test_from_json(
-            catena, "test/test_vectors/tweakDragonfly.json");
+    #[should_panic(expected = "k = 96 for garlic 5 is not a positive multiple of n = 64")]
+    fn k_schedule_invalid_test() {
+        static INVALID: [(u8, usize); 1] = [(5, 96)];
+        let _ = ::default_instances::dragonfly::new().with_k_schedule(&INVALID);
     }
 
     #[test]
-    fn compute_tweak_test_butterfly() {
-        let catena = ::default_instances::butterfly::new();
-        compute_tweak_test_from_json(
-            catena, "test/test_vectors/tweakButterfly.json");
+    fn hash_with_cost_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let mut butterfly = ::default_instances::butterfly::new();
+        butterfly.g_low = 8;
+        butterfly.g_high = 9;
+        let (hash, cost) = butterfly
+            .hash_with_cost(&pwd, &salt, &ad, 64, &gamma)
+            .unwrap();
+
+        assert_eq!(hash, butterfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+        assert_eq!(cost.memory_bytes, butterfly.memory_bytes(9));
+        assert_eq!(cost.garlic_low, 8);
+        assert_eq!(cost.garlic_high, 9);
+        // flap with (g_low + 1) / 2, g_low and g_high
+        assert_eq!(cost.h_prime_calls, Some(
+            butterfly.h_prime_invocations(4).unwrap()
+            + butterfly.h_prime_invocations(8).unwrap()
+            + butterfly.h_prime_invocations(9).unwrap()));
     }
 
     #[test]
-    fn compute_tweak_test_butterflyfull() {
-        let catena = ::default_instances::butterfly_full::new();
-        compute_tweak_test_from_json(
-            catena, "test/test_vectors/tweakButterflyFull.json");
+    fn hash_kdf_domain_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key_id = b"key".to_vec();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+
+        let x = dragonfly.hash_kdf_domain(&pwd, &salt, &ad, 64, &gamma)
+            .unwrap();
+        assert_eq!(x.as_bytes().len(), 64);
+        assert_ne!(x.as_bytes(),
+                   dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap()
+                       .as_bytes());
+        assert_eq!(x, dragonfly.generate_key_state(
+            pwd.clone(), &ad, salt.clone(), 64, gamma.clone()).unwrap());
+
+        let expansion = CounterExpand { key_identifier: key_id.clone(), n: 64 };
+        assert_eq!(
+            dragonfly.generate_key(pwd.clone(), &ad, salt.clone(), 64,
+                                   gamma.clone(), 100, key_id).unwrap().0,
+            expansion.expand_blocks(&dragonfly.algorithms, &x.0, 100, 0, 2));
     }
 
-    fn catena_test_from_json<T: Algorithms>(
-        mut catena: ::catena::Catena<T>, file: &str)
-    {
-        let json = ::helpers::files::open_json(file.to_string());
-        let unwrapped_json = json.as_ref().unwrap();
-        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
-
-        for n in 0..number_of_tests {
-            let ref inputs = unwrapped_json[n]["inputs"];
+    #[test]
+    fn then_kdf_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let key_id = b"key".to_vec();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let mut butterfly = ::default_instances::butterfly::new();
+        butterfly.g_low = 5;
+        butterfly.g_high = 5;
+
+        let hash = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let expected = butterfly.generate_key(
+            hash.0, &ad, salt.clone(), 64, gamma.clone(), 100, key_id.clone())
+                .unwrap();
+
+        let mut chained = dragonfly.then_kdf(butterfly);
+        let key = chained.hash(&pwd, &salt, &ad, 64, &gamma, 100, &key_id)
+            .unwrap();
+        assert_eq!(key, expected);
+        assert_eq!(key.as_bytes().len(), 100);
+    }
 
-            let pwd = inputs.parse_hex("pwd");
-            let salt = inputs.parse_hex("salt");
-            let gamma = inputs.parse_hex("gamma");
-            let ad = inputs.parse_hex("aData");
-            let len = inputs.parse_u16("outputLength");
+    #[test]
+    fn dyn_catena_test() {
+        let mut instances: Vec<Box<dyn DynCatena>> = vec![
+            Box::new(::default_instances::dragonfly::new()),
+            Box::new(::default_instances::butterfly::new()),
+        ];
+        for instance in instances.iter_mut() {
+            instance.set_garlic(9, 9);
+        }
 
-            let ref outputs = unwrapped_json[n]["outputs"];
-            let expected = outputs.parse_string("res");
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let mut butterfly = ::default_instances::butterfly::new();
+        butterfly.g_low = 9;
+        butterfly.g_high = 9;
+
+        assert_eq!(instances[0].vid(), "Dragonfly");
+        assert_eq!(instances[1].vid(), "Butterfly");
+        assert_eq!(instances[0].params(), dragonfly.params());
+        assert_eq!(instances[1].params(), CatenaParams {
+            vid: "Butterfly",
+            n: 64,
+            k: 64,
+            g_low: 9,
+            g_high: 9,
+            lambda: 4,
+        });
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+        assert_eq!(instances[0].hash(&pwd, &salt, &ad, 64, &gamma),
+                   dragonfly.hash(&pwd, &salt, &ad, 64, &gamma));
+        assert_eq!(instances[1].hash(&pwd, &salt, &ad, 64, &gamma),
+                   butterfly.hash(&pwd, &salt, &ad, 64, &gamma));
+    }
 
-            assert_eq!(
-                catena.hash(
-                    &pwd,
-                    &salt,
-                    &ad,
-                    len,
-                    &gamma).to_hex_string(),
-                expected);
+    #[test]
+    fn output_bounds_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        assert_eq!(dragonfly.output_bounds(), (1, 64));
+
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let (min, max) = dragonfly.output_bounds();
+        for &length in [min, max].iter() {
+            let hash = dragonfly.hash(
+                &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(),
+                length as u16, &b"gamma".to_vec());
+            assert_eq!(hash.map(|hash| hash.0.len()), Ok(length));
         }
+        assert_eq!(dragonfly.hash(
+            &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(),
+            max as u16 + 1, &b"gamma".to_vec()),
+            Err(CatenaError::OutputTooLong { requested: max + 1, max: max }));
     }
 
     #[test]
-    #[cfg(feature="fulltest")]
-    fn butterfly_test_from_json() {
-        let test_catena = ::default_instances::butterfly::new();
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaButterfly.json");
+    fn config_fingerprint_test() {
+        let dragonfly = ::default_instances::dragonfly::new();
+        let fingerprint = dragonfly.config_fingerprint();
+        assert_eq!(fingerprint, dragonfly.config_fingerprint());
+        assert_eq!(fingerprint,
+                   ::default_instances::dragonfly::new().config_fingerprint());
+        assert_ne!(fingerprint, ::default_instances::dragonfly_full::new()
+            .config_fingerprint());
+
+        let mut reduced = ::default_instances::dragonfly::new();
+        reduced.g_low = 9;
+        assert_ne!(fingerprint, reduced.config_fingerprint());
+
+        let bound = ::default_instances::dragonfly::new()
+            .with_bind_salt_in_final(true);
+        assert_ne!(fingerprint, bound.config_fingerprint());
+        let with_h_id = ::default_instances::dragonfly::new().with_h_id(1);
+        assert_ne!(fingerprint, with_h_id.config_fingerprint());
+
+        // options which do not change the output
+        let gamma = ::default_instances::dragonfly::new()
+            .with_require_gamma(true);
+        assert_eq!(fingerprint, gamma.config_fingerprint());
     }
 
     #[test]
-    fn butterfly_reduced_test_from_json() {
-        let mut test_catena = ::default_instances::butterfly::new();
-        test_catena.g_low = 9;
-        test_catena.g_high = 9;
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaButterflyReduced.json");
+    fn max_init_blocks_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        // 2k/n = 32768 hashes are rejected before the state is reserved
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.k = 1 << 20;
+        dragonfly.g_low = 1;
+        dragonfly.g_high = 1;
+        let error = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma);
+        assert_eq!(error, Err(CatenaError::InitialValueTooLarge {
+            blocks: 32768,
+            max: DEFAULT_MAX_INIT_BLOCKS,
+        }));
+        assert_eq!(format!("{}", error.unwrap_err()),
+                   "initial value needs 32768 hashes, but at most 1024 are \
+                    allowed");
+
+        // a lower cap rejects the 2 hashes of Dragonfly, but does not change
+        // the output as long as it allows them
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let expected = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let mut capped = dragonfly.clone().with_max_init_blocks(2);
+        assert_eq!(capped.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap(), expected);
+        let mut capped = dragonfly.with_max_init_blocks(1);
+        assert_eq!(capped.hash(&pwd, &salt, &ad, 64, &gamma),
+                   Err(CatenaError::InitialValueTooLarge { blocks: 2, max: 1 }));
     }
 
     #[test]
-    #[cfg(feature="fulltest")]
-    fn butterflyfull_test_from_json() {
-        let test_catena = ::default_instances::butterfly_full::new();
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaButterflyFull.json");
+    fn hash_peppered_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let plain = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma);
+
+        assert_eq!(dragonfly.hash_peppered(&pwd, &salt, b"", &ad, 64, &gamma),
+                   plain);
+
+        let peppered = dragonfly.hash_peppered(
+            &pwd, &salt, b"pepper", &ad, 64, &gamma);
+        assert!(peppered.is_ok());
+        assert!(peppered != plain);
+        assert!(dragonfly.hash_peppered(&pwd, &salt, b"pepped", &ad, 64,
+                                        &gamma) != peppered);
+        assert_eq!(peppered, dragonfly.hash(
+            &b"passwordpepper\x06\x00".to_vec(), &salt, &ad, 64, &gamma));
+
+        // the length separates the pepper from the password
+        assert!(dragonfly.hash_peppered(&b"passwordp".to_vec(), &salt,
+                                        b"epper", &ad, 64, &gamma) != peppered);
     }
 
     #[test]
-    fn butterflyfull_reduced_test_from_json() {
-        let mut test_catena = ::default_instances::butterfly_full::new();
-        test_catena.g_low = 9;
-        test_catena.g_high = 9;
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaButterflyFullReduced.json");
+    fn hash_result_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+
+        assert_eq!(dragonfly.hash(&pwd, &Vec::new(), &ad, 64, &gamma),
+                   Err(CatenaError::EmptySalt));
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 65, &gamma),
+                   Err(CatenaError::OutputTooLong { requested: 65, max: 64 }));
+        assert_eq!(dragonfly.hash(&pwd, &salt, &ad, 64, &gamma),
+                   Ok(PasswordHash(dragonfly.hash_unchecked(
+                       &pwd, &salt, &ad, 64, &gamma))));
+        assert_eq!(
+            dragonfly.generate_key(pwd.clone(), &ad, Vec::new(), 64,
+                                   gamma.clone(), 32, Vec::new()),
+            Err(CatenaError::EmptySalt));
+        assert_eq!(
+            dragonfly.keyed_hashing(pwd.clone(), Vec::new(), &ad, 64, &gamma,
+                                    b"uid".to_vec(), 9, &b"key".to_vec()),
+            Err(CatenaError::EmptySalt));
+
+        dragonfly.k = 96;
+        let result = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma);
+        assert_eq!(result,
+                   Err(CatenaError::InvalidParameters { k: 96, n: 64 }));
+        assert_eq!(format!("{}", result.unwrap_err()),
+                   "k = 96 is not a positive multiple of n = 64");
     }
 
     #[test]
-    #[cfg(feature="fulltest")]
-    fn dragonfly_test_from_json() {
-        let test_catena = ::default_instances::dragonfly::new();
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaDragonfly.json");
+    fn self_test_default_instances() {
+        assert_eq!(::default_instances::dragonfly::new().self_test(), Ok(()));
+        assert_eq!(::default_instances::dragonfly_full::new().self_test(), Ok(()));
+        assert_eq!(::default_instances::butterfly::new().self_test(), Ok(()));
+        assert_eq!(::default_instances::butterfly_full::new().self_test(), Ok(()));
     }
 
     #[test]
-    fn dragonfly_reduced_test_from_json() {
-        let mut test_catena = ::default_instances::dragonfly::new();
-        test_catena.g_low = 14;
-        test_catena.g_high = 14;
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaDragonflyReduced.json");
+    fn self_test_variants() {
+        assert_eq!(::variants::stonefly::new().self_test(), Ok(()));
+        assert_eq!(::variants::stonefly_full::new().self_test(), Ok(()));
+        assert_eq!(::variants::horsefly::new().self_test(), Ok(()));
+        assert_eq!(::variants::horsefly_full::new().self_test(), Ok(()));
+        assert_eq!(::variants::mydasfly::new().self_test(), Ok(()));
+        assert_eq!(::variants::mydasfly_full::new().self_test(), Ok(()));
+        assert_eq!(::variants::lanternfly::new().self_test(), Ok(()));
+        assert_eq!(::variants::lanternfly_full::new().self_test(), Ok(()));
+        assert_eq!(::variants::dragonfly_cta::new().self_test(), Ok(()));
     }
 
     #[test]
-    #[cfg(feature="fulltest")]
-    fn dragonflyfull_test_from_json() {
-        let test_catena = ::default_instances::dragonfly_full::new();
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaDragonflyFull.json");
+    fn self_test_restores_garlic() {
+        let mut catena_df = ::default_instances::dragonfly::new();
+        let _ = catena_df.self_test();
+        assert_eq!(catena_df.g_low, 21);
+        assert_eq!(catena_df.g_high, 21);
     }
 
     #[test]
-    fn dragonflyfull_reduced_test_from_json() {
-        let mut test_catena = ::default_instances::dragonfly_full::new();
-        test_catena.g_low = 14;
-        test_catena.g_high = 14;
-        catena_test_from_json(
-            test_catena, "test/test_vectors/catenaDragonflyFullReduced.json");
+    fn self_test_modified_instance() {
+        let mut catena_df = ::default_instances::dragonfly::new();
+        catena_df.lambda = 1;
+        assert_eq!(catena_df.self_test(), Err(CatenaError::SelfTestFailed));
+    }
+
+    #[test]
+    fn self_test_unknown_instance() {
+        let mut catena_df = ::default_instances::dragonfly::new();
+        catena_df.vid = "Custom";
+        catena_df.lambda = 1;
+        assert_eq!(catena_df.self_test(), Ok(()));
     }
 
     fn keyed_hash_test_from_json<T: Algorithms>(
@@ -1286,7 +5375,7 @@ mod tests {
                     &gamma,
                     uid,
                     g,
-                    &key).to_hex_string(),
+                    &key).unwrap().to_hex_string(),
                 expected);
         }
     }
@@ -1364,11 +5453,11 @@ mod tests {
                 salt,
                 &ad,
                 len,
-                &gamma);
+                &gamma).unwrap();
 
             let server_result = catena.server_final(
                 client_result,
-                len);
+                len).unwrap();
 
             assert_eq!(server_result.to_hex_string(),
                        expected);
@@ -1448,7 +5537,7 @@ mod tests {
                 salt,
                 &ad,
                 len,
-                &gamma);
+                &gamma).unwrap();
 
             assert_eq!(client_result.to_hex_string(),
                        expected);
@@ -1502,7 +5591,7 @@ mod tests {
 
             let result = catena.server_final(
                 hash,
-                len);
+                len).unwrap();
 
             assert_eq!(result.to_hex_string(),
             expected);
@@ -1601,7 +5690,7 @@ mod tests {
                     output_length,
                     gamma,
                     key_size,
-                    ki).to_hex_string(),
+                    ki).unwrap().to_hex_string(),
             expected);
         }
     }
@@ -1682,7 +5771,7 @@ mod tests {
                 g_old,
                 g_new,
                 &gamma,
-                out_length);
+                out_length).unwrap();
 
             assert_eq!(output.to_hex_string(),
                        expected);
@@ -1690,8 +5779,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn ci_update_panic_test() {
+    fn ci_update_invalid_garlic_test() {
         let mut catena = ::default_instances::dragonfly::new();
 
         let old_hash = vec![0u8];
@@ -1700,12 +5788,22 @@ mod tests {
         let gamma = vec![0u8];
         let out_length = 1;
 
-        let _output = catena.client_independent_update(
-            old_hash,
-            g_old,
-            g_new,
-            &gamma,
-            out_length);
+        assert_eq!(
+            catena.client_independent_update(
+                old_hash.clone(),
+                g_old,
+                g_new,
+                &gamma,
+                out_length),
+            Err(CatenaError::InvalidGarlic));
+        assert_eq!(
+            catena.client_independent_update(
+                old_hash,
+                g_old,
+                g_old,
+                &gamma,
+                out_length),
+            Err(CatenaError::InvalidGarlic));
     }
 
     #[test]
@@ -1748,7 +5846,7 @@ mod tests {
                 &gamma,
                 out_length,
                 &server_key,
-                &uid);
+                &uid).unwrap();
 
             assert_eq!(output.to_hex_string(),
                        expected, "test #{:?} failed", i);
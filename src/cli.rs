@@ -0,0 +1,54 @@
+//! Parsing of command line arguments shared by the examples and other
+//! command line tools.
+
+use catena::CatenaError;
+use catena::DynCatena;
+use registry::Variant;
+
+/// The largest garlic accepted by `parse_garlic`.
+pub const MAX_GARLIC: u8 = 63;
+
+/// Create an instance of the default instance or variant with the version ID
+/// `name`, ignoring case. Returns `None` for unknown names.
+pub fn parse_instance(name: &str) -> Option<Box<dyn DynCatena>> {
+    Variant::from_name(name).map(|variant| variant.instance())
+}
+
+/// Parse a garlic between 0 and `MAX_GARLIC`.
+pub fn parse_garlic(s: &str) -> Result<u8, CatenaError> {
+    match s.trim().parse::<u8>() {
+        Ok(garlic) if garlic <= MAX_GARLIC => Ok(garlic),
+        _ => Err(CatenaError::InvalidGarlic),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_instance_test() {
+        let instance = parse_instance("dragonfly").unwrap();
+        assert_eq!(instance.vid(), "Dragonfly");
+        assert_eq!(instance.garlic(), (21, 21));
+
+        assert_eq!(parse_instance("Lanternfly-Full").unwrap().vid(),
+                   "Lanternfly-Full");
+    }
+
+    #[test]
+    fn parse_instance_unknown_test() {
+        assert!(parse_instance("firefly").is_none());
+        assert!(parse_instance("").is_none());
+    }
+
+    #[test]
+    fn parse_garlic_test() {
+        assert_eq!(parse_garlic("18"), Ok(18));
+        assert_eq!(parse_garlic("63"), Ok(63));
+        assert_eq!(parse_garlic("64"), Err(CatenaError::InvalidGarlic));
+        assert_eq!(parse_garlic("eighteen"), Err(CatenaError::InvalidGarlic));
+        assert_eq!(parse_garlic("-1"), Err(CatenaError::InvalidGarlic));
+        assert_eq!(parse_garlic(""), Err(CatenaError::InvalidGarlic));
+    }
+}
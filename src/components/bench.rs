@@ -0,0 +1,82 @@
+//! Micro-benchmarks of the hash functions H and H'
+//!
+//! The time of a full hash is dominated by the calls of H', see
+//! `Catena::h_prime_invocations`, so timing the candidates for H' on the
+//! target machine allows to estimate the time of a variant before computing
+//! it. `Catena::profile` measures a full hash instead.
+extern crate rand;
+
+use components::fasthash::blake2b1::Blake2b1;
+use self::rand::Rng;
+
+use std::cell::RefCell;
+use std::hint::black_box;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The number of random inputs `time_hash` cycles through.
+const INPUTS: usize = 16;
+
+/// Time `iters` calls of the hash function `f` on random inputs of
+/// `input_len` bytes.
+///
+/// The inputs are generated before the timing starts. The duration includes
+/// the call through `f`, e.g. a copy of the input into a `Vec`, which is
+/// small compared to the hash functions of this crate.
+pub fn time_hash(
+    f: &dyn Fn(&[u8]) -> Vec<u8>,
+    input_len: usize,
+    iters: usize
+) -> Duration {
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Vec<u8>> = (0..INPUTS.min(iters.max(1)))
+        .map(|_| {
+            let mut input = vec![0u8; input_len];
+            rng.fill_bytes(&mut input);
+            input
+        })
+        .collect();
+
+    let start = Instant::now();
+    for i in 0..iters {
+        // keep the compiler from dropping calls with unused outputs
+        let _ = black_box(f(black_box(&inputs[i % inputs.len()])));
+    }
+    start.elapsed()
+}
+
+/// Time `iters` calls of Blake2b, the H of all instances, on inputs of
+/// 128 bytes.
+pub fn time_blake2b(iters: usize) -> Duration {
+    time_hash(&|x| ::components::hash::blake2b::hash(&x.to_vec()), 128, iters)
+}
+
+/// Time `iters` calls of the Argon2 compression function with G = G_B, the
+/// H' of Stonefly and Lanternfly, on inputs of 2048 bytes.
+pub fn time_cf_argon2_gb(iters: usize) -> Duration {
+    time_hash(
+        &|x| ::components::fasthash::cf_argon2::cf_argon2_gb(&x.to_vec()),
+        2048, iters)
+}
+
+/// Time `iters` calls of Blake2b-1, the H' of Dragonfly and Butterfly, on
+/// inputs of 128 bytes. The calls continue the state of one `Blake2b1`, as
+/// within a flap.
+pub fn time_blake2b1(iters: usize) -> Duration {
+    let blake2b_1 = RefCell::new(Blake2b1::default());
+    time_hash(&|x| blake2b_1.borrow_mut().hash(&x.to_vec()), 128, iters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_hash_test() {
+        assert!(time_blake2b(10) > Duration::new(0, 0));
+        assert!(time_cf_argon2_gb(10) > Duration::new(0, 0));
+        assert!(time_blake2b1(10) > Duration::new(0, 0));
+        // no calls for zero iterations
+        let _ = time_hash(&|_| panic!("called"), 16, 0);
+    }
+}
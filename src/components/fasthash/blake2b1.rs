@@ -26,6 +26,14 @@ const BLAKE2B_SIGMA: [[usize; 16]; 12] =
 
 const BLOCK_LENGTH_BYTES: u64 = 128;
 
+#[cfg(feature = "debug-trace")]
+thread_local! {
+    /// The rounds of the calls of `Blake2b1::hash` on this thread, see
+    /// `Blake2b1::round_trace`.
+    static ROUND_TRACE: ::std::cell::RefCell<Vec<u8>> =
+        ::std::cell::RefCell::new(Vec::new());
+}
+
 /// The internal state of Blake2b_1. This has to be a field of the algorithms of
 /// a Catena instance.
 ///
@@ -67,6 +75,25 @@ impl Blake2b1 {
         self.r = (self.r + 1) % 12;
     }
 
+    /// The round `r` of every call of `hash` on the current thread since the
+    /// last `clear_round_trace`, in order. This is only available with the
+    /// `debug-trace` feature.
+    ///
+    /// Within a flap, the rounds of each stage have to start at 0 and
+    /// increase by one modulo 12, as `reset_h_prime` is called between the
+    /// stages. The trace is kept per thread, not per instance, as Blake2b1
+    /// is `Copy`.
+    #[cfg(feature = "debug-trace")]
+    pub fn round_trace() -> Vec<u8> {
+        ROUND_TRACE.with(|trace| trace.borrow().clone())
+    }
+
+    /// Clear the trace of `round_trace` for the current thread.
+    #[cfg(feature = "debug-trace")]
+    pub fn clear_round_trace() {
+        ROUND_TRACE.with(|trace| trace.borrow_mut().clear());
+    }
+
     /// Reset the internal state of Blake2b_1.
     pub fn reset(&mut self) {
         self.r = 0;
@@ -79,21 +106,38 @@ impl Blake2b1 {
     }
 
     /// Call the reduced hash function Blake2b_1 and increase the internal round
-    /// counter `r` by 1. The input x has to be of length 128.
+    /// counter `r` by 1.
+    ///
+    /// The input is absorbed in blocks of 128 bytes. Each block is compressed
+    /// with the current round, so an input of exactly 128 bytes is a single
+    /// call of the compression function. Inputs of several blocks allow using
+    /// Blake2b_1 as H' with `k > 64`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `x` is not a non-zero multiple of 128.
     pub fn hash(&mut self, x: &Vec<u8>) -> Vec<u8> {
+        assert!(!x.is_empty() && x.len() % BLOCK_LENGTH_BYTES as usize == 0,
+                "Blake2b1 input length must be a non-zero multiple of {} \
+                 bytes, got {}", BLOCK_LENGTH_BYTES, x.len());
+
+        for block in x.chunks(BLOCK_LENGTH_BYTES as usize) {
+            self.t_0 = self.t_0.wrapping_add(BLOCK_LENGTH_BYTES);
+            if self.t_0 == 0 {
+                self.t_1 += 1;
+            }
 
-        self.t_0 += BLOCK_LENGTH_BYTES;
-        if self.t_0 == 0 {
-            self.t_1 += 1;
+            self.compress(block);
         }
 
-        self.compress(x);
-
         let mut out: Vec<u8> = Vec::new();
         for i in 0..self.h.len() {
             out.append(&mut u64_to_bytes(self.h[i]).to_vec());
         }
 
+        #[cfg(feature = "debug-trace")]
+        ROUND_TRACE.with(|trace| trace.borrow_mut().push(self.r));
+
         self.increase_r();
 
         out
@@ -210,6 +254,36 @@ mod tests {
     use bytes::Bytes;
     use bytes::HexRepresentation;
 
+    #[test]
+    #[cfg(feature = "debug-trace")]
+    fn round_trace_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+
+        Blake2b1::clear_round_trace();
+        let _ = dragonfly.hash(&b"password".to_vec(), &b"salt".to_vec(),
+                               &b"data".to_vec(), 64, &b"gamma".to_vec())
+            .unwrap();
+        let trace = Blake2b1::round_trace();
+
+        // flap with (g_low + 1) / 2 and with g_low
+        assert_eq!(trace.len() as u64,
+                   dragonfly.h_prime_invocations(5).unwrap()
+                   + dragonfly.h_prime_invocations(9).unwrap());
+
+        // the 2^g words of the state, SaltMix and the λ(2^g - 1) calls of F
+        // each start with round 0
+        let mut expected: Vec<u8> = Vec::new();
+        for &stage in [32, 16, 2 * 31, 512, 128, 2 * 511].iter() {
+            expected.extend((0..stage).map(|i| (i % 12) as u8));
+        }
+        assert_eq!(trace, expected);
+
+        Blake2b1::clear_round_trace();
+        assert!(Blake2b1::round_trace().is_empty());
+    }
+
     #[test]
     fn blake2b1_test() {
         let test_file = "test/test_vectors/blake2b1.json".to_string();
@@ -237,10 +311,10 @@ mod tests {
 
             blake2b1.set_r(round);
 
-            let output = blake2b1.hash(&data.to_string().to_be_bytes());
+            let output = blake2b1.hash(&data.to_string().to_be_vec());
 
             assert_eq!(output.to_hex_string(),
-                       expected.to_string().to_be_bytes().to_hex_string());
+                       expected.to_string().to_be_vec().to_hex_string());
         }
     }
 
@@ -252,4 +326,46 @@ mod tests {
         }
         assert_eq!(blake2b1.r, 3);
     }
+
+    #[test]
+    fn hash_one_block_test() {
+        let mut blake2b1: Blake2b1 = Default::default();
+        let block: Vec<u8> = (0..128).map(|i| i as u8).collect();
+
+        let first = blake2b1.hash(&block);
+        assert_eq!(first.len(), 64);
+        assert_eq!(blake2b1.r, 1);
+
+        blake2b1.reset();
+        assert_eq!(blake2b1.hash(&block), first);
+    }
+
+    #[test]
+    fn hash_two_blocks_test() {
+        let first_block: Vec<u8> = (0..128).map(|i| i as u8).collect();
+        let second_block: Vec<u8> = (128..256).map(|i| i as u8).collect();
+        let input = [&first_block[..], &second_block[..]].concat();
+
+        let mut blake2b1: Blake2b1 = Default::default();
+        let output = blake2b1.hash(&input);
+        assert_eq!(output.len(), 64);
+        assert_eq!(blake2b1.r, 1);
+        assert_eq!(blake2b1.t_0, 256);
+
+        // both blocks are absorbed
+        blake2b1.reset();
+        assert_ne!(blake2b1.hash(&first_block), output);
+        blake2b1.reset();
+        assert_ne!(blake2b1.hash(&second_block), output);
+        blake2b1.reset();
+        let changed = [&second_block[..], &second_block[..]].concat();
+        assert_ne!(blake2b1.hash(&changed), output);
+    }
+
+    #[test]
+    #[should_panic(expected = "got 100")]
+    fn hash_invalid_length_test() {
+        let mut blake2b1: Blake2b1 = Default::default();
+        let _ = blake2b1.hash(&vec![0; 100]);
+    }
 }
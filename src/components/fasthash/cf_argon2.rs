@@ -20,6 +20,28 @@ pub fn cf_argon2_gb(
     cf_argon2_wrapper(x, &permute_gb)
 }
 
+/// Compression function of Argon2 with G = G_B reduced to the row pass
+/// The input `x` has to be of length 2048.
+///
+/// This omits the second application of the permutation to the columns of
+/// the block, so each 128-byte row of the output only depends on the same
+/// row of the input. It is clearly weaker than `cf_argon2_gb` and only meant
+/// for benchmarking how much of the cost and the diffusion of the
+/// compression function is due to the column pass.
+pub fn cf_argon2_gb_reduced(
+    x: &Vec<u8>
+) -> Vec<u8> {
+    let x_len = x.len();
+    if x_len != 2048 {
+        panic!("Input length has to be 2048 but is {:?}.", x_len);
+    }
+    let r = ::helpers::vectors::xor(
+        (&x[..x_len / 2]).to_vec(), (&x[x_len / 2..]).to_vec());
+    let mut q = update_rows(&r, &permute_gb);
+    q.reverse_words(8);
+    ::helpers::vectors::xor(r, q)
+}
+
 /// Wrapper for `cf_argon2` with one 2048 byte input instead of two 1024 byte inputs.
 fn cf_argon2_wrapper(
     x: &Vec<u8>,
@@ -49,28 +71,7 @@ fn cf_argon2(
 ) -> Vec<u8> {
     let r = ::helpers::vectors::xor(x, y);
 
-    let mut q: Vec<u8> = Vec::new();
-    // update rows
-    for i in 0..8 {
-        let j = i * 16;
-        q.append(&mut p(
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 1), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 2), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 3), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 4), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 5), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 6), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 7), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 8), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 9), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 10), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 11), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 12), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 13), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 14), 0),
-                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 15), 0)));
-    }
+    let mut q = update_rows(&r, &p);
     // update columns
     for i in 0..8 {
         let j = i * 2;
@@ -105,6 +106,37 @@ fn cf_argon2(
     result
 }
 
+/// The row pass of the compression function: the permutation `p` applied
+/// to each of the 8 rows of 16 words of `r`.
+fn update_rows(
+    r: &Vec<u8>,
+    p: &Fn(u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64)
+        -> Vec<u8>
+) -> Vec<u8> {
+    let mut q: Vec<u8> = Vec::new();
+    for i in 0..8 {
+        let j = i * 16;
+        q.append(&mut p(
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 1), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 2), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 3), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 4), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 5), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 6), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 7), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 8), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 9), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 10), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 11), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 12), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 13), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 14), 0),
+                ::helpers::conversions::bytes_to_u64(&r.get_word(8, j + 15), 0)));
+    }
+    q
+}
+
 fn permute(
     mut v0: u64,
     mut v1: u64,
@@ -166,22 +198,22 @@ fn permute(
     v14 = new_values.3;
 
     let mut result: Vec<u8> = Vec::new();
-    result.append(&mut v0.to_be_bytes());
-    result.append(&mut v1.to_be_bytes());
-    result.append(&mut v2.to_be_bytes());
-    result.append(&mut v3.to_be_bytes());
-    result.append(&mut v4.to_be_bytes());
-    result.append(&mut v5.to_be_bytes());
-    result.append(&mut v6.to_be_bytes());
-    result.append(&mut v7.to_be_bytes());
-    result.append(&mut v8.to_be_bytes());
-    result.append(&mut v9.to_be_bytes());
-    result.append(&mut v10.to_be_bytes());
-    result.append(&mut v11.to_be_bytes());
-    result.append(&mut v12.to_be_bytes());
-    result.append(&mut v13.to_be_bytes());
-    result.append(&mut v14.to_be_bytes());
-    result.append(&mut v15.to_be_bytes());
+    result.append(&mut v0.to_be_vec());
+    result.append(&mut v1.to_be_vec());
+    result.append(&mut v2.to_be_vec());
+    result.append(&mut v3.to_be_vec());
+    result.append(&mut v4.to_be_vec());
+    result.append(&mut v5.to_be_vec());
+    result.append(&mut v6.to_be_vec());
+    result.append(&mut v7.to_be_vec());
+    result.append(&mut v8.to_be_vec());
+    result.append(&mut v9.to_be_vec());
+    result.append(&mut v10.to_be_vec());
+    result.append(&mut v11.to_be_vec());
+    result.append(&mut v12.to_be_vec());
+    result.append(&mut v13.to_be_vec());
+    result.append(&mut v14.to_be_vec());
+    result.append(&mut v15.to_be_vec());
     result
 }
 
@@ -500,6 +532,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cf_argon2_gb_reduced_test() {
+        let input: Vec<u8> = (0..2048).map(|i| (i * 7 % 251) as u8).collect();
+        let reduced = cf_argon2_gb_reduced(&input);
+        assert_eq!(reduced.len(), 1024);
+        assert_ne!(reduced, cf_argon2_gb(&input));
+
+        // without the column pass, a change of the first row of the input
+        // only changes the first row of the output
+        let mut changed = input.clone();
+        changed[0] ^= 1;
+        let reduced_changed = cf_argon2_gb_reduced(&changed);
+        assert_ne!(reduced_changed[..128], reduced[..128]);
+        assert_eq!(reduced_changed[128..], reduced[128..]);
+        assert_ne!(cf_argon2_gb(&changed)[128..], cf_argon2_gb(&input)[128..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cf_argon2_gb_reduced_panic_test() {
+        let _out = cf_argon2_gb_reduced(&vec![0u8; 1024]);
+    }
+
     #[test]
     #[should_panic]
     fn cf_argon_gl_panic_test() {
@@ -1,4 +1,145 @@
 //! Implementations for the reduced hash function H'.
 
+use std::fmt;
+
 pub mod blake2b1;
 pub mod cf_argon2;
+
+/// A hash function with its output truncated to a fixed length, which can be
+/// used as H'.
+///
+/// This is useful for instances where H' is the same function as H but with a
+/// shorter output. Unlike Blake2b1, it has no internal state, so resetting H' is
+/// not necessary.
+///
+/// ```
+/// use catena::components::fasthash::Truncated;
+///
+/// let mut h_prime = Truncated::new(
+///     |x: &Vec<u8>| catena::components::hash::blake2b::hash(x), 32);
+/// assert_eq!(h_prime.hash(&b"input".to_vec()).len(), 32);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Truncated<F: Fn(&Vec<u8>) -> Vec<u8>> {
+    hash_function: F,
+    output_length: usize,
+}
+
+impl<F: Fn(&Vec<u8>) -> Vec<u8>> Truncated<F> {
+    /// Wrap `hash_function`, truncating its output to `output_length` bytes.
+    pub fn new(hash_function: F, output_length: usize) -> Truncated<F> {
+        Truncated {
+            hash_function: hash_function,
+            output_length: output_length,
+        }
+    }
+
+    /// The output length in bytes.
+    pub fn output_length(&self) -> usize {
+        self.output_length
+    }
+
+    /// Hash `x` and truncate the output. If the hash function has a shorter
+    /// output, it is returned unchanged.
+    pub fn hash(&mut self, x: &Vec<u8>) -> Vec<u8> {
+        let mut output = (self.hash_function)(x);
+        output.truncate(self.output_length);
+        output
+    }
+}
+
+impl<F: Fn(&Vec<u8>) -> Vec<u8>> fmt::Debug for Truncated<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Truncated")
+            .field("output_length", &self.output_length)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catena::Algorithms;
+    use helpers::fixtures::test_inputs;
+
+    type HashFunction = fn(&Vec<u8>) -> Vec<u8>;
+
+    fn blake2b_32(x: &Vec<u8>) -> Vec<u8> {
+        let mut output = ::components::hash::blake2b::hash(x);
+        output.truncate(32);
+        output
+    }
+
+    /// An instance with H = H' = Blake2b truncated to 32 bytes.
+    #[derive(Clone, Copy, Debug)]
+    struct TruncatedAlgorithms {
+        h_prime: Truncated<HashFunction>,
+    }
+
+    impl Algorithms for TruncatedAlgorithms {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            blake2b_32(x)
+        }
+
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.h_prime.hash(x)
+        }
+
+        #[allow(unused_variables)]
+        fn gamma (&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            state
+        }
+
+        fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize,
+              k: usize) -> Result<Vec<u8>, ::catena::CatenaError> {
+            ::components::graph::generic_graph::bit_reversal_hash(
+                self, garlic, state, lambda, n, k)
+        }
+
+        #[allow(unused_variables)]
+        fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            state
+        }
+    }
+
+    #[test]
+    fn truncated_test() {
+        let x = b"The quick brown fox jumps over the lazy dog".to_vec();
+        let full = ::components::hash::blake2b::hash(&x);
+
+        let blake2b: HashFunction = ::components::hash::blake2b::hash;
+        let mut truncated = Truncated::new(blake2b, 32);
+        assert_eq!(truncated.output_length(), 32);
+        assert_eq!(truncated.hash(&x), full[..32].to_vec());
+
+        let mut longer = Truncated::new(blake2b, 100);
+        assert_eq!(longer.hash(&x), full);
+    }
+
+    #[test]
+    fn truncated_catena_test() {
+        let blake2b: HashFunction = ::components::hash::blake2b::hash;
+        let mut test_catena = ::catena::Catena {
+            algorithms: TruncatedAlgorithms {
+                h_prime: Truncated::new(blake2b, 32),
+            },
+            vid: "Truncated",
+            n: 32,
+            k: 32,
+            g_low: 9,
+            g_high: 9,
+            lambda: 2,
+            options: ::catena::Options::default(),
+        };
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let hash = test_catena.hash(&pwd, &salt, &ad, 32, &gamma).unwrap();
+        assert_eq!(hash.as_bytes().len(), 32);
+        assert_eq!(test_catena.hash(&pwd, &salt, &ad, 32, &gamma)
+                   .unwrap(), hash);
+        assert_eq!(test_catena.self_test(), Ok(()));
+    }
+}
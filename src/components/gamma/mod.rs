@@ -1,3 +1,4 @@
 //! Functions for Gamma
 
 pub mod saltmix;
+pub mod xorshift;
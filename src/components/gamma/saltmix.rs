@@ -7,10 +7,112 @@ use bytes::ByteState;
 pub fn saltmix <T: ::catena::Algorithms>(
         catena_instance: &mut T,
         garlic: u8,
-        mut state: Vec<u8>,
+        state: Vec<u8>,
+        salt: &Vec<u8>,
+        k: usize) -> Vec<u8> {
+    saltmix_sized(catena_instance, garlic, state, salt, k, k)
+}
+
+/// A cache of the seed of SaltMix for the last salt.
+///
+/// The seed `H(s) || H(H(s))` only depends on the salt, which is the same
+/// for every call of flap within one computation of Catena. Catena creates
+/// a cache for each computation and passes it to `Algorithms::gamma_cached`,
+/// which can call `saltmix_cached` to hash the salt only once, like
+/// Dragonfly does.
+///
+/// The cache keeps the last salt γ and its seed until `clear` is called or
+/// it is dropped. As γ may be secret, it is overwritten with zeros then.
+#[derive(Clone, Debug, Default)]
+pub struct SeedCache {
+    salt: Option<Vec<u8>>,
+    seed: [u64; 16],
+    hits: usize,
+}
+
+impl SeedCache {
+    /// The number of calls of `saltmix_cached` which used the cached seed.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Forget the cached salt and overwrite it and the seed with zeros.
+    pub fn clear(&mut self) {
+        if let Some(ref mut salt) = self.salt {
+            for b in salt.iter_mut() {
+                *b = 0;
+            }
+        }
+        self.salt = None;
+        self.seed = [0u64; 16];
+    }
+}
+
+impl Drop for SeedCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// The function SaltMix with the seed taken from `cache` if it was computed
+/// for the same salt. Otherwise the seed is computed and replaces the cached
+/// one. The output equals that of `saltmix`.
+pub fn saltmix_cached <T: ::catena::Algorithms>(
+        catena_instance: &mut T,
+        cache: &mut SeedCache,
+        garlic: u8,
+        state: Vec<u8>,
         salt: &Vec<u8>,
         k: usize) -> Vec<u8> {
 
+    if cache.salt.as_ref() == Some(salt) {
+        cache.hits += 1;
+    } else {
+        cache.seed = seed(catena_instance, salt);
+        cache.salt = Some(salt.clone());
+    }
+    mix(catena_instance, garlic, state, &cache.seed, k, k)
+}
+
+/// The function SaltMix on words of `word_size` bytes instead of `k` bytes.
+///
+/// The state of `2^garlic` words of `k` bytes is mixed as `2^garlic * k /
+/// word_size` words of `word_size` bytes, so `k / word_size` has to be a
+/// power of two. The indices are then uniform without a modular reduction.
+/// The number of updates is the same as for `saltmix` and `saltmix_sized` with
+/// `word_size = k` is `saltmix`. H' is applied to two words of `word_size`
+/// bytes and its output is truncated to `word_size` bytes.
+///
+/// # Security considerations
+///
+/// The security analysis of Γ in the Catena specification assumes words of
+/// `k` bytes. With a smaller word size, each update touches less of the
+/// state, so the same number of updates leaves more of it unmixed and
+/// truncating H' discards part of its output. This is intended for research
+/// on variants and should not be used without an own analysis.
+pub fn saltmix_sized <T: ::catena::Algorithms>(
+        catena_instance: &mut T,
+        garlic: u8,
+        state: Vec<u8>,
+        salt: &Vec<u8>,
+        k: usize,
+        word_size: usize) -> Vec<u8> {
+
+    if word_size == 0 || k % word_size != 0
+            || !(k / word_size).is_power_of_two() {
+        panic!("k has to be a power of two multiple of the word size of \
+                SaltMix");
+    }
+
+    let seed = seed(catena_instance, salt);
+    mix(catena_instance, garlic, state, &seed, k, word_size)
+}
+
+/// The seed `H(s) || H(H(s))` of the PRNG of SaltMix.
+fn seed <T: ::catena::Algorithms>(
+        catena_instance: &mut T,
+        salt: &Vec<u8>) -> [u64; 16] {
+
     // H(s)
     let hash_1: Vec<u8> = catena_instance.h(&salt);
     // H(H(s))
@@ -21,182 +123,66 @@ pub fn saltmix <T: ::catena::Algorithms>(
     r.append(&mut ::helpers::conversions::vec_u8_to_vec_u64(&hash_1));
     r.append(&mut ::helpers::conversions::vec_u8_to_vec_u64(&hash_2));
 
-    let mut p = 0;
+    let mut seed = [0u64; 16];
+    seed.copy_from_slice(&r);
+    seed
+}
+
+/// The number of updates of SaltMix, `2^ceil(3g/4)` for the garlic `g`.
+///
+/// Returns `CatenaError::InvalidGarlic` if the count does not fit into 64
+/// bits, which is the case for a garlic of 85 or more.
+pub fn saltmix_iterations(garlic: u8) -> Result<u64, ::catena::CatenaError> {
+    let shift = (3 * garlic as u32 + 3) / 4;
+    1u64.checked_shl(shift).ok_or(::catena::CatenaError::InvalidGarlic)
+}
+
+/// Update the state with the PRNG seeded with `seed`.
+fn mix <T: ::catena::Algorithms>(
+        catena_instance: &mut T,
+        garlic: u8,
+        mut state: Vec<u8>,
+        seed: &[u64; 16],
+        k: usize,
+        word_size: usize) -> Vec<u8> {
+
+    let mut prng = ::components::gamma::xorshift::Xorshift1024Star::from_seed(
+        seed);
+
+    // each word of k bytes is split into k / word_size words, a power of
+    // two, so the indices need log2(k / word_size) more bits and every
+    // output of the PRNG is a valid index
+    let index_bits = garlic + (k / word_size).trailing_zeros() as u8;
+
+    let iterations = saltmix_iterations(garlic)
+        .unwrap_or_else(|e| panic!("SaltMix with garlic {}: {}", garlic, e));
 
     let mut j_1: usize;
     let mut j_2: usize;
 
-    for _ in 0..(1 << (garlic as f64 * 3f64 / 4f64).ceil() as u32) {
+    for _ in 0..iterations {
 
-        j_1 = xorshift_1024_star(&mut r, &mut p, garlic) as usize;
-        j_2 = xorshift_1024_star(&mut r, &mut p, garlic) as usize;
+        j_1 = prng.next(index_bits) as usize;
+        j_2 = prng.next(index_bits) as usize;
 
         let new_value = &catena_instance.h_prime(
-            &[&state.get_word(k, j_1)[..],
-            &state.get_word(k, j_2)[..]].concat());
+            &[&state.get_word(word_size, j_1)[..],
+            &state.get_word(word_size, j_2)[..]].concat());
 
-        for i in 0..k {
-            state[j_1 * k + i] = new_value[i];
+        for i in 0..word_size {
+            state[j_1 * word_size + i] = new_value[i];
         }
     }
     state
 }
 
-fn xorshift_1024_star(
-    r: &mut Vec<u64>,
-    p: &mut u8,
-    garlic: u8) -> u64 {
-    let mut s: Vec<u64> = Vec::new();
-    s.push(r[*p as usize]);
-    *p = (*p + 1) % 16;
-    s.push(r[*p as usize]);
-    s[1] = s[1] ^ (s[1] << 31);
-    s[1] = s[1] ^ (s[1] >> 11);
-    s[0] = s[0] ^ (s[0] >> 30);
-    r[*p as usize] = s[0] ^ s[1];
-    let idx = r[*p as usize].wrapping_mul(1181783497276652981);
-    let a = idx >> (64 - garlic);
-    a
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use catena::Algorithms;
     use bytes::Bytes;
     use bytes::HexRepresentation;
 
-    #[test]
-    fn xorshift_1024_star_test_1() {
-        let mut vec: Vec<u64> = vec!(
-            0x0123456789abcdef,
-            0x0123456789abcdf0,
-            0x0123456789abcdf1,
-            0x0123456789abcdf2,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        let mut p = 1;
-        let g = 64;
-        let result = xorshift_1024_star(&mut vec, &mut p, g);
-        let expected_idx = 0x17D3885BABA0909E;
-        let expected_s: Vec<u64> = vec!(
-            0x0123456789abcdef,
-            0x0123456789abcdf0,
-            0xC4CD582CF76C20E6,
-            0x0123456789abcdf2,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        assert_eq!(result, expected_idx);
-        assert_eq!(vec, expected_s);
-    }
-
-    #[test]
-    fn xorshift_1024_star_test_2() {
-        let mut vec = vec!(
-            0x0123456789abcdef,
-            0x0123456789abcdf0,
-            0x0123456789abcdf1,
-            0x0123456789abcdf2,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        let mut p = 2;
-        let g = 64;
-        let result = xorshift_1024_star(&mut vec, &mut p, g);
-        let expected_idx = 0x840D2A0DA7209534;
-        let expected_s: Vec<u64> = vec!(
-            0x0123456789abcdef,
-            0x0123456789abcdf0,
-            0x0123456789abcdf1,
-            0xC4CD582D775C20E4,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        assert_eq!(result, expected_idx);
-        assert_eq!(vec, expected_s);
-    }
-
-    #[test]
-    fn xorshift_1024_star_test_3() {
-        let mut vec = vec!(
-            0x0123456789abcdef,
-            0x0123456789abcdf0,
-            0x0123456789abcdf1,
-            0x0123456789abcdf2,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        let mut p = 15;
-        let g = 64;
-        let result = xorshift_1024_star(&mut vec, &mut p, g);
-        let expected_idx = 0x8B1A3545F6C06BEE;
-        let expected_s: Vec<u64> = vec!(
-            0xC4CD5823F68C20F6,
-            0x0123456789abcdf0,
-            0x0123456789abcdf1,
-            0x0123456789abcdf2,
-            0x0123456789abcdf3,
-            0x0123456789abcdf4,
-            0x0123456789abcdf5,
-            0x0123456789abcdf6,
-            0x0123456789abcdf7,
-            0x0123456789abcdf8,
-            0x0123456789abcdf9,
-            0x0123456789abcdfa,
-            0x0123456789abcdfb,
-            0x0123456789abcdfc,
-            0x0123456789abcdfd,
-            0x0123456789abcdfe);
-        assert_eq!(result, expected_idx);
-        assert_eq!(vec, expected_s);
-    }
-
     fn test_saltmix_from_json<T: ::catena::Algorithms>(
         mut catena: ::catena::Catena<T>, file: &str)
     {
@@ -213,11 +199,11 @@ mod tests {
             let ref inputs = unwrapped_json[i]["inputs"];
             let hash_string = inputs["hash"].to_string();
             let hash_string_trimmed = hash_string.trim_matches('\"');
-            let hash = hash_string_trimmed.to_string().to_be_bytes();
+            let hash = hash_string_trimmed.to_string().to_be_vec();
             let garlic = inputs["garlic"].as_u64().unwrap();
             let salt_string = inputs["salt"].to_string();
             let salt_string_trimmed = salt_string.trim_matches('\"');
-            let salt = salt_string_trimmed.to_string().to_be_bytes();
+            let salt = salt_string_trimmed.to_string().to_be_vec();
 
             let ref outputs = unwrapped_json[i]["outputs"]["output_hash"];
             let expected_string = outputs.to_string();
@@ -233,6 +219,104 @@ mod tests {
         }
     }
 
+    fn test_state(garlic: u8, k: usize) -> Vec<u8> {
+        (0..(1usize << garlic) * k).map(|i| (i * 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_saltmix_sized_word_size_k() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let salt = b"salt".to_vec();
+        let state = test_state(6, 64);
+
+        let expected = saltmix(
+            &mut algorithms, 6, state.clone(), &salt, 64);
+        algorithms.reset_h_prime();
+        let result = saltmix_sized(
+            &mut algorithms, 6, state.clone(), &salt, 64, 64);
+        assert_eq!(result, expected);
+        assert_ne!(result, state);
+    }
+
+    #[test]
+    fn test_saltmix_sized_smaller_words() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let salt = b"salt".to_vec();
+        let state = test_state(6, 128);
+
+        let result = saltmix_sized(
+            &mut algorithms, 6, state.clone(), &salt, 128, 64);
+        assert_eq!(result.len(), state.len());
+        assert_ne!(result, state);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_saltmix_sized_invalid_word_size() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let _ = saltmix_sized(
+            &mut algorithms, 6, test_state(6, 64), &b"salt".to_vec(), 64, 48);
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two multiple")]
+    fn test_saltmix_sized_non_power_of_two_words() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let _ = saltmix_sized(
+            &mut algorithms, 6, test_state(6, 192), &b"salt".to_vec(), 192,
+            64);
+    }
+
+    #[test]
+    fn test_saltmix_cached() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let mut cache = SeedCache::default();
+        let salt = b"salt".to_vec();
+        let state = test_state(6, 64);
+
+        let expected = saltmix(
+            &mut algorithms, 6, state.clone(), &salt, 64);
+        for _ in 0..3 {
+            algorithms.reset_h_prime();
+            let result = saltmix_cached(
+                &mut algorithms, &mut cache, 6, state.clone(), &salt, 64);
+            assert_eq!(result, expected);
+        }
+        assert_eq!(cache.hits(), 2);
+
+        // a different salt replaces the cached seed
+        let other = b"other salt".to_vec();
+        algorithms.reset_h_prime();
+        let expected = saltmix(
+            &mut algorithms, 6, state.clone(), &other, 64);
+        algorithms.reset_h_prime();
+        assert_eq!(saltmix_cached(
+            &mut algorithms, &mut cache, 6, state.clone(), &other, 64), expected);
+        assert_eq!(cache.hits(), 2);
+
+        // after clear the seed is computed again
+        cache.clear();
+        assert_eq!(cache.seed, [0u64; 16]);
+        algorithms.reset_h_prime();
+        assert_eq!(saltmix_cached(
+            &mut algorithms, &mut cache, 6, state.clone(), &other, 64), expected);
+        assert_eq!(cache.hits(), 2);
+    }
+
+    #[test]
+    fn test_saltmix_iterations() {
+        for garlic in 1..31u8 {
+            let expected = 1u64 << (garlic as f64 * 3f64 / 4f64).ceil() as u32;
+            assert_eq!(saltmix_iterations(garlic), Ok(expected));
+        }
+        assert_eq!(saltmix_iterations(0), Ok(1));
+        assert_eq!(saltmix_iterations(84), Ok(1 << 63));
+        assert_eq!(saltmix_iterations(85),
+                   Err(::catena::CatenaError::InvalidGarlic));
+        assert_eq!(saltmix_iterations(255),
+                   Err(::catena::CatenaError::InvalidGarlic));
+    }
+
     #[test]
     fn test_saltmix_dragonflyfull_from_json() {
         let test_catena = ::default_instances:: dragonfly_full::new();
@@ -0,0 +1,201 @@
+//! The pseudorandom number generator xorshift1024*, used by SaltMix.
+
+/// The state of xorshift1024*: 16 words of 64 bits and the current position.
+#[derive(Clone, Copy, Debug)]
+pub struct Xorshift1024Star {
+    state: [u64; 16],
+    p: u8,
+}
+
+impl Xorshift1024Star {
+    /// Initialize the generator with 16 words of seed, starting at position 0.
+    pub fn from_seed(seed: &[u64; 16]) -> Xorshift1024Star {
+        Xorshift1024Star {
+            state: *seed,
+            p: 0,
+        }
+    }
+
+    /// Compute the next output and return its `garlic` most significant bits,
+    /// i.e. an index into a state of `2^garlic` words. For `garlic = 0` the
+    /// only such index is 0.
+    pub fn next(&mut self, garlic: u8) -> u64 {
+        let mut s: [u64; 2] = [0; 2];
+        s[0] = self.state[self.p as usize];
+        self.p = (self.p + 1) % 16;
+        s[1] = self.state[self.p as usize];
+        s[1] = s[1] ^ (s[1] << 31);
+        s[1] = s[1] ^ (s[1] >> 11);
+        s[0] = s[0] ^ (s[0] >> 30);
+        self.state[self.p as usize] = s[0] ^ s[1];
+        let idx = self.state[self.p as usize].wrapping_mul(1181783497276652981);
+        if garlic == 0 {
+            0
+        } else {
+            idx >> (64 - garlic)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xorshift_1024_star_test_1() {
+        let seed: [u64; 16] = [
+            0x0123456789abcdef,
+            0x0123456789abcdf0,
+            0x0123456789abcdf1,
+            0x0123456789abcdf2,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        let p = 1;
+        let g = 64;
+        let mut prng = Xorshift1024Star { state: seed, p: p };
+        let result = prng.next(g);
+        let expected_idx = 0x17D3885BABA0909E;
+        let expected_s: [u64; 16] = [
+            0x0123456789abcdef,
+            0x0123456789abcdf0,
+            0xC4CD582CF76C20E6,
+            0x0123456789abcdf2,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        assert_eq!(result, expected_idx);
+        assert_eq!(prng.state, expected_s);
+    }
+
+    #[test]
+    fn xorshift_1024_star_test_2() {
+        let seed: [u64; 16] = [
+            0x0123456789abcdef,
+            0x0123456789abcdf0,
+            0x0123456789abcdf1,
+            0x0123456789abcdf2,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        let p = 2;
+        let g = 64;
+        let mut prng = Xorshift1024Star { state: seed, p: p };
+        let result = prng.next(g);
+        let expected_idx = 0x840D2A0DA7209534;
+        let expected_s: [u64; 16] = [
+            0x0123456789abcdef,
+            0x0123456789abcdf0,
+            0x0123456789abcdf1,
+            0xC4CD582D775C20E4,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        assert_eq!(result, expected_idx);
+        assert_eq!(prng.state, expected_s);
+    }
+
+    #[test]
+    fn xorshift_1024_star_test_3() {
+        let seed: [u64; 16] = [
+            0x0123456789abcdef,
+            0x0123456789abcdf0,
+            0x0123456789abcdf1,
+            0x0123456789abcdf2,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        let p = 15;
+        let g = 64;
+        let mut prng = Xorshift1024Star { state: seed, p: p };
+        let result = prng.next(g);
+        let expected_idx = 0x8B1A3545F6C06BEE;
+        let expected_s: [u64; 16] = [
+            0xC4CD5823F68C20F6,
+            0x0123456789abcdf0,
+            0x0123456789abcdf1,
+            0x0123456789abcdf2,
+            0x0123456789abcdf3,
+            0x0123456789abcdf4,
+            0x0123456789abcdf5,
+            0x0123456789abcdf6,
+            0x0123456789abcdf7,
+            0x0123456789abcdf8,
+            0x0123456789abcdf9,
+            0x0123456789abcdfa,
+            0x0123456789abcdfb,
+            0x0123456789abcdfc,
+            0x0123456789abcdfd,
+            0x0123456789abcdfe];
+        assert_eq!(result, expected_idx);
+        assert_eq!(prng.state, expected_s);
+    }
+
+    #[test]
+    fn from_seed_test() {
+        let mut seed = [0u64; 16];
+        for i in 0..16 {
+            seed[i] = 0x0123456789abcdef + i as u64;
+        }
+
+        let mut prng = Xorshift1024Star::from_seed(&seed);
+        assert_eq!(prng.p, 0);
+        assert_eq!(prng.state, seed);
+
+        // one step from position 1 as in xorshift_1024_star_test_1
+        let mut stepped = Xorshift1024Star::from_seed(&seed);
+        stepped.p = 1;
+        assert_eq!(stepped.next(64), 0x17D3885BABA0909E);
+
+        for _ in 0..17 {
+            let _ = prng.next(21);
+        }
+        assert_eq!(prng.p, 1);
+        assert_eq!(prng.next(0), 0);
+    }
+}
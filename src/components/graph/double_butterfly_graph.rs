@@ -1,49 +1,39 @@
 //! Double-Butterfly-Graph-based hashing
 
-use bytes::ByteState;
-
 /// Hash with Double Butterfly Graph
+///
+/// Each of the `λ` double butterflies has the `2g - 1` layers `j = 1, ...,
+/// 2g - 1` of the specification. Layer `j` connects word `i` to
+/// `dbh_index(g, j - 1, i)`, which flips bit `g - j` of `i` for `j < g`, bit
+/// `0` for `j = g` and bit `j - g` for `j > g`. So for `g = 1` there is the
+/// single layer `j = 1` connecting `i` to `i XOR 1`. For `g = 0` there is no
+/// layer and the state is returned unchanged.
 pub fn double_butterfly_hash<T: ::catena::Algorithms>(
         algorithms: &mut T,
         garlic: &u8,
         state: Vec<u8>,
         lambda: u8,
         n: usize,
-        k: usize) -> Vec<u8> {
+        k: usize) -> Result<Vec<u8>, ::catena::CatenaError> {
 
     let mut v: Vec<u8> = state;
 
-    let j_limit = 2 * *garlic;
-    let i_limit: u64 = (1 << garlic) as u64;
-
-    for _ in 0..lambda {
-        for j in 1..j_limit {
-            let mut r: Vec<u8> = ::components::graph::h_first(
-                algorithms,
-                ::helpers::vectors::xor(
-                    v.get_word(k, i_limit as usize - 1), v.get_word(k, 0)),
-                v.get_word(k,
-                           dbh_index(*garlic, j - 1, 0) as usize),
-                           n,
-                           k);
-            for i in 1..i_limit {
-                let ri_xor_vi = ::helpers::vectors::xor(
-                    r.get_word(k, i as usize - 1),
-                    v.get_word(k, i as usize));
-                let v_p_index = v.get_word(k,
-                    dbh_index(*garlic, j - 1, i) as usize);
-                let ri_xor_vi_concat = [&ri_xor_vi[..],
-                    &v_p_index[..]].concat();
-
-                let ri = &mut algorithms.h_prime(&ri_xor_vi_concat);
-                r.append(ri);
-            }
-            v = r;
-
-        }
-
-    }
-    v
+    // 2g - 1 layers for each of the λ double butterflies
+    let layers_per_round = (2 * *garlic as usize).saturating_sub(1);
+    let g = *garlic;
+    let index = |layer: usize, i: u64| {
+        dbh_index(g, (layer % layers_per_round) as u8, i)
+    };
+
+    ::components::graph::generic_graph::generic_graph_based_hash_framed(
+        algorithms,
+        1 << garlic,
+        &mut v,
+        lambda as usize * layers_per_round,
+        n,
+        k,
+        &index,
+        &::components::graph::generic_graph::frame_xor)
 }
 
 fn dbh_index(g: u8, j: u8, i: u64) -> u64 {
@@ -60,6 +50,89 @@ mod tests {
     use super::*;
     use bytes::HexRepresentation;
     use bytes::Bytes;
+    use catena::Algorithms;
+
+    /// σ of the double-butterfly graph as in the specification, for the
+    /// layers `j = 1, ..., 2g - 1`. `dbh_index` counts the layers from 0.
+    fn reference_sigma(g: u8, j: u8, i: usize) -> usize {
+        if j < g {
+            i ^ (1 << (g - j))
+        } else {
+            i ^ (1 << (j - g))
+        }
+    }
+
+    /// DBH written down independently from the specification, without
+    /// `generic_graph_based_hash_framed`.
+    fn reference_dbh<T: ::catena::Algorithms>(
+            algorithms: &mut T, g: u8, mut v: Vec<Vec<u8>>, lambda: u8, n: usize,
+            k: usize) -> Vec<Vec<u8>> {
+        let dim = 1usize << g;
+        for _ in 0..lambda {
+            for j in 1..2 * g {
+                let mut r: Vec<Vec<u8>> = Vec::new();
+                r.push(::components::graph::h_first(
+                    algorithms,
+                    [&::helpers::vectors::xor(v[dim - 1].clone(), v[0].clone())[..],
+                     &v[reference_sigma(g, j, 0)][..]].concat(),
+                    Vec::new(), n, k));
+                for i in 1..dim {
+                    let input = [
+                        &::helpers::vectors::xor(r[i - 1].clone(), v[i].clone())[..],
+                        &v[reference_sigma(g, j, i)][..]].concat();
+                    r.push(algorithms.h_prime(&input));
+                }
+                v = r;
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn dbh_index_small_garlic_test() {
+        for g in 1..4u8 {
+            for j in 0..2 * g - 1 {
+                for i in 0..(1u64 << g) {
+                    let index = dbh_index(g, j, i);
+                    assert_eq!(index as usize,
+                               reference_sigma(g, j + 1, i as usize),
+                               "g = {}, j = {}, i = {}", g, j, i);
+                    // each layer is a perfect matching of the words
+                    assert!(index < 1 << g && index != i);
+                    assert_eq!(dbh_index(g, j, index), i);
+                }
+            }
+        }
+        assert_eq!(dbh_index(1, 0, 0), 1);
+        assert_eq!(dbh_index(1, 0, 1), 0);
+    }
+
+    #[test]
+    fn dbh_small_garlic_test() {
+        let mut butterfly = ::default_instances::butterfly::new();
+        for g in 1..4u8 {
+            for lambda in 1..3u8 {
+                let words: Vec<Vec<u8>> = (0..1usize << g)
+                    .map(|w| (0..64).map(|b| (w * 31 + b * 7) as u8).collect())
+                    .collect();
+
+                butterfly.algorithms.reset_h_prime();
+                let expected = reference_dbh(
+                    &mut butterfly.algorithms, g, words.clone(), lambda, 64, 64);
+                butterfly.algorithms.reset_h_prime();
+                let result = double_butterfly_hash(
+                    &mut butterfly.algorithms, &g, words.concat(), lambda, 64,
+                    64).unwrap();
+
+                assert_eq!(result, expected.concat(), "g = {}, λ = {}", g, lambda);
+            }
+        }
+
+        let state: Vec<u8> = (0..64).collect();
+        assert_eq!(double_butterfly_hash(
+            &mut butterfly.algorithms, &0, state.clone(), 2, 64, 64),
+            Ok(state));
+    }
 
     #[test]
     fn test_dbh_index_from_json() {
@@ -81,6 +154,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dbh_framed_test() {
+        // H of the output of the double-butterfly loop before it used
+        // `generic_graph_based_hash_framed`
+        let state: Vec<u8> = (0..(1usize << 6) * 64)
+            .map(|i| (i * 7 % 251) as u8).collect();
+        let mut butterfly = ::default_instances::butterfly::new();
+        let result = double_butterfly_hash(
+            &mut butterfly.algorithms, &6, state, 2, 64, 64).unwrap();
+        assert_eq!(
+            ::components::hash::blake2b::hash(&result).to_hex_string(),
+            concat!(
+                "06b6cb62ed4ca86dd973fdea3c2d4932d9b94c888bf37a7fdcc8b08e79d0f3fa",
+                "25391b5c5fec3eee451782c24248f18593fad577e36eea1365ad393bc67b8331"));
+    }
+
     fn dbh_test_from_json<T: ::catena::Algorithms>(mut catena: ::catena::Catena<T>, file: &str) {
         let json = ::helpers::files::open_json(file.to_string());
         let unwrapped_json = json.as_ref().unwrap();
@@ -91,7 +180,7 @@ mod tests {
             let ref inputs = unwrapped_json[i]["inputs"];
             let state_string = inputs["state"].to_string();
             let state_string_trimmed = state_string.trim_matches('\"');
-            let state = state_string_trimmed.to_string().to_be_bytes();
+            let state = state_string_trimmed.to_string().to_be_vec();
             let garlic = inputs["garlic"].as_u64().unwrap() as u8;
             let lambda = inputs["lambda"].as_u64().unwrap() as u8;
 
@@ -112,7 +201,7 @@ mod tests {
                 state,
                 lambda,
                 n,
-                k);
+                k).unwrap();
 
             assert_eq!(result.to_hex_string(), expected, "test #{:?} failed", i);
         }
@@ -3,6 +3,9 @@
 use bytes::ByteState;
 
 /// Hash with (g, λ)-Bit-Reversal Graph
+///
+/// Returns `CatenaError::AllocationFailed` if the memory for the new state
+/// can not be reserved, like the other graphs of this crate.
 pub fn bit_reversal_hash <T: ::catena::Algorithms>(
         algorithms: &mut T,
         garlic: &u8,
@@ -10,7 +13,7 @@ pub fn bit_reversal_hash <T: ::catena::Algorithms>(
         lambda: u8,
         n: usize,
         k: usize
-    ) -> Vec<u8> {
+    ) -> Result<Vec<u8>, ::catena::CatenaError> {
 
     generic_graph_based_hash(
         algorithms,
@@ -22,6 +25,105 @@ pub fn bit_reversal_hash <T: ::catena::Algorithms>(
         &brg_index)
 }
 
+/// Hash with (g, λ)-Bit-Reversal Graph in less memory, trading memory for
+/// calls of H'.
+///
+/// Only the last word of the output of `bit_reversal_hash` on `state` is
+/// computed, which is the output of flap if Φ is the identity. `state` is
+/// read, but not changed. Of the layers `1..λ`, only every `factor`-th word
+/// is stored together with a copy of the algorithms, so that a stateful H'
+/// like Blake2b-1 can continue from there. The other words are recomputed
+/// from the closest checkpoint whenever the next layer needs them. This
+/// needs `(λ - 1) * 2^g / factor` stored words instead of a second state of
+/// `2^g` words. `algorithms` makes the same calls of H' as with
+/// `bit_reversal_hash`.
+///
+/// For `λ = 2`, this costs about `(factor + 3) / 4` times the calls of H' of
+/// `bit_reversal_hash`, and each further layer multiplies the cost of a
+/// recomputation by about `factor / 2`.
+///
+/// # Panics
+///
+/// Panics if `factor` is 0.
+pub fn bit_reversal_hash_low_memory <T: ::catena::Algorithms + Clone>(
+        algorithms: &mut T,
+        garlic: &u8,
+        state: &Vec<u8>,
+        lambda: u8,
+        n: usize,
+        k: usize,
+        factor: usize
+    ) -> Vec<u8> {
+
+    if factor == 0 {
+        panic!("The tradeoff factor has to be positive.");
+    }
+
+    let dim: usize = (1 << garlic) as usize;
+    let mut checkpoints: Vec<Vec<(Vec<u8>, T)>> = Vec::new();
+    let mut r = state.get_word(k, dim - 1);
+
+    for layer in 1..lambda as usize + 1 {
+        let previous = |i: usize| recompute_word(
+            state, &checkpoints, layer - 1, i, garlic, k, factor);
+        let last = layer == lambda as usize;
+        let mut layer_checkpoints: Vec<(Vec<u8>, T)> = Vec::new();
+
+        r = ::components::graph::h_first(
+            algorithms,
+            frame_concat(&previous(dim - 1), &[],
+                         &previous(brg_index(0, *garlic) as usize)),
+            Vec::new(),
+            n, k);
+        if !last {
+            layer_checkpoints.push((r.clone(), algorithms.clone()));
+        }
+
+        for i in 1..dim {
+            let v_index = previous(brg_index(i as u64, *garlic) as usize);
+            r = algorithms.h_prime(&frame_concat(&r, &[], &v_index));
+            if !last && i % factor == 0 {
+                layer_checkpoints.push((r.clone(), algorithms.clone()));
+            }
+        }
+
+        if !last {
+            checkpoints.push(layer_checkpoints);
+        }
+    }
+    r
+}
+
+/// The word `i` of `layer` of `bit_reversal_hash_low_memory`, where layer 0
+/// is the input state and layer `l > 0` is recomputed from the checkpoint
+/// `checkpoints[l - 1][i / factor]`.
+fn recompute_word <T: ::catena::Algorithms + Clone>(
+        state: &Vec<u8>,
+        checkpoints: &Vec<Vec<(Vec<u8>, T)>>,
+        layer: usize,
+        i: usize,
+        garlic: &u8,
+        k: usize,
+        factor: usize
+    ) -> Vec<u8> {
+
+    if layer == 0 {
+        return state.get_word(k, i);
+    }
+
+    let checkpoint = i / factor;
+    let (ref word, ref algorithms) = checkpoints[layer - 1][checkpoint];
+    let mut r = word.clone();
+    let mut algorithms = algorithms.clone();
+    for m in checkpoint * factor + 1..i + 1 {
+        let v_index = recompute_word(
+            state, checkpoints, layer - 1, brg_index(m as u64, *garlic) as usize,
+            garlic, k, factor);
+        r = algorithms.h_prime(&frame_concat(&r, &[], &v_index));
+    }
+    r
+}
+
 /// Hash with Shifted (g, λ)-Bit-Reversal Graph
 pub fn shifted_bit_reversal_hash <T: ::catena::Algorithms>(
         algorithms: &mut T,
@@ -30,7 +132,7 @@ pub fn shifted_bit_reversal_hash <T: ::catena::Algorithms>(
         lambda: u8,
         n: usize,
         k: usize,
-        c: u8) -> Vec<u8> {
+        c: u8) -> Result<Vec<u8>, ::catena::CatenaError> {
 
     let index = |g, i| {
         sbrg_index(g, i, c)
@@ -54,7 +156,7 @@ pub fn gray_bit_reversal_hash <T: ::catena::Algorithms>(
         lambda: u8,
         n: usize,
         k: usize,
-        l: u8) -> Vec<u8> {
+        l: u8) -> Result<Vec<u8>, ::catena::CatenaError> {
 
     let index = |g, i| {
         grg_index(g, i, l)
@@ -77,32 +179,128 @@ fn generic_graph_based_hash <T: ::catena::Algorithms>(
         lambda: u8,
         n: usize,
         k: usize,
-        index_function: &Fn(u64, u8) -> u64) -> Vec<u8> {
+        index_function: &Fn(u64, u8) -> u64)
+    -> Result<Vec<u8>, ::catena::CatenaError> {
 
     let dim: usize = (1 << garlic) as usize;
 
+    generic_graph_based_hash_sized(
+        algorithms,
+        dim,
+        v,
+        lambda,
+        n,
+        k,
+        index_function)
+}
+
+/// Graph-based hashing over a state of `dim` words of `k` bytes, where `dim`
+/// does not need to be a power of two.
+///
+/// The index function is called with the number of bits `g = ⌈log2(dim)⌉` and
+/// its result is reduced modulo `dim`, so every edge stays within the state.
+/// For `dim = 2^g` this is the same as the power-of-two graphs above.
+///
+/// Note that the security analysis of the bit-reversal graphs only covers
+/// dimensions which are a power of two. For other dimensions the reduced
+/// indices are no longer a permutation: some vertices get more than one
+/// incoming edge from the previous layer and others none, which may weaken
+/// the time-memory tradeoff bounds. Use this only for experiments with finer
+/// memory tuning.
+///
+/// # Panics
+///
+/// Panics if `dim` is 0.
+pub fn generic_graph_based_hash_sized <T: ::catena::Algorithms>(
+        algorithms: &mut T,
+        dim: usize,
+        v: &mut Vec<u8>,
+        lambda: u8,
+        n: usize,
+        k: usize,
+        index_function: &Fn(u64, u8) -> u64)
+    -> Result<Vec<u8>, ::catena::CatenaError> {
+
+    if dim == 0 {
+        panic!("The dimension of the graph has to be positive.");
+    }
+
+    let g: u8 = (64 - (dim as u64 - 1).leading_zeros()) as u8;
+
+    let index = |_layer: usize, i: u64| index_function(i, g) % dim as u64;
+
+    generic_graph_based_hash_framed(
+        algorithms,
+        dim,
+        v,
+        lambda as usize,
+        n,
+        k,
+        &index,
+        &frame_concat)
+}
+
+/// Graph-based hashing with a custom framing of the input of H'.
+///
+/// The state of `dim` words of `k` bytes is hashed in `layers` layers. In
+/// layer `l`, the new word `r_i` is `H'(frame(r_(i-1), v_i, v_j))` with
+/// `j = index_function(l, i)`, where `r_(-1)` is the last word `v_(dim-1)` of
+/// the previous layer. The first word `r_0` is computed with `H_first` on
+/// the framed input instead of H'.
+///
+/// The bit-reversal graphs use `frame_concat` and the double-butterfly graph
+/// uses `frame_xor`, so custom graphs can reuse this loop with their own
+/// index function and framing. Both are generic, so the loop is compiled
+/// for each graph with the framing inlined, and trait objects can be passed
+/// as well.
+pub fn generic_graph_based_hash_framed <T, I, F>(
+        algorithms: &mut T,
+        dim: usize,
+        v: &mut Vec<u8>,
+        layers: usize,
+        n: usize,
+        k: usize,
+        index_function: &I,
+        frame: &F) -> Result<Vec<u8>, ::catena::CatenaError>
+    where T: ::catena::Algorithms,
+          I: Fn(usize, u64) -> u64 + ?Sized,
+          F: Fn(&[u8], &[u8], &[u8]) -> Vec<u8> + ?Sized {
+
     let mut r: Vec<u8>;
 
-    for _ in 0..lambda {
+    for layer in 0..layers {
 
-        let index = index_function(0, *garlic) as usize;
+        let index = index_function(layer, 0) as usize;
         r = ::components::graph::h_first(
             algorithms,
-            v.get_word(k, dim - 1),
-            v.get_word(k, index),
+            frame(&v.get_word(k, dim - 1), &v.get_word(k, 0),
+                  &v.get_word(k, index)),
+            Vec::new(),
             n, k);
+        ::helpers::vectors::try_reserve(&mut r, (dim - 1) * k)?;
 
         for i in 1..dim {
-            let index = index_function(i as u64, *garlic) as usize;
+            let index = index_function(layer, i as u64) as usize;
             let r_i = r.get_word(k, i - 1);
+            let v_i = v.get_word(k, i);
             let v_index = v.get_word(k, index);
-            let mut hashed = algorithms.h_prime(&[&r_i[..], &v_index[..]].concat());
+            let mut hashed = algorithms.h_prime(&frame(&r_i, &v_i, &v_index));
 
             r.append(&mut hashed);
         }
         *v = r;
     }
-    (*v).to_vec()
+    ::helpers::vectors::try_copy(v)
+}
+
+/// Framing of the bit-reversal graphs: `r_(i-1) || v_j`.
+pub fn frame_concat(r: &[u8], _v_i: &[u8], v_index: &[u8]) -> Vec<u8> {
+    [r, v_index].concat()
+}
+
+/// Framing of the double-butterfly graph: `(r_(i-1) XOR v_i) || v_j`.
+pub fn frame_xor(r: &[u8], v_i: &[u8], v_index: &[u8]) -> Vec<u8> {
+    [&::helpers::vectors::xor(r.to_vec(), v_i.to_vec())[..], v_index].concat()
 }
 
 fn brg_index(index: u64, g: u8) -> u64 {
@@ -137,6 +335,12 @@ fn sbrg_index(i: u64, g: u8, c: u8) -> u64 {
     (brg_index(i, g) + c as u64) % (1 << g) as u64
 }
 
+/// Index of the (g, λ, l)-Gray-Reversal Graph:
+/// `τ(i) XOR (τ(¬i) >> ⌈g / l⌉)`, where `τ` reverses the `g` lower bits and
+/// `¬i` is the `g`-bit complement of `i`.
+///
+/// `!index` flips all 64 bits, but `brg_index` only reverses the `g` lower
+/// bits and shifts the others out, so this equals the `g`-bit complement.
 fn grg_index(index: u64, g: u8, l: u8) -> u64 {
     brg_index(index, g) ^ (brg_index(!index, g) >> (g as f64 / l as f64).ceil()as u64)
 }
@@ -149,6 +353,8 @@ mod tests {
     use super::*;
     use bytes::HexRepresentation;
     use bytes::Bytes;
+    use catena::Algorithms;
+    use helpers::files::JSONTests;
 
     #[test]
     fn reverse_byte_order_test() {
@@ -158,6 +364,22 @@ mod tests {
         assert_eq!( 0xff, reverse_byte_order(test_byte2));
     }
 
+    #[test]
+    fn brg_index_permutation_test() {
+        // the bit reversal is an involution, so moving word i to brg(i) is
+        // the same as reading word brg(i) at position i
+        let g = 4u8;
+        let original: Vec<u8> = (0..32).collect();
+        let mut state = original.clone();
+        state.permute_words(2, &|i| brg_index(i as u64, g) as usize);
+        for i in 0..16 {
+            assert_eq!(state.get_word(2, i),
+                       original.get_word(2, brg_index(i as u64, g) as usize));
+        }
+        state.permute_words(2, &|i| brg_index(i as u64, g) as usize);
+        assert_eq!(state, original);
+    }
+
     #[test]
     fn brg_index_test_from_json() {
         let json = ::helpers::files::open_json("test/test_vectors/brgIndex.json".to_string());
@@ -214,6 +436,79 @@ mod tests {
         }
     }
 
+    /// Reverse the `g` lower bits of `i` bit by bit.
+    fn reference_reverse(i: u64, g: u8) -> u64 {
+        let mut reversed = 0;
+        for bit in 0..g {
+            if i & (1 << bit) != 0 {
+                reversed |= 1 << (g - 1 - bit);
+            }
+        }
+        reversed
+    }
+
+    #[test]
+    fn grg_index_reference_test() {
+        for g in 1..11u8 {
+            let mask = (1u64 << g) - 1;
+            for l in 1..5u8 {
+                let shift = (g + l - 1) / l;
+                for i in 0..1u64 << g {
+                    let expected = reference_reverse(i, g)
+                        ^ (reference_reverse(!i & mask, g) >> shift);
+                    let index = grg_index(i, g, l);
+                    assert_eq!(index, expected, "g = {}, l = {}, i = {}", g, l, i);
+                    assert!(index <= mask);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grg_index_high_bits_test() {
+        // bits above g do not change the index
+        for i in 0..1u64 << 6 {
+            assert_eq!(grg_index(i | 0xffff_0000_0000_0000, 6, 3),
+                       grg_index(i, 6, 3));
+        }
+    }
+
+    #[test]
+    fn framed_test() {
+        // H of the outputs of the bit-reversal loop before it used
+        // `generic_graph_based_hash_framed`
+        let state: Vec<u8> = (0..(1usize << 6) * 64)
+            .map(|i| (i * 7 % 251) as u8).collect();
+        let mut dragonfly = ::default_instances::dragonfly::new();
+
+        let result = bit_reversal_hash(
+            &mut dragonfly.algorithms, &6, &mut state.clone(), 2, 64, 64)
+            .unwrap();
+        assert_eq!(
+            ::components::hash::blake2b::hash(&result).to_hex_string(),
+            concat!(
+                "cc6e1fdde5e7fdf04f80afb6cee74cfa35f99d84160e985649633777e488dc57",
+                "7079fa9d852968edd41e3c9e177d02d538a38380e2747c194da939d43231c447"));
+
+        let result = gray_bit_reversal_hash(
+            &mut dragonfly.algorithms, &6, &mut state.clone(), 2, 64, 64, 3)
+            .unwrap();
+        assert_eq!(
+            ::components::hash::blake2b::hash(&result).to_hex_string(),
+            concat!(
+                "4c8319abfd31058664a7f35ceae0b01a635288447b6dc9819f12dd103ac1d2d3",
+                "89530eb1fe947950322eda43b75ae6b2da0f4a5875de3f63eceeba2ce2256a99"));
+    }
+
+    #[test]
+    fn frame_test() {
+        let r = [1u8, 2];
+        let v_i = [3u8, 3];
+        let v_index = [4u8, 5];
+        assert_eq!(frame_concat(&r, &v_i, &v_index), vec![1, 2, 4, 5]);
+        assert_eq!(frame_xor(&r, &v_i, &v_index), vec![2, 1, 4, 5]);
+    }
+
     fn brg_test_from_json<T: ::catena::Algorithms>(mut catena: ::catena::Catena<T>, file: &str) {
         let json = ::helpers::files::open_json(file.to_string());
         let unwrapped_json = json.as_ref().unwrap();
@@ -224,7 +519,7 @@ mod tests {
             let ref inputs = unwrapped_json[i]["inputs"];
             let state_string = inputs["state"].to_string();
             let state_string_trimmed = state_string.trim_matches('\"');
-            let mut state = state_string_trimmed.to_string().to_be_bytes();
+            let mut state = state_string_trimmed.to_string().to_be_vec();
             let garlic = inputs["garlic"].as_u64().unwrap() as u8;
             let lambda = inputs["lambda"].as_u64().unwrap() as u8;
 
@@ -245,7 +540,7 @@ mod tests {
                 &mut state,
                 lambda,
                 n,
-                k);
+                k).unwrap();
 
             assert_eq!(result.to_hex_string(), expected, "test #{:?} failed", i);
         }
@@ -273,7 +568,7 @@ mod tests {
             let ref inputs = unwrapped_json[i]["inputs"];
             let state_string = inputs["state"].to_string();
             let state_string_trimmed = state_string.trim_matches('\"');
-            let mut state = state_string_trimmed.to_string().to_be_bytes();
+            let mut state = state_string_trimmed.to_string().to_be_vec();
             let garlic = inputs["garlic"].as_u64().unwrap() as u8;
             let lambda = inputs["lambda"].as_u64().unwrap() as u8;
             let c = inputs["c"].as_u64().unwrap() as u8;
@@ -296,12 +591,84 @@ mod tests {
                 lambda,
                 n,
                 k,
-                c);
+                c).unwrap();
 
             assert_eq!(result.to_hex_string(), expected, "test #{:?} failed", i);
         }
     }
 
+    fn brg_low_memory_test_from_json<T: ::catena::Algorithms + Clone>(
+        mut catena: ::catena::Catena<T>, file: &str)
+    {
+        let json = ::helpers::files::open_json(file.to_string());
+        let unwrapped_json = json.as_ref().unwrap();
+        let number_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+
+        for i in 0..number_of_tests {
+            let ref inputs = unwrapped_json[i]["inputs"];
+            let state = inputs.parse_hex("state");
+            let garlic = inputs["garlic"].as_u64().unwrap() as u8;
+            let lambda = inputs["lambda"].as_u64().unwrap() as u8;
+            let expected = unwrapped_json[i]["outputs"].parse_hex("res");
+
+            let n: usize;
+            let k: usize;
+            {
+                n = catena.n;
+                k = catena.k;
+            }
+
+            for &factor in [1, 8].iter() {
+                catena.algorithms.reset_h_prime();
+                let result = bit_reversal_hash_low_memory(
+                    &mut catena.algorithms, &garlic, &state, lambda, n, k,
+                    factor);
+                assert_eq!(result, expected.get_word(k, (1 << garlic) - 1),
+                           "test #{:?} failed with factor {}", i, factor);
+            }
+        }
+    }
+
+    #[test]
+    fn brg_low_memory_test_dragonfly_from_json() {
+        let catena = ::default_instances::dragonfly::new();
+        brg_low_memory_test_from_json(catena, "test/test_vectors/brgAny.json");
+    }
+
+    #[test]
+    fn brg_low_memory_parity_test() {
+        let garlic = 5u8;
+        let state: Vec<u8> = (0..(1usize << garlic) * 64)
+            .map(|i| (i * 7 % 251) as u8).collect();
+
+        for lambda in 0..4u8 {
+            let mut full = ::default_instances::dragonfly::new().algorithms;
+            let expected = bit_reversal_hash(
+                &mut full, &garlic, &mut state.clone(), lambda, 64, 64)
+                .unwrap();
+
+            for &factor in [1, 3, 8, 32, 64].iter() {
+                let mut low = ::default_instances::dragonfly::new().algorithms;
+                let result = bit_reversal_hash_low_memory(
+                    &mut low, &garlic, &state, lambda, 64, 64, factor);
+                assert_eq!(result, expected.get_word(64, (1 << garlic) - 1),
+                           "lambda = {}, factor = {}", lambda, factor);
+
+                // the state of H' is the same as after the full computation
+                let probe = vec![0u8; 128];
+                assert_eq!(low.h_prime(&probe), full.clone().h_prime(&probe));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn brg_low_memory_zero_factor_test() {
+        let mut algorithms = ::default_instances::dragonfly::new().algorithms;
+        let _ = bit_reversal_hash_low_memory(
+            &mut algorithms, &2, &vec![0u8; 4 * 64], 2, 64, 64, 0);
+    }
+
     #[test]
     fn sbrg_test_dragonfly_from_json() {
         let catena = ::default_instances::dragonfly::new();
@@ -324,7 +691,7 @@ mod tests {
             let ref inputs = unwrapped_json[i]["inputs"];
             let state_string = inputs["state"].to_string();
             let state_string_trimmed = state_string.trim_matches('\"');
-            let mut state = state_string_trimmed.to_string().to_be_bytes();
+            let mut state = state_string_trimmed.to_string().to_be_vec();
             let garlic = inputs["garlic"].as_u64().unwrap() as u8;
             let lambda = inputs["lambda"].as_u64().unwrap() as u8;
             let l = inputs["l"].as_u64().unwrap() as u8;
@@ -347,7 +714,7 @@ mod tests {
                 lambda,
                 n,
                 k,
-                l);
+                l).unwrap();
 
             assert_eq!(result.to_hex_string(), expected, "test #{:?} failed", i);
         }
@@ -364,4 +731,50 @@ mod tests {
         let catena = ::default_instances::dragonfly_full::new();
         grg_test_from_json(catena, "test/test_vectors/grgAnyFull.json");
     }
+
+    #[test]
+    fn sized_matches_power_of_two_test() {
+        let mut catena = ::default_instances::dragonfly::new();
+        let garlic: u8 = 5;
+        let dim: usize = 1 << garlic;
+        let state: Vec<u8> = (0..dim * catena.k).map(|i| i as u8).collect();
+
+        let mut v = state.clone();
+        catena.algorithms.reset_h_prime();
+        let expected = bit_reversal_hash(
+            &mut catena.algorithms, &garlic, &mut v, 2, catena.n, catena.k)
+            .unwrap();
+
+        let mut v_sized = state.clone();
+        catena.algorithms.reset_h_prime();
+        let result = generic_graph_based_hash_sized(
+            &mut catena.algorithms, dim, &mut v_sized, 2, catena.n, catena.k,
+            &brg_index).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn sized_non_power_of_two_test() {
+        let mut catena = ::default_instances::dragonfly::new();
+        let dim: usize = 24;
+        let mut v: Vec<u8> = (0..dim * catena.k).map(|i| i as u8).collect();
+
+        let result = generic_graph_based_hash_sized(
+            &mut catena.algorithms, dim, &mut v, 2, catena.n, catena.k,
+            &brg_index).unwrap();
+
+        assert_eq!(result.len(), dim * catena.k);
+    }
+
+    #[test]
+    #[should_panic(expected = "dimension of the graph has to be positive")]
+    fn sized_zero_dimension_test() {
+        let mut catena = ::default_instances::dragonfly::new();
+        let mut v: Vec<u8> = Vec::new();
+
+        let _ = generic_graph_based_hash_sized(
+            &mut catena.algorithms, 0, &mut v, 2, catena.n, catena.k,
+            &brg_index);
+    }
 }
@@ -2,6 +2,30 @@
 
 pub mod generic_graph;
 pub mod double_butterfly_graph;
+pub mod sponge_graph;
+
+/// The kinds of graph-based hash functions F in `catena::components::graph`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphKind {
+    /// (g, λ)-Bit-Reversal Graph
+    BitReversal,
+    /// Shifted (g, λ)-Bit-Reversal Graph
+    ShiftedBitReversal {
+        /// The shift of the indices
+        c: u8,
+    },
+    /// (g, λ, l)-Gray-Reversal Graph
+    GrayReversal {
+        /// The parameter l of the Gray-Reversal Graph
+        l: u8,
+    },
+    /// Double Butterfly Graph
+    DoubleButterfly,
+    /// Experimental sponge-based F of `sponge_graph`
+    Sponge,
+    /// A graph which is not part of this crate
+    Unknown,
+}
 
 fn h_first <T: ::catena::Algorithms>(
         catena_instance: &T,
@@ -31,6 +55,7 @@ fn h_first <T: ::catena::Algorithms>(
 mod tests {
     use super::*;
     use bytes::Bytes;
+    use catena::Algorithms;
 
     fn h_first_test(hash_1: Vec<u8>, hash_2: Vec<u8>) -> Vec<u8> {
         let mut test_catena = ::default_instances:: dragonfly::new();
@@ -45,14 +70,14 @@ mod tests {
         let json = ::helpers::files::open_json(path);
         let json_v1_tmp = json.as_ref().unwrap()[0]["inputs"]["v"][0].to_string();
         let json_v1 = json_v1_tmp.trim_matches('\"');
-        let hash_1: Vec<u8> = json_v1.to_string().to_be_bytes();
+        let hash_1: Vec<u8> = json_v1.to_string().to_be_vec();
         let json_v2_tmp = json.as_ref().unwrap()[0]["inputs"]["v"][1].to_string();
         let json_v2 = json_v2_tmp.trim_matches('\"');
-        let hash_2: Vec<u8> = json_v2.to_string().to_be_bytes();
+        let hash_2: Vec<u8> = json_v2.to_string().to_be_vec();
         let h_first: Vec<u8> = h_first_test(hash_1,hash_2);
         let json_w_tmp = json.as_ref().unwrap()[0]["outputs"]["w"].to_string();
         let json_w = json_w_tmp.trim_matches('\"');
-        let hash_w: Vec<u8> = json_w.to_string().to_be_bytes();
+        let hash_w: Vec<u8> = json_w.to_string().to_be_vec();
 
         assert_eq!(hash_w,h_first);
     }
@@ -63,15 +88,33 @@ mod tests {
         let json = ::helpers::files::open_json(path);
         let json_v1_tmp = json.as_ref().unwrap()[1]["inputs"]["v"][0].to_string();
         let json_v1 = json_v1_tmp.trim_matches('\"');
-        let hash_1: Vec<u8> = json_v1.to_string().to_be_bytes();
+        let hash_1: Vec<u8> = json_v1.to_string().to_be_vec();
         let json_v2_tmp = json.as_ref().unwrap()[1]["inputs"]["v"][1].to_string();
         let json_v2 = json_v2_tmp.trim_matches('\"');
-        let hash_2: Vec<u8> = json_v2.to_string().to_be_bytes();
+        let hash_2: Vec<u8> = json_v2.to_string().to_be_vec();
         let h_first: Vec<u8> = h_first_test(hash_1,hash_2);
         let json_w_tmp = json.as_ref().unwrap()[1]["outputs"]["w"].to_string();
         let json_w = json_w_tmp.trim_matches('\"');
-        let hash_w: Vec<u8> = json_w.to_string().to_be_bytes();
+        let hash_w: Vec<u8> = json_w.to_string().to_be_vec();
 
         assert_eq!(hash_w,h_first);
     }
+
+    #[test]
+    fn graph_kind_test() {
+        assert_eq!(::default_instances::dragonfly::new().algorithms.graph_kind(),
+                   GraphKind::BitReversal);
+        assert_eq!(::default_instances::butterfly::new().algorithms.graph_kind(),
+                   GraphKind::DoubleButterfly);
+        assert_eq!(::variants::lanternfly::new().algorithms.graph_kind(),
+                   GraphKind::GrayReversal { l: 3 });
+    }
+
+    #[test]
+    fn graph_kind_registry_test() {
+        for variant in ::registry::Variant::all() {
+            assert!(variant.instance().graph_kind() != GraphKind::Unknown,
+                    "{} has no graph kind", variant.vid());
+        }
+    }
 }
@@ -0,0 +1,85 @@
+//! Sponge-based hashing in the style of Lyra2
+//!
+//! This F is experimental and not part of the Catena specification. An
+//! instance using it does not interoperate with any other implementation.
+
+use bytes::ByteState;
+
+/// The size of the sponge and of the state words in bytes, the block size of
+/// the Argon2 compression function.
+const BLOCK_SIZE: usize = 1024;
+
+/// Hash with a duplex sponge over the state.
+///
+/// The sponge is initialized with the last word of the state. In each of the
+/// `lambda` rounds every word `v_i` is absorbed into the sponge with
+/// `s = CF_B(s || v_i)`, and the sponge is squeezed into the state by
+/// `v_i = v_i XOR s`. Even rounds visit the words in ascending order, odd
+/// rounds in descending order, so every word depends on all words of the
+/// previous round. The sponge transform is the Argon2 compression function
+/// with G = G_B, thus `k` has to be 1024.
+///
+/// The access pattern is independent of the password.
+pub fn sponge_hash(
+        garlic: &u8,
+        state: &mut Vec<u8>,
+        lambda: u8,
+        k: usize
+    ) -> Result<Vec<u8>, ::catena::CatenaError> {
+
+    if k != BLOCK_SIZE {
+        panic!("Word size has to be {} but is {}.", BLOCK_SIZE, k);
+    }
+
+    let g = 1usize << *garlic;
+    let mut sponge = state.get_word(k, g - 1);
+
+    for j in 0..lambda {
+        for i in 0..g {
+            let index = if j % 2 == 0 { i } else { g - 1 - i };
+            let word = state.get_word(k, index);
+
+            // absorb
+            sponge = ::components::fasthash::cf_argon2::cf_argon2_gb(
+                &[&sponge[..], &word[..]].concat());
+
+            // squeeze
+            state.set_word(
+                k, index, ::helpers::vectors::xor(word, sponge.clone()));
+        }
+    }
+    ::helpers::vectors::try_copy(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sponge_hash_last_word_test() {
+        // with lambda = 1, the last word is v_(g-1) XOR the sponge after
+        // absorbing all words
+        let garlic = 2u8;
+        let original: Vec<u8> = (0..4 * BLOCK_SIZE).map(|i| (i % 251) as u8)
+            .collect();
+        let mut state = original.clone();
+        let hashed = sponge_hash(&garlic, &mut state, 1, BLOCK_SIZE).unwrap();
+
+        let mut sponge = original.get_word(BLOCK_SIZE, 3);
+        for i in 0..4 {
+            sponge = ::components::fasthash::cf_argon2::cf_argon2_gb(
+                &[&sponge[..], &original.get_word(BLOCK_SIZE, i)[..]].concat());
+        }
+        assert_eq!(hashed.get_word(BLOCK_SIZE, 3),
+                   ::helpers::vectors::xor(
+                       original.get_word(BLOCK_SIZE, 3), sponge));
+        assert_eq!(hashed, state);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sponge_hash_word_size_test() {
+        let mut state = vec![0u8; 4 * 64];
+        let _ = sponge_hash(&2, &mut state, 1, 64);
+    }
+}
@@ -7,6 +7,17 @@ pub fn hash(x: &Vec<u8>) -> Vec<u8> {
     blake2_rfc::blake2b::blake2b(64, &[], x).as_bytes().to_vec()
 }
 
+/// Blake2b keyed with `key` as a MAC, with an output of 64 bytes.
+///
+/// # Panics
+///
+/// Panics if `key` is longer than 64 bytes.
+pub fn mac(key: &[u8], x: &[u8]) -> Vec<u8> {
+    assert!(key.len() <= 64, "Blake2b keys have at most 64 bytes, got {}",
+            key.len());
+    blake2_rfc::blake2b::blake2b(64, key, x).as_bytes().to_vec()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -29,13 +40,28 @@ mod tests {
         assert_eq!(hash(&x), expected);
     }
 
+    #[test]
+    fn mac_test() {
+        let x = b"data".to_vec();
+        assert_eq!(mac(&[], &x), hash(&x));
+        assert_ne!(mac(b"key", &x), hash(&x));
+        assert_ne!(mac(b"key", &x), mac(b"other key", &x));
+        assert_eq!(mac(&[0u8; 64], &x).len(), 64);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mac_long_key_test() {
+        let _ = mac(&[0u8; 65], b"data");
+    }
+
     #[test]
     fn blake2b_test_2() {
         let x = b"The quick brown fox jumps over the lazy dog".to_vec();
 
         let expected = "a8add4bdddfd93e4877d2746e62817b116364a1fa7bc148d95090bc\
                         7333b3673f82401cf7aa2e4cb1ecd90296e3f14cb5413f8ed77be73\
-                        045b13914cdcd6a918".to_string().to_be_bytes();
+                        045b13914cdcd6a918".to_string().to_be_vec();
 
         assert_eq!(hash(&x), expected);
     }
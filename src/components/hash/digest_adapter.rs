@@ -0,0 +1,48 @@
+//! Adapter for hash functions implementing `digest::Digest`
+extern crate digest;
+
+/// Any hash function of the RustCrypto project, e.g. SHA-2, SHA-3 or Blake2,
+/// which can be used as H.
+///
+/// The output length varies with `D`, so `n` of the Catena instance has to be
+/// set to `D::output_size()`.
+///
+/// ```ignore
+/// fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+///     ::catena::components::hash::digest_adapter::hash::<sha2::Sha512>(x)
+/// }
+/// ```
+pub fn hash<D: digest::Digest>(x: &[u8]) -> Vec<u8> {
+    D::digest(x).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate sha2;
+
+    use super::*;
+    use self::digest::Digest;
+    use bytes::Bytes;
+
+    #[test]
+    fn digest_adapter_sha256_test() {
+        let x = b"abc".to_vec();
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff\
+                        61f20015ad".to_string().to_be_vec();
+
+        let result = hash::<sha2::Sha256>(&x);
+        assert_eq!(result.len(), sha2::Sha256::output_size());
+        assert_eq!(result.len(), 32);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn digest_adapter_sha512_test() {
+        let x = b"abc".to_vec();
+
+        let result = hash::<sha2::Sha512>(&x);
+        assert_eq!(result.len(), sha2::Sha512::output_size());
+        assert_eq!(result.len(), 64);
+    }
+}
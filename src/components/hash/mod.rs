@@ -1,3 +1,5 @@
 //! Implementations for H
 
 pub mod blake2b;
+#[cfg(feature = "digest")]
+pub mod digest_adapter;
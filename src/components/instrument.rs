@@ -0,0 +1,545 @@
+//! Instrumentation of the variable components of Catena.
+
+use components::gamma::saltmix::SeedCache;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// The number of calls of each of the algorithms of a Catena instance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Calls of H
+    pub h: u64,
+    /// Calls of H'
+    pub h_prime: u64,
+    /// Calls of Γ
+    pub gamma: u64,
+    /// Calls of F
+    pub f: u64,
+    /// Calls of Φ
+    pub phi: u64,
+}
+
+/// Algorithms which count their calls and delegate to the wrapped algorithms.
+///
+/// Only calls made through the `Algorithms` interface of the wrapper are
+/// counted. Calls of H and H' made inside of Γ, F and Φ of the wrapped
+/// algorithms go directly to the wrapped algorithms and are not included.
+///
+/// ```
+/// use catena::components::instrument::Counting;
+///
+/// let dragonfly = catena::default_instances::dragonfly::new();
+/// let mut counting = catena::catena::Catena {
+///     algorithms: Counting::new(dragonfly.algorithms),
+///     vid: dragonfly.vid,
+///     n: dragonfly.n,
+///     k: dragonfly.k,
+///     g_low: 9,
+///     g_high: 9,
+///     lambda: dragonfly.lambda,
+///     options: dragonfly.options,
+/// };
+/// ```
+#[derive(Debug)]
+pub struct Counting<T: ::catena::Algorithms> {
+    inner: T,
+    h: AtomicU64,
+    h_prime: AtomicU64,
+    gamma: AtomicU64,
+    f: AtomicU64,
+    phi: AtomicU64,
+}
+
+impl<T: ::catena::Algorithms> Counting<T> {
+    /// Wrap `inner` with all counters set to zero.
+    pub fn new(inner: T) -> Counting<T> {
+        Counting {
+            inner: inner,
+            h: AtomicU64::new(0),
+            h_prime: AtomicU64::new(0),
+            gamma: AtomicU64::new(0),
+            f: AtomicU64::new(0),
+            phi: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of calls so far.
+    pub fn counts(&self) -> Counts {
+        Counts {
+            h: self.h.load(Ordering::Relaxed),
+            h_prime: self.h_prime.load(Ordering::Relaxed),
+            gamma: self.gamma.load(Ordering::Relaxed),
+            f: self.f.load(Ordering::Relaxed),
+            phi: self.phi.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Set all counters to zero.
+    pub fn reset_counts(&self) {
+        self.h.store(0, Ordering::Relaxed);
+        self.h_prime.store(0, Ordering::Relaxed);
+        self.gamma.store(0, Ordering::Relaxed);
+        self.f.store(0, Ordering::Relaxed);
+        self.phi.store(0, Ordering::Relaxed);
+    }
+
+    /// Remove the wrapper and return the wrapped algorithms.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ::catena::Algorithms + Clone> Clone for Counting<T> {
+    fn clone(&self) -> Counting<T> {
+        let counts = self.counts();
+        Counting {
+            inner: self.inner.clone(),
+            h: AtomicU64::new(counts.h),
+            h_prime: AtomicU64::new(counts.h_prime),
+            gamma: AtomicU64::new(counts.gamma),
+            f: AtomicU64::new(counts.f),
+            phi: AtomicU64::new(counts.phi),
+        }
+    }
+}
+
+impl<T: ::catena::Algorithms> ::catena::Algorithms for Counting<T> {
+    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+        let _ = self.h.fetch_add(1, Ordering::Relaxed);
+        self.inner.h(x)
+    }
+
+    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+        let _ = self.h_prime.fetch_add(1, Ordering::Relaxed);
+        self.inner.h_prime(x)
+    }
+
+    fn reset_h_prime(&mut self) {
+        self.inner.reset_h_prime();
+    }
+
+    fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+        -> Vec<u8> {
+        let _ = self.gamma.fetch_add(1, Ordering::Relaxed);
+        self.inner.gamma(garlic, state, gamma, k)
+    }
+
+    fn gamma_cached(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                    k: usize, cache: &mut SeedCache) -> Vec<u8> {
+        let _ = self.gamma.fetch_add(1, Ordering::Relaxed);
+        self.inner.gamma_cached(garlic, state, gamma, k, cache)
+    }
+
+    fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, ::catena::CatenaError> {
+        let _ = self.f.fetch_add(1, Ordering::Relaxed);
+        self.inner.f(garlic, state, lambda, n, k)
+    }
+
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        self.inner.graph_kind()
+    }
+
+    fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+        let _ = self.phi.fetch_add(1, Ordering::Relaxed);
+        self.inner.phi(garlic, state, mu, k)
+    }
+
+    fn uses_gamma(&self) -> bool {
+        self.inner.uses_gamma()
+    }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        self.inner.phi_is_data_dependent()
+    }
+}
+
+/// Algorithms with Γ, F or Φ replaced by the identity, for ablation studies.
+///
+/// All other calls are delegated to the wrapped algorithms. A disabled F is
+/// no graph, so `graph_kind` is `GraphKind::Unknown`, and a disabled Γ or Φ
+/// neither uses γ nor is data-dependent. See `Catena::ablated`.
+#[derive(Clone, Debug)]
+pub struct Ablated<T: ::catena::Algorithms> {
+    inner: T,
+    /// Γ is the identity.
+    pub disable_gamma: bool,
+    /// F is the identity.
+    pub disable_f: bool,
+    /// Φ is the identity.
+    pub disable_phi: bool,
+}
+
+impl<T: ::catena::Algorithms> Ablated<T> {
+    /// Wrap `inner` with all components enabled.
+    pub fn new(inner: T) -> Ablated<T> {
+        Ablated {
+            inner: inner,
+            disable_gamma: false,
+            disable_f: false,
+            disable_phi: false,
+        }
+    }
+
+    /// Remove the wrapper and return the wrapped algorithms.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ::catena::Algorithms> ::catena::Algorithms for Ablated<T> {
+    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+        self.inner.h(x)
+    }
+
+    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+        self.inner.h_prime(x)
+    }
+
+    fn reset_h_prime(&mut self) {
+        self.inner.reset_h_prime();
+    }
+
+    fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+        -> Vec<u8> {
+        if self.disable_gamma {
+            state
+        } else {
+            self.inner.gamma(garlic, state, gamma, k)
+        }
+    }
+
+    fn gamma_cached(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                    k: usize, cache: &mut SeedCache) -> Vec<u8> {
+        if self.disable_gamma {
+            state
+        } else {
+            self.inner.gamma_cached(garlic, state, gamma, k, cache)
+        }
+    }
+
+    fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, ::catena::CatenaError> {
+        if self.disable_f {
+            Ok(state.clone())
+        } else {
+            self.inner.f(garlic, state, lambda, n, k)
+        }
+    }
+
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        if self.disable_f {
+            ::components::graph::GraphKind::Unknown
+        } else {
+            self.inner.graph_kind()
+        }
+    }
+
+    fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+        if self.disable_phi {
+            state
+        } else {
+            self.inner.phi(garlic, state, mu, k)
+        }
+    }
+
+    fn uses_gamma(&self) -> bool {
+        !self.disable_gamma && self.inner.uses_gamma()
+    }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        !self.disable_phi && self.inner.phi_is_data_dependent()
+    }
+}
+
+/// Algorithms which count all calls of H and H', including those inside of
+/// Γ, F and Φ.
+///
+/// Unlike `Counting`, Γ, F and Φ are not delegated but rebuilt from the
+/// components of this crate, as declared by `uses_gamma`, `graph_kind` and
+/// `phi_is_data_dependent` of the wrapped algorithms, such that they call H
+/// and H' of the wrapper. This requires Γ to be SaltMix and Φ to be Φ-lsb if
+/// they are used, like for all instances of this crate. For
+/// `GraphKind::Unknown`, F of the wrapped algorithms is called and its calls
+/// of H and H' are not counted. See `Catena::profile`.
+#[derive(Clone, Debug)]
+pub struct Recomposed<T: ::catena::Algorithms> {
+    counting: Counting<T>,
+}
+
+impl<T: ::catena::Algorithms> Recomposed<T> {
+    /// Wrap `inner` with all counters set to zero.
+    pub fn new(inner: T) -> Recomposed<T> {
+        Recomposed { counting: Counting::new(inner) }
+    }
+
+    /// The number of calls so far.
+    pub fn counts(&self) -> Counts {
+        self.counting.counts()
+    }
+
+    /// Set all counters to zero.
+    pub fn reset_counts(&self) {
+        self.counting.reset_counts();
+    }
+
+    /// Remove the wrapper and return the wrapped algorithms.
+    pub fn into_inner(self) -> T {
+        self.counting.into_inner()
+    }
+}
+
+impl<T: ::catena::Algorithms> ::catena::Algorithms for Recomposed<T> {
+    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+        self.counting.h(x)
+    }
+
+    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+        self.counting.h_prime(x)
+    }
+
+    fn reset_h_prime(&mut self) {
+        self.counting.reset_h_prime();
+    }
+
+    fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+        -> Vec<u8> {
+        let _ = self.counting.gamma.fetch_add(1, Ordering::Relaxed);
+        if self.uses_gamma() {
+            ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
+        } else {
+            state
+        }
+    }
+
+    fn gamma_cached(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>,
+                    k: usize, cache: &mut SeedCache) -> Vec<u8> {
+        let _ = self.counting.gamma.fetch_add(1, Ordering::Relaxed);
+        if self.uses_gamma() {
+            ::components::gamma::saltmix::saltmix_cached(
+                self, cache, garlic, state, gamma, k)
+        } else {
+            state
+        }
+    }
+
+    fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+        -> Result<Vec<u8>, ::catena::CatenaError> {
+        use components::graph::GraphKind;
+        use components::graph::double_butterfly_graph;
+        use components::graph::generic_graph;
+        let _ = self.counting.f.fetch_add(1, Ordering::Relaxed);
+        match self.graph_kind() {
+            GraphKind::BitReversal => generic_graph::bit_reversal_hash(
+                self, garlic, state, lambda, n, k),
+            GraphKind::ShiftedBitReversal { c } =>
+                generic_graph::shifted_bit_reversal_hash(
+                    self, garlic, state, lambda, n, k, c),
+            GraphKind::GrayReversal { l } =>
+                generic_graph::gray_bit_reversal_hash(
+                    self, garlic, state, lambda, n, k, l),
+            GraphKind::DoubleButterfly =>
+                double_butterfly_graph::double_butterfly_hash(
+                    self, garlic, state.clone(), lambda, n, k),
+            GraphKind::Sponge => ::components::graph::sponge_graph::sponge_hash(
+                garlic, state, lambda, k),
+            GraphKind::Unknown =>
+                self.counting.inner.f(garlic, state, lambda, n, k),
+        }
+    }
+
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        self.counting.graph_kind()
+    }
+
+    fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+        let _ = self.counting.phi.fetch_add(1, Ordering::Relaxed);
+        if self.phi_is_data_dependent() {
+            ::components::phi::lsb::phi_lsb(self, garlic, state, mu, k)
+        } else {
+            state
+        }
+    }
+
+    fn uses_gamma(&self) -> bool {
+        self.counting.uses_gamma()
+    }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        self.counting.phi_is_data_dependent()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catena::Algorithms;
+    use helpers::fixtures::test_inputs;
+
+    #[test]
+    fn counting_test() {
+        let mut bare = ::default_instances::dragonfly::new();
+        bare.g_low = 9;
+        bare.g_high = 9;
+
+        let mut counting = ::catena::Catena {
+            algorithms: Counting::new(bare.algorithms),
+            vid: bare.vid,
+            n: bare.n,
+            k: bare.k,
+            g_low: bare.g_low,
+            g_high: bare.g_high,
+            lambda: bare.lambda,
+            options: bare.options,
+        };
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        assert_eq!(counting.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(),
+                   bare.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+
+        // one flap with (g_low + 1) / 2 and one with g_low
+        let counts = counting.algorithms.counts();
+        assert!(counts.h > 0);
+        assert_eq!(counts.h_prime, (1 << 5) + (1 << 9));
+        assert_eq!(counts.gamma, 2);
+        assert_eq!(counts.f, 2);
+        assert_eq!(counts.phi, 2);
+
+        counting.algorithms.reset_counts();
+        assert_eq!(counting.algorithms.counts(), Counts::default());
+    }
+
+    /// Dragonfly with Γ, F and Φ as the identity: the state is only the hash
+    /// chain `v_i = H'(v_(i-1) || v_(i-2))`.
+    #[derive(Clone, Copy, Debug)]
+    struct HashChain {
+        blake2b_1: ::components::fasthash::blake2b1::Blake2b1,
+    }
+
+    impl ::catena::Algorithms for HashChain {
+        fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+            ::components::hash::blake2b::hash(x)
+        }
+
+        fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+            self.blake2b_1.hash(x)
+        }
+
+        fn reset_h_prime(&mut self) {
+            self.blake2b_1.reset();
+        }
+
+        #[allow(unused_variables)]
+        fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            state
+        }
+
+        #[allow(unused_variables)]
+        fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize,
+             k: usize) -> Result<Vec<u8>, ::catena::CatenaError> {
+            Ok(state.clone())
+        }
+
+        #[allow(unused_variables)]
+        fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize)
+            -> Vec<u8> {
+            state
+        }
+    }
+
+    #[test]
+    fn ablated_test() {
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 9;
+        dragonfly.g_high = 9;
+        let expected = dragonfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+
+        let mut ablated = dragonfly.clone().ablated();
+        assert_eq!(ablated.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap(), expected);
+        assert_eq!(ablated.algorithms.graph_kind(),
+                   ::components::graph::GraphKind::BitReversal);
+
+        let mut without_f = dragonfly.clone().ablated().disable_f();
+        let ablated_f = without_f.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        assert!(ablated_f != expected);
+        assert_eq!(without_f.algorithms.graph_kind(),
+                   ::components::graph::GraphKind::Unknown);
+
+        let mut hash_chain = ::catena::Catena {
+            algorithms: HashChain { blake2b_1: Default::default() },
+            vid: dragonfly.vid,
+            n: dragonfly.n,
+            k: dragonfly.k,
+            g_low: dragonfly.g_low,
+            g_high: dragonfly.g_high,
+            lambda: dragonfly.lambda,
+            options: dragonfly.options,
+        };
+        let chain = hash_chain.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+
+        // flap only outputs the last word, which SaltMix does not update for
+        // this γ, and Φ of Dragonfly is the identity, so without F only the
+        // hash chain remains
+        assert_eq!(ablated_f, chain);
+
+        let mut without_all = without_f.disable_gamma().disable_phi();
+        assert!(!without_all.algorithms.uses_gamma());
+        assert_eq!(without_all.hash(&pwd, &salt, &ad, 64, &gamma)
+                   .unwrap(), chain);
+    }
+
+    fn check_h_prime_invocations<T: ::catena::Algorithms + Clone>(
+            mut bare: ::catena::Catena<T>, garlic: u8) {
+        bare.g_low = garlic;
+        bare.g_high = garlic;
+
+        let mut recomposed = ::catena::Catena {
+            algorithms: Recomposed::new(bare.algorithms.clone()),
+            vid: bare.vid,
+            n: bare.n,
+            k: bare.k,
+            g_low: bare.g_low,
+            g_high: bare.g_high,
+            lambda: bare.lambda,
+            options: bare.options,
+        };
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        assert_eq!(recomposed.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(),
+                   bare.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+
+        // one flap with (g_low + 1) / 2 and one with g_low
+        let expected = recomposed.h_prime_invocations((garlic + 1) / 2).unwrap()
+            + recomposed.h_prime_invocations(garlic).unwrap();
+        assert_eq!(recomposed.algorithms.counts().h_prime, expected,
+                   "{}", recomposed.vid);
+    }
+
+    #[test]
+    fn h_prime_invocations_test() {
+        check_h_prime_invocations(::default_instances::dragonfly::new(), 9);
+        check_h_prime_invocations(::default_instances::dragonfly_full::new(), 9);
+        check_h_prime_invocations(::default_instances::butterfly::new(), 9);
+        check_h_prime_invocations(::default_instances::butterfly_full::new(), 9);
+        // Φ-lsb and no Γ
+        check_h_prime_invocations(::variants::mydasfly::new(), 9);
+        check_h_prime_invocations(::variants::lanternfly::new(), 9);
+    }
+
+    #[test]
+    fn h_prime_invocations_formula_test() {
+        let dragonfly = ::default_instances::dragonfly::new();
+        // 2^g + λ(2^g - 1) + 2^ceil(3g/4)
+        assert_eq!(dragonfly.h_prime_invocations(8), Some(256 + 2 * 255 + 64));
+        let butterfly = ::default_instances::butterfly::new();
+        // 2^g + λ(2g - 1)(2^g - 1) + 2^ceil(3g/4)
+        assert_eq!(butterfly.h_prime_invocations(8),
+                   Some(256 + 4 * 15 * 255 + 64));
+    }
+}
@@ -5,3 +5,7 @@ pub mod gamma;
 pub mod graph;
 pub mod hash;
 pub mod fasthash;
+pub mod instrument;
+pub mod output;
+#[cfg(feature = "rand")]
+pub mod bench;
@@ -0,0 +1,153 @@
+//! Strategies to bring the output of Catena to the requested length
+
+use bytes::Bytes;
+
+/// Produces the final output of `m` bytes from the output `x` of Catena.
+pub trait OutputStrategy {
+    /// Finalize the output `x` to `m` bytes. `algorithms` provides H for
+    /// strategies which expand the output.
+    fn finalize<T: ::catena::Algorithms>(
+        &mut self,
+        algorithms: &T,
+        x: Vec<u8>,
+        m: usize
+    ) -> Vec<u8>;
+}
+
+/// Truncate the output to `m` bytes. An output shorter than `m` bytes is
+/// returned unchanged. This is the output of `Catena::hash`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Truncate;
+
+impl OutputStrategy for Truncate {
+    fn finalize<T: ::catena::Algorithms>(
+        &mut self,
+        _algorithms: &T,
+        x: Vec<u8>,
+        m: usize
+    ) -> Vec<u8> {
+        let mut x = x;
+        x.truncate(m);
+        x
+    }
+}
+
+/// Expand the output to `m` bytes with the counter mode of Catena-KG, i.e.
+/// the concatenation of `H(i || key_identifier || m || x)` for the 2-byte
+/// little-endian counters `i = 1, 2, ...`, truncated to `m` bytes. This is
+/// the output of `Catena::generate_key`.
+///
+/// # Panics
+///
+/// As `m` and the counter are encoded as 2-byte values, the output can have
+/// at most 65535 bytes, see `Catena::generate_key_large` for larger keys.
+/// Expanding to a longer output panics.
+#[derive(Clone, Debug)]
+pub struct CounterExpand {
+    /// The key identifier of Catena-KG.
+    pub key_identifier: Vec<u8>,
+    /// The output length of H in bytes.
+    pub n: usize,
+}
+
+impl CounterExpand {
+    /// The number of blocks of `n` bytes of an output of `m` bytes.
+    pub fn blocks(&self, m: usize) -> usize {
+        (m + self.n - 1) / self.n
+    }
+
+    /// The blocks `start..end` of the output of `m` bytes, counted from 0.
+    /// The last block of the output is truncated, so the concatenation of
+    /// consecutive ranges equals `finalize`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is larger than 65535.
+    pub fn expand_blocks<T: ::catena::Algorithms>(
+        &self,
+        algorithms: &T,
+        x: &Vec<u8>,
+        m: usize,
+        start: usize,
+        end: usize
+    ) -> Vec<u8> {
+        assert!(m <= u16::max_value() as usize,
+                "output of {} bytes, at most 65535 are supported", m);
+        let end = end.min(self.blocks(m));
+        let key_size = (m as u16).to_le_vec();
+        let mut k: Vec<u8> = Vec::new();
+
+        for i in start + 1..end + 1 {
+            k.append(&mut algorithms.h(&[
+                &(i as u16).to_le_vec()[..],
+                &self.key_identifier[..],
+                &key_size[..],
+                &x[..]].concat()));
+        }
+
+        if start < end && end * self.n > m {
+            let len = k.len() - (end * self.n - m);
+            k.truncate(len);
+        }
+        k
+    }
+}
+
+impl OutputStrategy for CounterExpand {
+    fn finalize<T: ::catena::Algorithms>(
+        &mut self,
+        algorithms: &T,
+        x: Vec<u8>,
+        m: usize
+    ) -> Vec<u8> {
+        let blocks = self.blocks(m);
+        self.expand_blocks(algorithms, &x, m, 0, blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_test() {
+        let algorithms = ::default_instances::dragonfly::new().algorithms;
+        let x = vec![0x42; 64];
+
+        assert_eq!(Truncate.finalize(&algorithms, x.clone(), 32),
+                   vec![0x42; 32]);
+        assert_eq!(Truncate.finalize(&algorithms, x.clone(), 64), x);
+        assert_eq!(Truncate.finalize(&algorithms, x.clone(), 100), x);
+    }
+
+    #[test]
+    fn counter_expand_test() {
+        let algorithms = ::default_instances::dragonfly::new().algorithms;
+        let x = vec![0x42; 64];
+        let mut strategy = CounterExpand {
+            key_identifier: b"key".to_vec(),
+            n: 64,
+        };
+
+        for &m in [1usize, 64, 65, 200].iter() {
+            assert_eq!(strategy.finalize(&algorithms, x.clone(), m).len(), m);
+        }
+
+        let short = strategy.finalize(&algorithms, x.clone(), 64);
+        let long = strategy.finalize(&algorithms, x.clone(), 128);
+        assert_eq!(short, ::components::hash::blake2b::hash(
+            &[&[1, 0][..], &b"key"[..], &[64, 0][..], &x[..]].concat()));
+        assert_ne!(short[..], long[..64]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn counter_expand_too_long_test() {
+        let algorithms = ::default_instances::dragonfly::new().algorithms;
+        let mut strategy = CounterExpand {
+            key_identifier: b"key".to_vec(),
+            n: 64,
+        };
+        let _ = strategy.finalize(&algorithms, vec![0x42; 64], 1 << 16);
+    }
+}
@@ -3,7 +3,12 @@
 /// Index function that returns the g last bits.
 fn lsb(v: &Vec<u8>, g: u8) -> usize {
     let mask: u64 = 0xFFFFFFFFFFFFFFFF - ((1 << g) - 1);
-    let last = ::helpers::conversions::bytes_to_u64_be(v, v.len() - 8);
+    let last = match v.len().checked_sub(8).and_then(
+        |offset| ::helpers::conversions::try_bytes_to_u64_be(v, offset)) {
+        Some(last) => last,
+        // states shorter than 8 bytes are read as a whole
+        None => v.iter().fold(0u64, |last, &byte| (last << 8) | byte as u64),
+    };
     let result = last & !mask;
     result as usize
 }
@@ -30,6 +35,14 @@ mod tests {
     use super::*;
     use helpers::files::JSONTests;
 
+    #[test]
+    fn lsb_short_state_test() {
+        let state = vec![0x12, 0x34, 0x56, 0x78];
+        assert_eq!(lsb(&state, 8), 0x78);
+        assert_eq!(lsb(&state, 12), 0x678);
+        assert_eq!(lsb(&Vec::new(), 8), 0);
+    }
+
     #[test]
     fn lsb_index_test() {
         let json = ::helpers::files::open_json("test/test_vectors/lsbIndex.json".to_string());
@@ -0,0 +1,167 @@
+//! Test vectors of Catena in the JSON format of the reference implementation.
+//!
+//! A file of test vectors is an array of objects with the hex-encoded inputs
+//! `pwd`, `salt`, `gamma` and `aData`, the integer `outputLength` and the
+//! hex-encoded output `res`:
+//!
+//! ```json
+//! [{"inputs": {"pwd": "…", "salt": "…", "gamma": "…", "aData": "…",
+//!              "outputLength": 64},
+//!   "outputs": {"res": "…"}}]
+//! ```
+//!
+//! All vectors are validated when they are loaded, so a malformed file yields
+//! an error naming the vector and the field instead of a panic in the middle
+//! of a test.
+//!
+//! This module requires the `json` feature, which adds the dependency on
+//! `serde_json`.
+extern crate serde_json;
+
+use catena::CatenaError;
+
+use std::fs::File;
+use std::io::Read;
+
+/// One test vector of Catena.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    /// The password.
+    pub pwd: Vec<u8>,
+    /// The salt.
+    pub salt: Vec<u8>,
+    /// The public input γ.
+    pub gamma: Vec<u8>,
+    /// The associated data.
+    pub associated_data: Vec<u8>,
+    /// The output length in bytes.
+    pub output_length: u16,
+    /// The expected output.
+    pub res: Vec<u8>,
+}
+
+/// Load and validate the test vectors of the JSON file at `path`.
+pub fn load_vectors(path: &str) -> Result<Vec<TestVector>, CatenaError> {
+    let mut json = String::new();
+    let _ = File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut json))
+        .map_err(|e| CatenaError::IoFailed { kind: e.kind() })?;
+    parse_vectors(&json)
+}
+
+/// Parse and validate test vectors from a JSON string, see `load_vectors`.
+pub fn parse_vectors(json: &str) -> Result<Vec<TestVector>, CatenaError> {
+    let value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|_| CatenaError::InvalidJson)?;
+    let vectors = value.as_array().ok_or(CatenaError::InvalidJson)?;
+
+    let mut result = Vec::with_capacity(vectors.len());
+    for (index, vector) in vectors.iter().enumerate() {
+        let inputs = &vector["inputs"];
+        let outputs = &vector["outputs"];
+        let output_length = inputs["outputLength"].as_u64()
+            .and_then(|len| if len <= u16::max_value() as u64 {
+                Some(len as u16)
+            } else {
+                None
+            })
+            .ok_or(CatenaError::InvalidTestVector {
+                index: index,
+                field: "outputLength",
+            })?;
+
+        result.push(TestVector {
+            pwd: hex_field(inputs, index, "pwd")?,
+            salt: hex_field(inputs, index, "salt")?,
+            gamma: hex_field(inputs, index, "gamma")?,
+            associated_data: hex_field(inputs, index, "aData")?,
+            output_length: output_length,
+            res: hex_field(outputs, index, "res")?,
+        });
+    }
+    Ok(result)
+}
+
+/// The hex-encoded field `field` of the vector `index`.
+fn hex_field(
+    value: &serde_json::Value,
+    index: usize,
+    field: &'static str
+) -> Result<Vec<u8>, CatenaError> {
+    value[field].as_str()
+        .and_then(decode_hex)
+        .ok_or(CatenaError::InvalidTestVector { index: index, field: field })
+}
+
+/// Decode a hex string of upper or lower case digits.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let high = (pair[0] as char).to_digit(16)?;
+            let low = (pair[1] as char).to_digit(16)?;
+            Some((high * 16 + low) as u8)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VECTOR: &'static str = r#"[{
+        "inputs": {"pwd": "70617373776f7264", "salt": "73616c74",
+                   "gamma": "", "aData": "00FF", "outputLength": 32},
+        "outputs": {"res": "0102"}
+    }]"#;
+
+    #[test]
+    fn parse_vectors_test() {
+        let vectors = parse_vectors(VECTOR).unwrap();
+        assert_eq!(vectors, vec![TestVector {
+            pwd: b"password".to_vec(),
+            salt: b"salt".to_vec(),
+            gamma: Vec::new(),
+            associated_data: vec![0x00, 0xff],
+            output_length: 32,
+            res: vec![0x01, 0x02],
+        }]);
+    }
+
+    #[test]
+    fn missing_field_test() {
+        let json = VECTOR.replace(r#""salt": "73616c74","#, "");
+        let result = parse_vectors(&json);
+        assert_eq!(result, Err(CatenaError::InvalidTestVector {
+            index: 0,
+            field: "salt",
+        }));
+        assert_eq!(format!("{}", result.unwrap_err()),
+                   "test vector 0: field \"salt\" is missing or invalid");
+
+        let json = VECTOR.replace("\"0102\"", "\"0x\"");
+        assert_eq!(parse_vectors(&json), Err(CatenaError::InvalidTestVector {
+            index: 0,
+            field: "res",
+        }));
+        let json = VECTOR.replace("32", "65536");
+        assert_eq!(parse_vectors(&json), Err(CatenaError::InvalidTestVector {
+            index: 0,
+            field: "outputLength",
+        }));
+    }
+
+    #[test]
+    fn invalid_json_test() {
+        assert_eq!(parse_vectors("{\"inputs\": {}}"),
+                   Err(CatenaError::InvalidJson));
+        assert_eq!(parse_vectors("[{"), Err(CatenaError::InvalidJson));
+        assert_eq!(load_vectors("test/test_vectors/nonexistent.json"),
+                   Err(CatenaError::IoFailed {
+                       kind: ::std::io::ErrorKind::NotFound,
+                   }));
+    }
+}
@@ -34,11 +34,15 @@ impl ::catena::Algorithms for ButterflyAlgorithms {
     }
 
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::double_butterfly_graph::double_butterfly_hash(
             self, garlic, state.clone(), lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::DoubleButterfly
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -46,6 +50,9 @@ impl ::catena::Algorithms for ButterflyAlgorithms {
 }
 
 /// Constructor for a Catena-Butterfly instance.
+///
+/// With n = k = 64 the parameters always pass `Catena::check_parameters`,
+/// so `hash` only fails for an empty salt or an output longer than 64 bytes.
 pub fn new() -> ::catena::Catena<ButterflyAlgorithms> {
     let bf_algorithms = ButterflyAlgorithms {
         blake2b_1: Default::default(),
@@ -58,5 +65,6 @@ pub fn new() -> ::catena::Catena<ButterflyAlgorithms> {
         g_low: 16,
         g_high: 16,
         lambda: 4,
+        options: ::catena::Options::default(),
         }
 }
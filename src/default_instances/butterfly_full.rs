@@ -29,11 +29,15 @@ impl ::catena::Algorithms for ButterflyFullAlgorithms {
     }
 
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::double_butterfly_graph::double_butterfly_hash(
             self, garlic, state.clone(), lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::DoubleButterfly
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -41,6 +45,9 @@ impl ::catena::Algorithms for ButterflyFullAlgorithms {
 }
 
 /// Constructor for a Catena-Butterfly-Full instance.
+///
+/// With n = k = 64 the parameters always pass `Catena::check_parameters`,
+/// so `hash` only fails for an empty salt or an output longer than 64 bytes.
 pub fn new() -> ::catena::Catena<ButterflyFullAlgorithms> {
     let bff_algorithms = ButterflyFullAlgorithms;
     ::catena::Catena {
@@ -51,5 +58,6 @@ pub fn new() -> ::catena::Catena<ButterflyFullAlgorithms> {
         g_low: 17,
         g_high: 17,
         lambda: 4,
+        options: ::catena::Options::default(),
         }
 }
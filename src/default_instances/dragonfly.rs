@@ -11,6 +11,9 @@
 /// - F: BRH(21,2)
 /// - Γ: SaltMix
 /// - Φ: Identity function
+///
+/// The seed of SaltMix is computed once per hash, see
+/// `Algorithms::gamma_cached`.
 #[derive(Clone, Copy, Debug)]
 pub struct DragonflyAlgorithms {
     blake2b_1: ::components::fasthash::blake2b1::Blake2b1,
@@ -33,12 +36,23 @@ impl ::catena::Algorithms for DragonflyAlgorithms {
         ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
     }
 
+    fn gamma_cached (
+        &mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize,
+        cache: &mut ::components::gamma::saltmix::SeedCache) -> Vec<u8> {
+        ::components::gamma::saltmix::saltmix_cached(
+            self, cache, garlic, state, gamma, k)
+    }
+
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::generic_graph::bit_reversal_hash(
             self, garlic, state, lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::BitReversal
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -46,6 +60,9 @@ impl ::catena::Algorithms for DragonflyAlgorithms {
 }
 
 /// Constructor for a Catena-Dragonfly instance.
+///
+/// With n = k = 64 the parameters always pass `Catena::check_parameters`,
+/// so `hash` only fails for an empty salt or an output longer than 64 bytes.
 pub fn new() -> ::catena::Catena<DragonflyAlgorithms> {
     let df_algorithms = DragonflyAlgorithms {
         blake2b_1: Default::default(),
@@ -58,5 +75,6 @@ pub fn new() -> ::catena::Catena<DragonflyAlgorithms> {
         g_low: 21,
         g_high: 21,
         lambda: 2,
+        options: ::catena::Options::default(),
         }
 }
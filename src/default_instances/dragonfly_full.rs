@@ -29,11 +29,15 @@ impl ::catena::Algorithms for DragonflyFullAlgorithms {
     }
 
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::generic_graph::bit_reversal_hash(
             self, garlic, state, lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::BitReversal
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -41,6 +45,9 @@ impl ::catena::Algorithms for DragonflyFullAlgorithms {
 }
 
 /// Constructor for a Catena-Dragonfly-Full instance.
+///
+/// With n = k = 64 the parameters always pass `Catena::check_parameters`,
+/// so `hash` only fails for an empty salt or an output longer than 64 bytes.
 pub fn new() -> ::catena::Catena<DragonflyFullAlgorithms> {
     let dff_algorithms = DragonflyFullAlgorithms;
     ::catena::Catena {
@@ -51,6 +58,7 @@ pub fn new() -> ::catena::Catena<DragonflyFullAlgorithms> {
         g_low: 22,
         g_high: 22,
         lambda: 2,
+        options: ::catena::Options::default(),
         }
 }
 
@@ -0,0 +1,135 @@
+//! Getting and setting the CPU affinity of the current thread on Linux.
+extern crate libc;
+
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::mem::size_of;
+
+use catena::CatenaError;
+
+/// A set of CPUs as used by `sched_getaffinity` and `sched_setaffinity`.
+pub type CpuSet = libc::cpu_set_t;
+
+fn last_errno() -> i32 {
+    io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+/// Create a `CpuSet` containing the given CPUs.
+#[allow(unsafe_code)]
+pub fn cpu_set(cpus: &[usize]) -> Result<CpuSet, CatenaError> {
+    let mut set: CpuSet = unsafe { mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(CatenaError::AffinityFailed { errno: libc::EINVAL });
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    Ok(set)
+}
+
+/// The CPU affinity of the current thread.
+#[allow(unsafe_code)]
+pub fn get() -> Result<CpuSet, CatenaError> {
+    let mut set: CpuSet = unsafe { mem::zeroed() };
+    let result = unsafe {
+        libc::sched_getaffinity(0, size_of::<CpuSet>(), &mut set)
+    };
+    if result == 0 {
+        Ok(set)
+    } else {
+        Err(CatenaError::AffinityFailed { errno: last_errno() })
+    }
+}
+
+/// Set the CPU affinity of the current thread.
+#[allow(unsafe_code)]
+pub fn set(set: &CpuSet) -> Result<(), CatenaError> {
+    let result = unsafe {
+        libc::sched_setaffinity(0, size_of::<CpuSet>(), set)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(CatenaError::AffinityFailed { errno: last_errno() })
+    }
+}
+
+/// Restores the previous CPU affinity of the current thread when dropped,
+/// also if the thread panics while pinned. It can not be sent to another
+/// thread, as it would restore the affinity of that thread.
+#[derive(Debug)]
+pub struct Pinned {
+    previous: CpuSet,
+    thread: PhantomData<*const ()>,
+}
+
+/// Set the CPU affinity of the current thread to `cpus` until the returned
+/// guard is dropped.
+pub fn pin(cpus: &[usize]) -> Result<Pinned, CatenaError> {
+    let previous = get()?;
+    set(&cpu_set(cpus)?)?;
+    Ok(Pinned {
+        previous: previous,
+        thread: PhantomData,
+    })
+}
+
+impl Drop for Pinned {
+    fn drop(&mut self) {
+        // the previous set was valid for this thread, so restoring it can
+        // only fail if the CPUs went offline in the meantime
+        let _ = set(&self.previous);
+    }
+}
+
+/// The CPUs contained in `set`.
+#[cfg(test)]
+#[allow(unsafe_code)]
+pub fn cpus(set: &CpuSet) -> Vec<usize> {
+    (0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, set) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_restore_test() {
+        let original = get().unwrap();
+        let allowed = cpus(&original);
+        assert!(!allowed.is_empty());
+
+        set(&cpu_set(&allowed[..1]).unwrap()).unwrap();
+        assert_eq!(cpus(&get().unwrap()), allowed[..1].to_vec());
+
+        set(&original).unwrap();
+        assert_eq!(cpus(&get().unwrap()), allowed);
+    }
+
+    #[test]
+    fn pin_restores_on_panic_test() {
+        let allowed = cpus(&get().unwrap());
+        let first = allowed[0];
+
+        let result = ::std::panic::catch_unwind(|| {
+            let _pinned = pin(&[first]).unwrap();
+            assert_eq!(cpus(&get().unwrap()), vec![first]);
+            panic!("while pinned");
+        });
+        assert!(result.is_err());
+        assert_eq!(cpus(&get().unwrap()), allowed);
+
+        assert!(pin(&[]).is_err());
+        assert_eq!(cpus(&get().unwrap()), allowed);
+    }
+
+    #[test]
+    fn invalid_cpu_test() {
+        assert_eq!(cpu_set(&[libc::CPU_SETSIZE as usize]).err(),
+                   Some(CatenaError::AffinityFailed { errno: libc::EINVAL }));
+        assert!(set(&cpu_set(&[]).unwrap()).is_err());
+    }
+}
@@ -34,6 +34,15 @@ pub fn bytes_to_u64_be(bytes: &[u8], offset: usize) -> u64 {
     ( bytes[offset + 7] as u64 & 0xFF)
 }
 
+/// Convert 8 bytes of a `&[u8]` to a big-endian `u64` value. Returns `None`
+/// if `bytes` has less than 8 bytes starting at `offset`.
+pub fn try_bytes_to_u64_be(bytes: &[u8], offset: usize) -> Option<u64> {
+    match offset.checked_add(8) {
+        Some(end) if end <= bytes.len() => Some(bytes_to_u64_be(bytes, offset)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +53,14 @@ mod tests {
         let input = vec![0u8; 2];
         let _out = vec_u8_to_vec_u64(&input);
     }
+
+    #[test]
+    fn try_bytes_to_u64_be_test() {
+        let bytes = [1u8, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(try_bytes_to_u64_be(&bytes, 0), Some(0x0102030405060708));
+        assert_eq!(try_bytes_to_u64_be(&bytes, 1), Some(0x0203040506070809));
+        assert_eq!(try_bytes_to_u64_be(&bytes, 2), None);
+        assert_eq!(try_bytes_to_u64_be(&bytes[..4], 0), None);
+        assert_eq!(try_bytes_to_u64_be(&bytes, usize::max_value()), None);
+    }
 }
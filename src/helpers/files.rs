@@ -60,7 +60,7 @@ impl JSONTests for serde_json::Value {
     fn parse_hex(&self, field_name: &str) -> Vec<u8> {
         let parsed_string = self[field_name].to_string();
         let trimmed_string = parsed_string.trim_matches('\"');
-        trimmed_string.to_string().to_be_bytes()
+        trimmed_string.to_string().to_be_vec()
     }
 
     fn parse_u8(&self, field_name: &str) -> u8 {
@@ -0,0 +1,7 @@
+//! Inputs shared by the tests.
+
+/// The password, salt, associated data and γ used by most tests.
+pub fn test_inputs() -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>) {
+    (b"password".to_vec(), b"salt".to_vec(), b"data".to_vec(),
+     b"gamma".to_vec())
+}
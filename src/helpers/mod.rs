@@ -1,3 +1,8 @@
 pub mod conversions;
 pub mod vectors;
+#[cfg(any(test, feature = "json"))]
 pub mod files;
+#[cfg(test)]
+pub mod fixtures;
+#[cfg(all(target_os = "linux", feature = "affinity"))]
+pub mod affinity;
@@ -1,3 +1,33 @@
+/// Reserve capacity for at least `bytes` more bytes in `v` or return
+/// `CatenaError::AllocationFailed` instead of aborting.
+pub fn try_reserve(v: &mut Vec<u8>, bytes: usize)
+    -> Result<(), ::catena::CatenaError> {
+    v.try_reserve(bytes)
+        .map_err(|_| ::catena::CatenaError::AllocationFailed { bytes: bytes })
+}
+
+/// Copy `v` into a new vector or return `CatenaError::AllocationFailed`
+/// instead of aborting.
+pub fn try_copy(v: &[u8]) -> Result<Vec<u8>, ::catena::CatenaError> {
+    let mut copy = Vec::new();
+    try_reserve(&mut copy, v.len())?;
+    copy.extend_from_slice(v);
+    Ok(copy)
+}
+
+/// Compare two byte slices in constant time with respect to their contents.
+/// Only the lengths are compared with an early return.
+pub fn ct_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    if lhs.len() != rhs.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for i in 0..lhs.len() {
+        diff |= lhs[i] ^ rhs[i];
+    }
+    diff == 0
+}
+
 /// Concatenate p 0-bytes to a Vec<u8> x.
 pub fn zero_padding(x: Vec<u8>, p: usize) -> Vec<u8> {
     let padding = vec![0;p];
@@ -37,6 +67,14 @@ pub fn xor(lhs: Vec<u8>, rhs: Vec<u8>) -> Vec<u8> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn ct_eq_test() {
+        assert!(ct_eq(&[1, 2, 3], &[1, 2, 3]));
+        assert!(ct_eq(&[], &[]));
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2, 4]));
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+    }
+
     #[test]
     fn zero_padding_test() {
         let x = vec![1u8];
@@ -0,0 +1,199 @@
+//! Typed inputs of Catena.
+//!
+//! Catena takes two public, password-independent inputs which are easy to
+//! confuse:
+//!
+//! - The salt is part of the initial hash `H(t || pwd || salt)`. It should be
+//!   chosen randomly for each password.
+//! - γ (gamma) is the input of the password-independent random layer Γ. For
+//!   the default instances it seeds SaltMix, which is why the specification
+//!   sometimes calls it a salt as well.
+//!
+//! Using the newtypes `Salt` and `Gamma` with `Catena::hash_typed` turns an
+//! accidental transposition of the two into a compile error.
+//!
+//! The outputs are typed as well: `Catena::hash` returns a `PasswordHash`,
+//! which `Catena::verify` checks, and `Catena::generate_key` returns a
+//! `DerivedKey`. A derived key can not be stored or checked as a password
+//! hash by accident.
+
+#[cfg(feature = "rand")]
+extern crate rand;
+
+use bytes::HexRepresentation;
+use catena::CatenaError;
+#[cfg(feature = "rand")]
+use self::rand::OsRng;
+#[cfg(feature = "rand")]
+use self::rand::Rng;
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+/// The salt of a password hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Salt(pub Vec<u8>);
+
+/// The input γ of the password-independent random layer Γ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gamma(pub Vec<u8>);
+
+/// A password hash, the output of `Catena::hash`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordHash(pub Vec<u8>);
+
+/// A key derived with Catena-KG, the output of `Catena::generate_key`.
+///
+/// A derived key is not a password hash, so it can not be checked with
+/// `Catena::verify`:
+///
+/// ```compile_fail
+/// use catena::inputs::DerivedKey;
+///
+/// let mut dragonfly = catena::default_instances::dragonfly::new();
+/// let key = DerivedKey(vec![0; 64]);
+/// dragonfly.verify(&b"password".to_vec(), &b"salt".to_vec(), &Vec::new(),
+///                  &Vec::new(), &key);
+/// ```
+///
+/// `Debug` only prints the length, so a key does not end up in logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DerivedKey(pub Vec<u8>);
+
+impl PasswordHash {
+    /// The bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerivedKey {
+    /// The bytes of the key.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DerivedKey({} bytes)", self.0.len())
+    }
+}
+
+impl HexRepresentation for PasswordHash {
+    fn to_hex_string(&self) -> String {
+        self.0.to_hex_string()
+    }
+}
+
+impl HexRepresentation for DerivedKey {
+    fn to_hex_string(&self) -> String {
+        self.0.to_hex_string()
+    }
+}
+
+impl From<Vec<u8>> for Salt {
+    fn from(salt: Vec<u8>) -> Salt {
+        Salt(salt)
+    }
+}
+
+impl From<Vec<u8>> for Gamma {
+    fn from(gamma: Vec<u8>) -> Gamma {
+        Gamma(gamma)
+    }
+}
+
+/// Read γ as raw bytes from the file at `path`.
+///
+/// γ does not depend on the password, so it is often stored next to the
+/// configuration instead of being passed on the command line.
+pub fn load_gamma(path: &str) -> Result<Vec<u8>, CatenaError> {
+    let mut gamma = Vec::new();
+    let _ = File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut gamma))
+        .map_err(|e| CatenaError::IoFailed { kind: e.kind() })?;
+    Ok(gamma)
+}
+
+/// Generate a random salt of `len` bytes with the random number generator
+/// of the operating system, see `gen_salt_from`. Only available with the
+/// default feature `rand`.
+#[cfg(feature = "rand")]
+pub fn gen_salt(len: usize) -> Result<Vec<u8>, CatenaError> {
+    let mut rng = OsRng::new()
+        .map_err(|e| CatenaError::IoFailed { kind: e.kind() })?;
+    Ok(gen_salt_from(&mut rng, len))
+}
+
+/// Generate a salt of `len` bytes with the random number generator `rng`,
+/// e.g. a hardware generator or a seeded one in tests. The generator has to
+/// be cryptographically secure for salts of real passwords.
+#[cfg(feature = "rand")]
+pub fn gen_salt_from<R: Rng>(rng: &mut R, len: usize) -> Vec<u8> {
+    let mut salt = vec![0u8; len];
+    rng.fill_bytes(&mut salt);
+    salt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+    use std::process;
+    use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
+
+    /// A path in the temporary directory which no other test run uses.
+    fn unique_temp_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+            .subsec_nanos();
+        env::temp_dir().join(
+            format!("catena-{}-{}-{}", name, process::id(), nanos))
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn gen_salt_test() {
+        let salt = gen_salt(16).unwrap();
+        assert_eq!(salt.len(), 16);
+        assert_ne!(salt, gen_salt(16).unwrap());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn gen_salt_from_test() {
+        use self::rand::SeedableRng;
+        use self::rand::StdRng;
+
+        let seed: &[usize] = &[1, 2, 3, 4];
+        let salt = gen_salt_from(&mut StdRng::from_seed(seed), 16);
+        assert_eq!(salt.len(), 16);
+        assert_eq!(salt, gen_salt_from(&mut StdRng::from_seed(seed), 16));
+
+        let other: &[usize] = &[4, 3, 2, 1];
+        assert_ne!(salt, gen_salt_from(&mut StdRng::from_seed(other), 16));
+    }
+
+    #[test]
+    fn load_gamma_test() {
+        let path = unique_temp_path("load-gamma-test");
+        let gamma = vec![0x00, 0x67, 0x61, 0x6d, 0x6d, 0x61, 0xff];
+        fs::write(&path, &gamma).unwrap();
+
+        let loaded = load_gamma(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, Ok(gamma));
+    }
+
+    #[test]
+    fn load_gamma_missing_file_test() {
+        let path = unique_temp_path("load-gamma-missing");
+        assert_eq!(load_gamma(path.to_str().unwrap()),
+                   Err(CatenaError::IoFailed { kind: ErrorKind::NotFound }));
+    }
+}
@@ -21,6 +21,8 @@
 //!   pub g_high: u8,
 //!   /// The depth of the graph structure.
 //!   pub lambda: u8,
+//!   /// Optional, non-standard modifications of the construction.
+//!   pub options: Options,
 //! }
 //! ```
 //!
@@ -41,11 +43,21 @@
 //!   /// The optional password-independent random layer Γ
 //!   fn gamma(&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8>;
 //!
+//!   /// Whether Γ uses γ.
+//!   fn uses_gamma(&self) -> bool { true }
+//!
 //!   /// The graph-based hash function F
-//!   fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize) -> Vec<u8>;
+//!   fn f(&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+//!       -> Result<Vec<u8>, CatenaError>;
+//!
+//!   /// The kind of graph used by F, if it is one of `components::graph`.
+//!   fn graph_kind(&self) -> GraphKind { GraphKind::Unknown }
 //!
 //!   /// The optional password-dependent random layer Φ
 //!   fn phi(&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8>;
+//!
+//!   /// Whether Φ reads words at password-dependent indices.
+//!   fn phi_is_data_dependent(&self) -> bool { false }
 //! }
 //! ```
 //!
@@ -56,7 +68,8 @@
 //!
 //! ```
 //! let mut catena_dff = catena::default_instances::dragonfly_full::new();
-//! let hash = catena_dff.hash(&pwd, &salt, &ad, output_length, &gamma);
+//! let hash = catena_dff.hash(&pwd, &salt, &ad, output_length, &gamma)
+//!     .unwrap();
 //! ```
 //!
 //! The second possibility is to create a custom Catena instance. First one has to
@@ -79,7 +92,7 @@
 //!   ) -> Vec<u8> { state }
 //!
 //!   fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize
-//!   ) -> Vec<u8> { state.clone() }
+//!   ) -> Result<Vec<u8>, catena::catena::CatenaError> { Ok(state.clone()) }
 //!
 //!   fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> { state }
 //! }
@@ -88,25 +101,19 @@
 //! In this example we define a Catena instance
 //! which uses Blake2b for H and H' and the identity function for Gamma, F, and Phi.
 //!
-//! Then we can instantiate a `Catena` struct and set the values for the version ID,
-//! g_low, g_high, lambda, n and k:
+//! Then we can instantiate a `Catena` struct with the values for the version ID,
+//! n, k, g_low, g_high and lambda:
 //!
 //! ```
-//! let mut custom_catena = catena::catena::Catena {
-//!     algorithms: CustomCatena,
-//!     vid: "CustomCatena",
-//!     g_low: 10,
-//!     g_high: 10,
-//!     lambda: 10,
-//!     n: 64,
-//!     k: 64,
-//! };
+//! let mut custom_catena = catena::catena::Catena::new(
+//!     CustomCatena, "CustomCatena", 64, 64, 10, 10, 10);
 //! ```
 //!
 //! After that we can use this `Catena` instance like the predefined default instances and variants:
 //!
 //! ```
-//! let hash = custom_catena.hash(&pwd, &salt, &ad, output_length, &gamma);
+//! let hash = custom_catena.hash(&pwd, &salt, &ad, output_length, &gamma)
+//!     .unwrap();
 //! ```
 
 #![deny(missing_docs,
@@ -120,13 +127,35 @@
         unused_qualifications,
         unused_results)]
 
+#[macro_use]
+mod macros;
 pub mod catena;
 pub mod default_instances;
 pub mod variants;
 pub mod components;
 pub mod bytes;
+pub mod inputs;
+pub mod registry;
+pub mod cli;
+pub mod phc;
+pub mod pow;
+#[cfg(any(test, feature = "json"))]
+pub mod conformance;
 mod helpers;
 
+pub use catena::build_info;
+pub use catena::commit_hashes;
+pub use catena::endianness_report;
+pub use catena::known_answers;
+#[cfg(feature = "rand")]
+pub use inputs::gen_salt;
+#[cfg(feature = "rand")]
+pub use inputs::gen_salt_from;
+pub use inputs::load_gamma;
+pub use phc::from_phc_header;
+pub use phc::verify_phc;
+pub use registry::recommend;
+
 #[cfg(test)]
 mod tests {
 }
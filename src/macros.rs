@@ -0,0 +1,160 @@
+//! Macros to define variants of Catena.
+
+/// Define a variant of Catena from a choice of components.
+///
+/// This generates a unit struct implementing `catena::Algorithms` with the
+/// given components and a constructor `new()` for an instance with the given
+/// parameters and `g_low = g_high = g`. The possible components are:
+///
+/// - `h`: `blake2b`
+/// - `h_prime`: `blake2b`, `cf_argon2_gb`, `cf_argon2_gl` or the weaker
+///   `cf_argon2_gb_reduced` without the column pass
+/// - `f`: `brh` (bit-reversal graph), `gray` (Gray-reversal graph with
+///   `l = 3`), `double_butterfly` or the experimental `sponge` (needs
+///   `k = 1024`)
+/// - `gamma`: `saltmix` or `identity`
+/// - `phi`: `lsb` or `identity`
+///
+/// The generated struct has no state, so H' can not be the stateful Blake2b-1
+/// of the default instances.
+///
+/// ```ignore
+/// define_variant!(
+///     /// The choices for H, H', F, Γ and Φ for Catena-Stonefly.
+///     StoneflyAlgorithms,
+///     vid = "Stonefly", n = 64, k = 1024, g = 18, lambda = 2,
+///     h = blake2b, h_prime = cf_argon2_gb, f = brh, gamma = saltmix,
+///     phi = lsb);
+/// ```
+#[macro_export]
+macro_rules! define_variant {
+    (
+        $(#[$attr:meta])*
+        $name:ident,
+        vid = $vid:expr, n = $n:expr, k = $k:expr, g = $g:expr,
+        lambda = $lambda:expr,
+        h = $h:ident, h_prime = $h_prime:ident, f = $f:ident,
+        gamma = $gamma:ident, phi = $phi:ident
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name;
+
+        impl $crate::catena::Algorithms for $name {
+            fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+                define_variant!(@hash $h, x)
+            }
+
+            fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+                define_variant!(@hash $h_prime, x)
+            }
+
+            #[allow(unused_variables)]
+            fn gamma (&mut self, garlic: u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8> {
+                define_variant!(@gamma $gamma, self, garlic, state, gamma, k)
+            }
+
+            fn uses_gamma(&self) -> bool {
+                define_variant!(@uses_gamma $gamma)
+            }
+
+            #[allow(unused_variables)]
+            fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+            -> Result<Vec<u8>, $crate::catena::CatenaError> {
+                define_variant!(@f $f, self, garlic, state, lambda, n, k)
+            }
+
+            fn graph_kind(&self) -> $crate::components::graph::GraphKind {
+                define_variant!(@graph_kind $f)
+            }
+
+            #[allow(unused_variables)]
+            fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+                define_variant!(@phi $phi, self, garlic, state, mu, k)
+            }
+
+            fn phi_is_data_dependent(&self) -> bool {
+                define_variant!(@phi_is_data_dependent $phi)
+            }
+        }
+
+        #[doc = concat!("Constructor for a Catena-", $vid, " instance.")]
+        pub fn new() -> $crate::catena::Catena<$name> {
+            $crate::catena::Catena::new($name, $vid, $n, $k, $g, $g, $lambda)
+        }
+    };
+
+    (@hash blake2b, $x:expr) => {
+        $crate::components::hash::blake2b::hash($x)
+    };
+    (@hash cf_argon2_gb, $x:expr) => {
+        $crate::components::fasthash::cf_argon2::cf_argon2_gb($x)
+    };
+    (@hash cf_argon2_gl, $x:expr) => {
+        $crate::components::fasthash::cf_argon2::cf_argon2_gl($x)
+    };
+    (@hash cf_argon2_gb_reduced, $x:expr) => {
+        $crate::components::fasthash::cf_argon2::cf_argon2_gb_reduced($x)
+    };
+
+    (@gamma saltmix, $s:expr, $garlic:expr, $state:expr, $gamma:expr, $k:expr) => {
+        $crate::components::gamma::saltmix::saltmix(
+            $s, $garlic, $state, $gamma, $k)
+    };
+    (@gamma identity, $s:expr, $garlic:expr, $state:expr, $gamma:expr, $k:expr) => {
+        $state
+    };
+
+    (@uses_gamma saltmix) => {
+        true
+    };
+    (@uses_gamma identity) => {
+        false
+    };
+
+    (@f brh, $s:expr, $garlic:expr, $state:expr, $lambda:expr, $n:expr, $k:expr) => {
+        $crate::components::graph::generic_graph::bit_reversal_hash(
+            $s, $garlic, $state, $lambda, $n, $k)
+    };
+    (@f gray, $s:expr, $garlic:expr, $state:expr, $lambda:expr, $n:expr, $k:expr) => {
+        $crate::components::graph::generic_graph::gray_bit_reversal_hash(
+            $s, $garlic, $state, $lambda, $n, $k, 3)
+    };
+    (@f double_butterfly, $s:expr, $garlic:expr, $state:expr, $lambda:expr, $n:expr, $k:expr) => {
+        $crate::components::graph::double_butterfly_graph::double_butterfly_hash(
+            $s, $garlic, $state.clone(), $lambda, $n, $k)
+    };
+
+    (@f sponge, $s:expr, $garlic:expr, $state:expr, $lambda:expr, $n:expr, $k:expr) => {
+        $crate::components::graph::sponge_graph::sponge_hash(
+            $garlic, $state, $lambda, $k)
+    };
+
+    (@graph_kind brh) => {
+        $crate::components::graph::GraphKind::BitReversal
+    };
+    (@graph_kind gray) => {
+        $crate::components::graph::GraphKind::GrayReversal { l: 3 }
+    };
+    (@graph_kind double_butterfly) => {
+        $crate::components::graph::GraphKind::DoubleButterfly
+    };
+
+    (@graph_kind sponge) => {
+        $crate::components::graph::GraphKind::Sponge
+    };
+
+    (@phi lsb, $s:expr, $garlic:expr, $state:expr, $mu:expr, $k:expr) => {
+        $crate::components::phi::lsb::phi_lsb($s, $garlic, $state, $mu, $k)
+    };
+    (@phi identity, $s:expr, $garlic:expr, $state:expr, $mu:expr, $k:expr) => {
+        $state
+    };
+
+    (@phi_is_data_dependent lsb) => {
+        true
+    };
+    (@phi_is_data_dependent identity) => {
+        false
+    };
+}
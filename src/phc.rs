@@ -0,0 +1,314 @@
+//! Hashes in the PHC string format.
+//!
+//! A PHC string of Catena is the header of `Catena::encode_header` with a
+//! leading `$`, followed by the salt and the hash, e.g.
+//! `$catena-dragonfly$g=21,l=2,n=64$<salt>$<hash>`. Salt and hash are
+//! encoded in base64 without padding, as required by the PHC string format.
+
+use catena::CatenaError;
+use catena::DynCatena;
+use cli::parse_garlic;
+use inputs::PasswordHash;
+use registry::Variant;
+
+const BASE64_ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The parameters of a Catena instance encoded in a PHC string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhcParams {
+    /// The predefined instance identified by the version ID.
+    pub variant: Variant,
+    /// Minimum garlic, `gl` or `g` if absent.
+    pub g_low: u8,
+    /// Maximum garlic, `g`.
+    pub g_high: u8,
+    /// Depth of the graph, `l`.
+    pub lambda: u8,
+    /// Output length of H in bytes, `n`.
+    pub n: usize,
+}
+
+/// A parsed PHC string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PhcString {
+    /// The parameters of the instance.
+    pub params: PhcParams,
+    /// The decoded salt.
+    pub salt: Vec<u8>,
+    /// The decoded hash.
+    pub hash: Vec<u8>,
+}
+
+/// Parse a header as written by `Catena::encode_header`, i.e.
+/// `catena-<vid>$g=<g_high>[,gl=<g_low>],l=<lambda>,n=<n>`.
+pub fn parse_header(header: &str) -> Result<PhcParams, CatenaError> {
+    let mut parts = header.splitn(2, '$');
+    let id = parts.next().unwrap_or("");
+    let params = parts.next().ok_or(CatenaError::InvalidPhc)?;
+
+    if !id.starts_with("catena-") {
+        return Err(CatenaError::InvalidPhc);
+    }
+    let variant = Variant::from_name(&id["catena-".len()..])
+        .ok_or(CatenaError::UnknownVariant)?;
+
+    let mut g_high: Option<u8> = None;
+    let mut g_low: Option<u8> = None;
+    let mut lambda: Option<u8> = None;
+    let mut n: Option<usize> = None;
+    for param in params.split(',') {
+        let mut key_value = param.splitn(2, '=');
+        let key = key_value.next().unwrap_or("");
+        let value = key_value.next().ok_or(CatenaError::InvalidPhc)?;
+        match key {
+            "g" if g_high.is_none() =>
+                g_high = Some(parse_garlic(value)?),
+            "gl" if g_low.is_none() =>
+                g_low = Some(parse_garlic(value)?),
+            "l" if lambda.is_none() =>
+                lambda = Some(value.parse()
+                              .map_err(|_| CatenaError::InvalidPhc)?),
+            "n" if n.is_none() =>
+                n = Some(value.parse()
+                         .map_err(|_| CatenaError::InvalidPhc)?),
+            _ => return Err(CatenaError::InvalidPhc),
+        }
+    }
+
+    let g_high = g_high.ok_or(CatenaError::InvalidPhc)?;
+    let g_low = g_low.unwrap_or(g_high);
+    if g_low > g_high {
+        return Err(CatenaError::InvalidGarlic);
+    }
+
+    Ok(PhcParams {
+        variant: variant,
+        g_low: g_low,
+        g_high: g_high,
+        lambda: lambda.ok_or(CatenaError::InvalidPhc)?,
+        n: n.ok_or(CatenaError::InvalidPhc)?,
+    })
+}
+
+/// Parse a PHC string `$<header>$<salt>$<hash>`.
+pub fn parse(phc: &str) -> Result<PhcString, CatenaError> {
+    if !phc.starts_with('$') {
+        return Err(CatenaError::InvalidPhc);
+    }
+    let mut parts = phc[1..].rsplitn(3, '$');
+    let hash = parts.next().ok_or(CatenaError::InvalidPhc)?;
+    let salt = parts.next().ok_or(CatenaError::InvalidPhc)?;
+    let header = parts.next().ok_or(CatenaError::InvalidPhc)?;
+
+    Ok(PhcString {
+        params: parse_header(header)?,
+        salt: base64_decode(salt)?,
+        hash: base64_decode(hash)?,
+    })
+}
+
+/// Encode a header of `Catena::encode_header`, a salt and a hash as a PHC
+/// string.
+pub fn encode(header: &[u8], salt: &[u8], hash: &[u8]) -> String {
+    format!("${}${}${}",
+            String::from_utf8_lossy(header),
+            base64_encode(salt),
+            base64_encode(hash))
+}
+
+/// Check a password against a PHC string.
+///
+/// The instance is taken from the registry by the version ID and configured
+/// with the garlic of the PHC string. Returns `Ok(false)` if the password
+/// does not match and an error if the PHC string cannot be parsed, the
+/// version ID is unknown or `l` and `n` differ from the instance. The PHC
+/// string does not contain associated data, so it is empty.
+pub fn verify_phc(phc: &str, pwd: &[u8], gamma: &[u8])
+    -> Result<bool, CatenaError> {
+    let parsed = parse(phc)?;
+    let mut instance = parsed.params.variant.instance();
+    instance.set_garlic(parsed.params.g_low, parsed.params.g_high);
+    if !instance.matches_header(phc[1..].as_bytes()) {
+        return Err(CatenaError::InvalidPhc);
+    }
+
+    instance.verify(pwd, &parsed.salt, &[], gamma, &PasswordHash(parsed.hash))
+}
+
+/// Create the instance described by a header of `Catena::encode_header`, e.g.
+/// `catena-dragonfly$g=21,l=2,n=64`.
+///
+/// The instance is taken from the registry by the version ID and configured
+/// with the garlic of the header. Returns an error if the header cannot be
+/// parsed, the version ID is unknown, the garlic is out of range or `l` and
+/// `n` differ from the instance, as they are fixed for each variant.
+pub fn from_phc_header(header: &str)
+    -> Result<Box<dyn DynCatena>, CatenaError> {
+    let params = parse_header(header)?;
+    let mut instance = params.variant.instance();
+    instance.set_garlic(params.g_low, params.g_high);
+
+    let own = instance.params();
+    if own.lambda != params.lambda || own.n != params.n {
+        return Err(CatenaError::InvalidPhc);
+    }
+    Ok(instance)
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let bits = (block[0] as u32) << 16
+            | (block[1] as u32) << 8
+            | block[2] as u32;
+        for i in 0..chunk.len() + 1 {
+            let index = (bits >> (18 - 6 * i)) & 0x3f;
+            encoded.push(BASE64_ALPHABET[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+fn base64_decode(s: &str) -> Result<Vec<u8>, CatenaError> {
+    let mut decoded = Vec::new();
+    for chunk in s.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return Err(CatenaError::InvalidPhc);
+        }
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|a| a == c)
+                .ok_or(CatenaError::InvalidPhc)?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        // the bits of the last character which are not part of a byte have
+        // to be zero, so every byte string has a single encoding
+        if bits & ((1 << (24 - 8 * (chunk.len() - 1))) - 1) != 0 {
+            return Err(CatenaError::InvalidPhc);
+        }
+        for i in 0..chunk.len() - 1 {
+            decoded.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dragonfly_phc(pwd: &[u8]) -> String {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 14;
+        dragonfly.g_high = 14;
+        let salt = b"salt".to_vec();
+        let hash = dragonfly.password_hash(
+            &pwd.to_vec(), &salt, &Vec::new(), &b"gamma".to_vec()).unwrap();
+        encode(&dragonfly.encode_header().unwrap(), &salt, hash.as_bytes())
+    }
+
+    #[test]
+    fn base64_test() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg");
+        assert_eq!(base64_encode(b"fo"), "Zm8");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg");
+        for len in 0..10 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            assert_eq!(base64_decode(&base64_encode(&bytes)), Ok(bytes));
+        }
+        assert_eq!(base64_decode("Z"), Err(CatenaError::InvalidPhc));
+        assert_eq!(base64_decode("Zm9v="), Err(CatenaError::InvalidPhc));
+        // "Zh" and "Zm9" encode "f" and "fo" with non-zero unused bits
+        assert_eq!(base64_decode("Zh"), Err(CatenaError::InvalidPhc));
+        assert_eq!(base64_decode("Zm9"), Err(CatenaError::InvalidPhc));
+    }
+
+    #[test]
+    fn parse_header_test() {
+        let params = parse_header("catena-dragonfly$g=21,gl=18,l=2,n=64")
+            .unwrap();
+        assert_eq!(params, PhcParams {
+            variant: Variant::Dragonfly,
+            g_low: 18,
+            g_high: 21,
+            lambda: 2,
+            n: 64,
+        });
+        assert_eq!(parse_header("catena-dragonfly$g=21,l=2"),
+                   Err(CatenaError::InvalidPhc));
+        assert_eq!(parse_header("catena-dragonfly$g=64,l=2,n=64"),
+                   Err(CatenaError::InvalidGarlic));
+    }
+
+    #[test]
+    fn from_phc_header_test() {
+        let mut instance = from_phc_header("catena-butterfly$g=9,l=4,n=64")
+            .unwrap();
+        assert_eq!(instance.vid(), "Butterfly");
+        assert_eq!(instance.garlic(), (9, 9));
+        assert!(instance.matches_header(b"catena-butterfly$g=9,l=4,n=64"));
+
+        let mut butterfly = ::default_instances::butterfly::new();
+        butterfly.g_low = 9;
+        butterfly.g_high = 9;
+        assert_eq!(
+            instance.hash(b"password", b"salt", b"data", 64, b"gamma"),
+            butterfly.hash(
+                &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(),
+                64, &b"gamma".to_vec()));
+
+        let instance = from_phc_header("catena-dragonfly$g=21,gl=18,l=2,n=64")
+            .unwrap();
+        assert_eq!(instance.garlic(), (18, 21));
+    }
+
+    #[test]
+    fn from_phc_header_error_test() {
+        assert_eq!(from_phc_header("catena-firefly$g=21,l=2,n=64").err(),
+                   Some(CatenaError::UnknownVariant));
+        assert_eq!(from_phc_header("catena-dragonfly$g=64,l=2,n=64").err(),
+                   Some(CatenaError::InvalidGarlic));
+        assert_eq!(from_phc_header("catena-dragonfly$g=21,gl=22,l=2,n=64")
+                       .err(),
+                   Some(CatenaError::InvalidGarlic));
+        assert_eq!(from_phc_header("catena-dragonfly$g=21,l=3,n=64").err(),
+                   Some(CatenaError::InvalidPhc));
+        assert_eq!(from_phc_header("catena-dragonfly$g=21,l=2,n=32").err(),
+                   Some(CatenaError::InvalidPhc));
+        assert_eq!(from_phc_header("dragonfly").err(),
+                   Some(CatenaError::InvalidPhc));
+    }
+
+    #[test]
+    fn verify_phc_test() {
+        let phc = dragonfly_phc(b"password");
+        assert!(phc.starts_with("$catena-dragonfly$g=14,l=2,n=64$c2FsdA$"));
+        assert_eq!(verify_phc(&phc, b"password", b"gamma"), Ok(true));
+    }
+
+    #[test]
+    fn verify_phc_wrong_password_test() {
+        let phc = dragonfly_phc(b"password");
+        assert_eq!(verify_phc(&phc, b"passw0rd", b"gamma"), Ok(false));
+    }
+
+    #[test]
+    fn verify_phc_unknown_variant_test() {
+        let phc = dragonfly_phc(b"password")
+            .replace("catena-dragonfly", "catena-firefly");
+        assert_eq!(verify_phc(&phc, b"password", b"gamma"),
+                   Err(CatenaError::UnknownVariant));
+    }
+
+    #[test]
+    fn verify_phc_mismatched_params_test() {
+        let phc = dragonfly_phc(b"password").replace("l=2", "l=3");
+        assert_eq!(verify_phc(&phc, b"password", b"gamma"),
+                   Err(CatenaError::InvalidPhc));
+    }
+}
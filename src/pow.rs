@@ -0,0 +1,850 @@
+//! The proof-of-work mode of Catena
+//!
+//! The server hashes a password and hides `p` bits of the salt or the whole
+//! password of `p` bits, and the client searches the `2^p` candidates. The
+//! hash of both is `Catena::proof_of_work_hash`, which uses the proof-of-work
+//! domain in the tweak. The client starts the search at a random candidate
+//! drawn from `rand::thread_rng` or from the generator passed to
+//! `Catena::proof_of_work_client_with_rng`. Only the client uses `rand` and
+//! is behind the default feature `rand`. A server which only checks solutions
+//! of the client needs nothing but `verify`.
+#[cfg(feature = "rand")]
+extern crate rand;
+
+#[cfg(feature = "rand")]
+use bytes::Bytes;
+use catena::Algorithms;
+use catena::Catena;
+use catena::CatenaError;
+#[cfg(feature = "rand")]
+use self::rand::Rng;
+
+#[cfg(feature = "rand")]
+use std::time::Instant;
+
+/// The mask which clears the `p` secret bits of the salt in the salt mode of
+/// the proof of work, see `Catena::proof_of_work_server`.
+///
+/// The mask has `p / 8 + 1` bytes and is applied to the last bytes of the
+/// salt. All bytes but the first are zero, the first clears the `p % 8` low
+/// bits, e.g. `[0x80]` for `p = 7`, `[0xff, 0x00]` for `p = 8` and
+/// `[0xfe, 0x00]` for `p = 9`.
+fn proof_of_work_salt_mask(p: usize) -> Vec<u8> {
+    let mut mask = vec![0u8; p / 8 + 1];
+    mask[0] = 0xff << (p % 8);
+    mask
+}
+
+/// Whether the optional deadline has passed.
+#[cfg(feature = "rand")]
+fn timed_out(deadline: Option<Instant>) -> bool {
+    deadline.map_or(false, |deadline| Instant::now() >= deadline)
+}
+
+/// Check a solution of the proof of work: whether Catena in the
+/// proof-of-work mode with `g_low` and `g_high` of the instance yields
+/// `expected_hash`. The hashes are compared in constant time. The errors of
+/// `Catena::proof_of_work_hash` are returned.
+pub fn verify<T: Algorithms>(
+    catena: &mut Catena<T>,
+    pwd: &Vec<u8>,
+    salt: &Vec<u8>,
+    associated_data: &Vec<u8>,
+    gamma: &Vec<u8>,
+    output_len: u16,
+    expected_hash: &[u8]
+) -> Result<bool, CatenaError> {
+    let hash = catena.proof_of_work_hash(
+        pwd, salt, associated_data, gamma, output_len)?;
+    Ok(::helpers::vectors::ct_eq(&hash, expected_hash))
+}
+
+impl<T: Algorithms> Catena<T> {
+    /// Check the parameters of the proof of work before any hash is computed.
+    ///
+    /// Returns `CatenaError::InvalidProofOfWork` naming the first parameter
+    /// which does not fit:
+    ///
+    /// - `"p"` if the number of secret bits is not between 1 and 63, as the
+    ///   client searches the `2^p` candidates with 64-bit integers,
+    /// - `"salt"` if the salt of `salt_len` bytes has less than `p` bits in
+    ///   salt mode,
+    /// - `"pwd"` if the password of `pwd_len` bytes has less than `p` bits in
+    ///   password mode,
+    /// - `"mode"` if `mode` is neither 0 nor 1.
+    ///
+    /// In password mode, the server still panics if the password is not
+    /// exactly `p` bits long without leading zero bits.
+    pub fn pow_validate(
+        &self,
+        p: usize,
+        salt_len: usize,
+        pwd_len: usize,
+        mode: u8
+    ) -> Result<(), CatenaError> {
+        let invalid = |parameter| Err(CatenaError::InvalidProofOfWork {
+            parameter: parameter,
+        });
+        if p == 0 || p >= 64 {
+            return invalid("p");
+        }
+        match mode {
+            0 if salt_len * 8 < p => invalid("salt"),
+            1 if pwd_len * 8 < p => invalid("pwd"),
+            0 | 1 => Ok(()),
+            _ => invalid("mode"),
+        }
+    }
+
+    /// Server side of Catena proof of work mode.
+    ///
+    /// # Inputs
+    ///
+    /// - pwd: the password to be hashed
+    /// - salt: the salt value
+    /// - associated_data: associated data of the user
+    /// - gamma: a public and password-independent input
+    /// - output_length: length of the final hash in bytes
+    /// - p: number of secret bits, 1 <= p <= 63
+    /// - mode:
+    ///     - 0: salt mode
+    ///     - 1: password mode
+    ///
+    /// In salt mode, the secret bits are the `p` least significant bits of
+    /// the salt read as a big-endian number: the last `p / 8` bytes and the
+    /// `p % 8` low bits of the byte before them. These bits are set to zero
+    /// in the returned salt. The salt must be at least `p` bits long.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pow_validate` rejects the parameters or
+    /// `proof_of_work_hash` returns an error.
+    ///
+    /// # Returns
+    ///
+    /// - password
+    /// - salt
+    /// - associated data
+    /// - gamma
+    /// - output length
+    /// - output hash
+    /// - p
+    /// - mode (0 = salt; 1 = password)
+
+    pub fn proof_of_work_server(
+        &mut self,
+        pwd: &Vec<u8>,
+        salt: &mut Vec<u8>,
+        associated_data: &Vec<u8>,
+        gamma: &Vec<u8>,
+        output_len: u16,
+        p: usize,
+        mode: u8
+    ) -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>, u16, Vec<u8>, usize, u8) {
+
+        self.pow_validate(p, salt.len(), pwd.len(), mode)
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let hash = self.proof_of_work_hash(
+            pwd, salt, associated_data, gamma, output_len)
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        if mode == 0 {
+            let mask = proof_of_work_salt_mask(p);
+
+            // the first byte of the mask only clears bits if p % 8 != 0, so
+            // a salt of exactly p / 8 bytes is masked completely
+            for (salt_byte, mask_byte) in
+                salt.iter_mut().rev().zip(mask.iter().rev()) {
+                *salt_byte &= *mask_byte;
+            }
+
+            (pwd.to_vec(),
+             salt.to_vec(),
+             associated_data.to_vec(),
+             gamma.to_vec(),
+             output_len,
+             hash,
+             p,
+             mode)
+        } else if mode == 1 {
+            let bin_len =
+                (format!("{:b}", pwd[0])).len() + ((pwd.len() -1 ) * 8);
+            if bin_len != p {
+                panic!("pwd is not p bit long");
+            }
+
+            let empty_pwd: Vec<u8> = Vec::new();
+            (empty_pwd, salt.to_vec(), associated_data.to_vec(), gamma.to_vec(), output_len, hash, p, mode)
+        } else {
+            panic!("Invalid mode for proof of work");
+        }
+    }
+
+    /// Client side computation of proof of work
+    ///
+    /// # Inputs
+    ///
+    /// - pwd: the password to be hashed
+    /// - salt: the salt value
+    /// - associated_data: associated data of the user
+    /// - gamma: a public and password-independent input
+    /// - output_length: length of the final hash in bytes
+    /// - hash: hash to check if the computed password or salt is correct
+    /// - p: number of secret bits, 1 <= p <= 63
+    /// - mode:
+    ///     - 0: salt mode
+    ///     - 1: password mode
+    ///
+    /// Each candidate is compared with `hash` in constant time, like in
+    /// `pow::verify`.
+
+    #[cfg(feature = "rand")]
+    pub fn proof_of_work_client(
+        &mut self,
+        pwd: Vec<u8>,
+        salt: Vec<u8>,
+        associated_data: Vec<u8>,
+        gamma: Vec<u8>,
+        output_len: u16,
+        hash: Vec<u8>,
+        p: usize,
+        mode: u8
+    ) -> Vec<u8> {
+        self.try_proof_of_work_client(
+            &mut rand::thread_rng(), pwd, salt, associated_data, gamma,
+            output_len, hash, p, mode, None)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Client side computation of proof of work which draws the candidate
+    /// the search starts at from `rng` instead of `rand::thread_rng`.
+    ///
+    /// The inputs are the same as for `proof_of_work_client`. A seeded
+    /// generator makes the order of the search reproducible. Returns
+    /// `CatenaError::ProofOfWorkNotFound` if no candidate matches.
+    #[cfg(feature = "rand")]
+    pub fn proof_of_work_client_with_rng<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        pwd: Vec<u8>,
+        salt: Vec<u8>,
+        associated_data: Vec<u8>,
+        gamma: Vec<u8>,
+        output_len: u16,
+        hash: Vec<u8>,
+        p: usize,
+        mode: u8
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.try_proof_of_work_client(
+            rng, pwd, salt, associated_data, gamma, output_len, hash, p, mode,
+            None)
+    }
+
+    /// Client side computation of proof of work which gives up at a deadline.
+    ///
+    /// The inputs are the same as for `proof_of_work_client`. Instead of
+    /// searching all `2^p` candidates, `CatenaError::ProofOfWorkTimeout` is
+    /// returned as soon as `deadline` has passed, and
+    /// `CatenaError::ProofOfWorkNotFound` if no candidate matches.
+    #[cfg(feature = "rand")]
+    pub fn proof_of_work_client_timed(
+        &mut self,
+        pwd: Vec<u8>,
+        salt: Vec<u8>,
+        associated_data: Vec<u8>,
+        gamma: Vec<u8>,
+        output_len: u16,
+        hash: Vec<u8>,
+        p: usize,
+        mode: u8,
+        deadline: Instant
+    ) -> Result<Vec<u8>, CatenaError> {
+        self.try_proof_of_work_client(
+            &mut rand::thread_rng(), pwd, salt, associated_data, gamma,
+            output_len, hash, p, mode, Some(deadline))
+    }
+
+    #[cfg(feature = "rand")]
+    fn try_proof_of_work_client<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        pwd: Vec<u8>,
+        salt: Vec<u8>,
+        associated_data: Vec<u8>,
+        gamma: Vec<u8>,
+        output_len: u16,
+        hash: Vec<u8>,
+        p: usize,
+        mode: u8,
+        deadline: Option<Instant>
+    ) -> Result<Vec<u8>, CatenaError> {
+
+        // in password mode the client searches passwords of p bits
+        self.pow_validate(p, salt.len(), (p + 7) / 8, mode)?;
+
+        let border: u64 = 1 << p;
+        let rand_num = rng.gen_range(0, (1 << p) - 1);
+
+        if mode == 0 {
+
+            for i in 0..border {
+                if timed_out(deadline) {
+                    return Err(CatenaError::ProofOfWorkTimeout);
+                }
+
+                let mut new_vec = ((i + rand_num) % (border)).to_be_vec();
+
+                while new_vec[0] == 0 && new_vec.len() > 1{
+                    let _ = new_vec.remove(0);
+                }
+
+                let len = salt.len();
+                let len_new_vec = new_vec.len();
+
+                let mut tmp_salt = salt.clone();
+
+                for (i, _) in new_vec.iter().enumerate() {
+
+                    let tmp = tmp_salt[len - (i + 1)];
+                    tmp_salt[len - (i + 1)] = new_vec[len_new_vec - (i + 1)] 
+                        | tmp;
+                }
+
+                let hash_to_test = self.proof_of_work_hash(
+                    &pwd, &tmp_salt, &associated_data, &gamma, output_len)?;
+
+                // every candidate is hashed and compared completely, only a
+                // match ends the search
+                if ::helpers::vectors::ct_eq(&hash_to_test, &hash) {
+                    return Ok(tmp_salt)
+                }
+            }
+
+            Err(CatenaError::ProofOfWorkNotFound)
+
+        } else if mode == 1 {
+
+            for i in 0..border+1 {
+                if timed_out(deadline) {
+                    return Err(CatenaError::ProofOfWorkTimeout);
+                }
+
+                let mut new_vec = ((i + rand_num) % (border)).to_be_vec();
+
+                while (new_vec[0] == 0) & (new_vec.len() > 1){
+                    let _ = new_vec.remove(0);
+                }
+
+                let hash_to_test = self.proof_of_work_hash(
+                    &new_vec, &salt, &associated_data, &gamma, output_len)?;
+
+                if ::helpers::vectors::ct_eq(&hash_to_test, &hash) {
+                    return Ok(new_vec);
+                }
+
+            }
+            Err(CatenaError::ProofOfWorkNotFound)
+        } else {
+            panic!("Invalid mode for proof of work");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::HexRepresentation;
+    use helpers::files::JSONTests;
+    #[cfg(feature = "rand")]
+    use std::time::Duration;
+    use super::*;
+
+    fn proof_of_work_server_test_from_json <T: Algorithms>(
+        mut catena: ::catena::Catena<T>, file: &str)
+    {
+        let json = ::helpers::files::open_json(file.to_string());
+        let unwrapped_json = json.as_ref().unwrap();
+        let numbers_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+
+        for i in 0..numbers_of_tests {
+            let ref inputs = unwrapped_json[i]["inputs"];
+
+            let pwd = inputs.parse_hex("pwd");
+            let mut salt = inputs.parse_hex("salt");
+            let ad = inputs.parse_hex("aData");
+            let gamma = inputs.parse_hex("gamma");
+            let out_len = inputs.parse_u16("outLen");
+            let p = inputs.parse_usize("p");
+            let mode = inputs.parse_u8("mode");
+
+            let (result_pwd,
+                 result_salt,
+                 result_ad,
+                 result_gamma,
+                 result_out_len,
+                 result_hash,
+                 result_p,
+                 result_mode) = catena.proof_of_work_server(
+                     &pwd,
+                     &mut salt,
+                     &ad,
+                     &gamma,
+                     out_len,
+                     p,
+                     mode);
+
+            let ref outputs = unwrapped_json[i]["outputs"];
+
+            let expected_pwd = outputs.parse_hex("pwd");
+            let expected_salt = outputs.parse_hex("salt");
+            let expected_ad = outputs.parse_hex("aData");
+            let expected_gamma = outputs.parse_hex("gamma");
+            let expected_out_len = outputs.parse_u16("outLen");
+            let expected_hash = outputs.parse_hex("outHash");
+            let expected_p = outputs.parse_usize("p");
+            let expected_mode = outputs.parse_u8("mode");
+
+            assert_eq!(result_pwd.to_hex_string(), expected_pwd.to_hex_string());
+            assert_eq!(result_salt.to_hex_string(), expected_salt.to_hex_string());
+            assert_eq!(result_ad.to_hex_string(), expected_ad.to_hex_string());
+            assert_eq!(result_gamma.to_hex_string(), expected_gamma.to_hex_string());
+            assert_eq!(result_out_len, expected_out_len);
+            assert_eq!(result_hash.to_hex_string(), expected_hash.to_hex_string());
+            assert_eq!(result_p, expected_p);
+            assert_eq!(result_mode, expected_mode);
+        }
+    }
+
+    #[test]
+    fn proof_of_work_server_salt_test_butterfly_reduced() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+            catena_bf.g_low = 9;
+            catena_bf.g_high = 9;
+        proof_of_work_server_test_from_json(
+            catena_bf,
+            "test/test_vectors/proofOfWorkServerSaltButterflyReduced.json");
+    }
+
+    #[test]
+    fn proof_of_work_server_pwd_test_butterfly_reduced() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+            catena_bf.g_low = 9;
+            catena_bf.g_high = 9;
+        proof_of_work_server_test_from_json(
+            catena_bf,
+            "test/test_vectors/proofOfWorkServerPwdButterflyReduced.json");
+    }
+
+    #[test]
+    #[should_panic]
+    fn proof_of_work_server_panic_test_1() {
+        let pwd: Vec<u8> = vec!(0, 0);
+        let mut salt: Vec<u8> = vec!(0, 0);
+        let ad: Vec<u8> = vec!(0, 0);
+        let gamma: Vec<u8> = vec!(0, 0);
+        let out_len: u16 = 64;
+        let p = 1;
+        let mode: u8 = 6;
+
+        let mut catena_bf = ::default_instances::butterfly::new();
+
+        let _result = catena_bf.proof_of_work_server(
+            &pwd,
+            &mut salt,
+            &ad,
+            &gamma,
+            out_len,
+            p,
+            mode);
+    }
+
+    #[test]
+    #[should_panic]
+    /// test for wrong password length panic
+    fn proof_of_work_server_panic_test_2() {
+        let pwd: Vec<u8> = vec!(0, 0);
+        let mut salt: Vec<u8> = vec!(0, 0);
+        let ad: Vec<u8> = vec!(0, 0);
+        let gamma: Vec<u8> = vec!(0, 0);
+        let out_len: u16 = 64;
+        let p = 1;
+        let mode: u8 = 1;
+
+        let mut catena_bf = ::default_instances::butterfly::new();
+
+        let _result = catena_bf.proof_of_work_server(
+            &pwd,
+            &mut salt,
+            &ad,
+            &gamma,
+            out_len,
+            p,
+            mode);
+    }
+
+    #[cfg(feature = "rand")]
+    fn proof_of_work_client_test_from_json <T: Algorithms>(
+        mut catena: ::catena::Catena<T>, file: &str)
+    {
+        let json = ::helpers::files::open_json(file.to_string());
+        let unwrapped_json = json.as_ref().unwrap();
+        let numbers_of_tests = unwrapped_json.clone().as_array().unwrap().len();
+
+        for i in 0..numbers_of_tests {
+            let ref inputs = unwrapped_json[i]["inputs"];
+
+            let pwd = inputs.parse_hex("pwd");
+            let salt = inputs.parse_hex("salt");
+            let ad = inputs.parse_hex("aData");
+            let gamma = inputs.parse_hex("gamma");
+            let out_len = inputs.parse_u16("outLen");
+            let hash = inputs.parse_hex("hash");
+            let p = inputs.parse_usize("p");
+            let mode = inputs.parse_u8("mode");
+
+            let result = catena.proof_of_work_client(
+                pwd,
+                salt,
+                ad,
+                gamma,
+                out_len,
+                hash,
+                p,
+                mode);
+
+            let ref outputs = unwrapped_json[i]["outputs"];
+
+            let expected = outputs.parse_hex("res");
+
+            assert_eq!(result.to_hex_string(), expected.to_hex_string());
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_salt_test_butterfly_reduced() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+            catena_bf.g_low = 9;
+            catena_bf.g_high = 9;
+        proof_of_work_client_test_from_json(
+            catena_bf,
+            "test/test_vectors/proofOfWorkClientSaltButterflyReduced.json");
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_pwd_test_butterfly_reduced() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+            catena_bf.g_low = 9;
+            catena_bf.g_high = 9;
+        proof_of_work_client_test_from_json(
+            catena_bf,
+            "test/test_vectors/proofOfWorkClientPwdButterflyReduced.json");
+    }
+
+    #[test]
+    #[should_panic]
+    /// test for invalid mode
+    #[cfg(feature = "rand")]
+    fn proof_of_work_client_panic_test_1() {
+        let pwd: Vec<u8> = vec!(0, 0);
+        let salt: Vec<u8> = vec!(0, 0);
+        let ad: Vec<u8> = vec!(0, 0);
+        let gamma: Vec<u8> = vec!(0, 0);
+        let out_len: u16 = 64;
+        let hash: Vec<u8> = vec!(0, 0);
+        let p = 1;
+        let mode: u8 = 3;
+
+        let mut catena_bf = ::default_instances::butterfly::new();
+
+        let _result = catena_bf.proof_of_work_client(
+            pwd,
+            salt,
+            ad,
+            gamma,
+            out_len,
+            hash,
+            p,
+            mode);
+    }
+
+    #[test]
+    #[should_panic]
+    /// test for salt not found panic
+    #[cfg(feature = "rand")]
+    fn proof_of_work_client_panic_test_2() {
+        let pwd: Vec<u8> = vec!(0, 0);
+        let salt: Vec<u8> = vec!(0, 0);
+        let ad: Vec<u8> = vec!(0, 0);
+        let gamma: Vec<u8> = vec!(0, 0);
+        let out_len: u16 = 64;
+        let hash: Vec<u8> = vec!(0, 0);
+        let p = 1;
+        let mode: u8 = 0;
+
+        let mut catena_bf = ::default_instances::butterfly::new();
+
+        let _result = catena_bf.proof_of_work_client(
+            pwd,
+            salt,
+            ad,
+            gamma,
+            out_len,
+            hash,
+            p,
+            mode);
+    }
+
+    #[test]
+    #[should_panic]
+    /// test for password not found panic
+    #[cfg(feature = "rand")]
+    fn proof_of_work_client_panic_test_3() {
+        let pwd: Vec<u8> = vec!(0, 0);
+        let salt: Vec<u8> = vec!(0, 0);
+        let ad: Vec<u8> = vec!(0, 0);
+        let gamma: Vec<u8> = vec!(0, 0);
+        let out_len: u16 = 64;
+        let hash: Vec<u8> = vec!(0, 0);
+        let p = 1;
+        let mode: u8 = 1;
+
+        let mut catena_bf = ::default_instances::butterfly::new();
+
+        let _result = catena_bf.proof_of_work_client(
+            pwd,
+            salt,
+            ad,
+            gamma,
+            out_len,
+            hash,
+            p,
+            mode);
+    }
+
+    #[test]
+    fn proof_of_work_salt_mask_test() {
+        assert_eq!(proof_of_work_salt_mask(0), vec![0xff]);
+        assert_eq!(proof_of_work_salt_mask(7), vec![0x80]);
+        assert_eq!(proof_of_work_salt_mask(8), vec![0xff, 0x00]);
+        assert_eq!(proof_of_work_salt_mask(9), vec![0xfe, 0x00]);
+        assert_eq!(proof_of_work_salt_mask(16), vec![0xff, 0x00, 0x00]);
+        assert_eq!(proof_of_work_salt_mask(63),
+                   vec![0x80, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(proof_of_work_salt_mask(64),
+                   vec![0xff, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_salt_round_trip_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        let pwd = b"password".to_vec();
+        let ad = b"data".to_vec();
+        let gamma = b"gamma".to_vec();
+        let original = vec![0xa5, 0xff, 0xff, 0xff];
+
+        for &(p, expected) in [
+            (7, [0xa5, 0xff, 0xff, 0x80]),
+            (8, [0xa5, 0xff, 0xff, 0x00]),
+            (9, [0xa5, 0xff, 0xfe, 0x00]),
+            (16, [0xa5, 0xff, 0x00, 0x00]),
+        ].iter() {
+            let mut salt = original.clone();
+            let (_, masked_salt, _, _, _, hash, _, _) = dragonfly
+                .proof_of_work_server(&pwd, &mut salt, &ad, &gamma, 64, p, 0);
+            assert_eq!(masked_salt, expected.to_vec(), "p = {}", p);
+
+            let recovered = dragonfly.proof_of_work_client(
+                pwd.clone(), masked_salt, ad.clone(), gamma.clone(), 64, hash,
+                p, 0);
+            assert_eq!(recovered, original, "p = {}", p);
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_pwd_round_trip_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        // 0x5a has 7 significant bits
+        let pwd = vec![0x5a];
+        let (empty_pwd, salt, ad, gamma, output_len, hash, p, mode) = dragonfly
+            .proof_of_work_server(&pwd, &mut b"salt".to_vec(),
+                                  &b"data".to_vec(), &b"gamma".to_vec(),
+                                  64, 7, 1);
+        assert!(empty_pwd.is_empty());
+
+        let recovered = dragonfly.proof_of_work_client(
+            empty_pwd, salt, ad, gamma, output_len, hash, p, mode);
+        assert_eq!(recovered, pwd);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_with_rng_test() {
+        use self::rand::SeedableRng;
+        use self::rand::StdRng;
+
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        let pwd = vec![0x5a];
+        let (empty_pwd, salt, ad, gamma, output_len, hash, p, mode) = dragonfly
+            .proof_of_work_server(&pwd, &mut b"salt".to_vec(),
+                                  &b"data".to_vec(), &b"gamma".to_vec(),
+                                  64, 7, 1);
+
+        let seed: &[usize] = &[1, 2, 3, 4];
+        let mut rng = StdRng::from_seed(seed);
+        assert_eq!(dragonfly.proof_of_work_client_with_rng(
+            &mut rng, empty_pwd.clone(), salt.clone(), ad.clone(),
+            gamma.clone(), output_len, hash, p, mode), Ok(pwd));
+
+        assert_eq!(dragonfly.proof_of_work_client_with_rng(
+            &mut rng, empty_pwd, salt, ad, gamma, output_len, vec![0u8; 64],
+            p, mode), Err(CatenaError::ProofOfWorkNotFound));
+    }
+
+    #[test]
+    fn proof_of_work_salt_exact_length_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        let mut salt = vec![0xff, 0xff];
+        let (_, masked_salt, _, _, _, _, _, _) = dragonfly.proof_of_work_server(
+            &b"password".to_vec(), &mut salt, &b"data".to_vec(),
+            &b"gamma".to_vec(), 64, 16, 0);
+        assert_eq!(masked_salt, vec![0x00, 0x00]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_timed_timeout_test() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+        catena_bf.g_low = 9;
+        catena_bf.g_high = 9;
+
+        let start = Instant::now();
+        let result = catena_bf.proof_of_work_client_timed(
+            vec!(0, 0),
+            vec!(0, 0),
+            vec!(0, 0),
+            vec!(0, 0),
+            64,
+            vec!(0, 0),
+            16,
+            0,
+            start);
+
+        assert_eq!(result, Err(CatenaError::ProofOfWorkTimeout));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_timed_test() {
+        let mut catena_bf = ::default_instances::butterfly::new();
+        catena_bf.g_low = 9;
+        catena_bf.g_high = 9;
+
+        let pwd = b"password".to_vec();
+        let ad = b"data".to_vec();
+        let gamma = b"gamma".to_vec();
+        let mut salt = b"salt".to_vec();
+        let (_, masked_salt, _, _, _, hash, _, _) = catena_bf
+            .proof_of_work_server(&pwd, &mut salt, &ad, &gamma, 64, 4, 0);
+
+        let result = catena_bf.proof_of_work_client_timed(
+            pwd, masked_salt, ad, gamma, 64, hash, 4, 0,
+            Instant::now() + Duration::from_secs(60));
+        assert_eq!(result, Ok(b"salt".to_vec()));
+    }
+
+    #[test]
+    fn pow_validate_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        assert_eq!(dragonfly.pow_validate(16, 2, 0, 0), Ok(()));
+        assert_eq!(dragonfly.pow_validate(16, 0, 2, 1), Ok(()));
+        assert_eq!(dragonfly.pow_validate(1, 16, 16, 0), Ok(()));
+        assert_eq!(dragonfly.pow_validate(63, 16, 16, 1), Ok(()));
+        assert_eq!(dragonfly.pow_validate(0, 16, 16, 0),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "p" }));
+        assert_eq!(dragonfly.pow_validate(64, 16, 16, 0),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "p" }));
+        assert_eq!(dragonfly.pow_validate(65, 16, 16, 0),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "p" }));
+        assert_eq!(dragonfly.pow_validate(17, 2, 16, 0),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "salt" }));
+        assert_eq!(dragonfly.pow_validate(17, 16, 2, 1),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "pwd" }));
+        assert_eq!(dragonfly.pow_validate(1, 16, 16, 2),
+                   Err(CatenaError::InvalidProofOfWork { parameter: "mode" }));
+        assert_eq!(format!("{}", dragonfly.pow_validate(1, 16, 16, 2)
+                                     .unwrap_err()),
+                   "proof of work parameter \"mode\" is invalid");
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn proof_of_work_client_validate_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        // the client checks before the first hash
+        assert_eq!(dragonfly.proof_of_work_client_timed(
+            Vec::new(), vec![0], b"data".to_vec(), b"gamma".to_vec(), 64,
+            vec![0; 64], 9, 0, Instant::now()),
+            Err(CatenaError::InvalidProofOfWork { parameter: "salt" }));
+    }
+
+    #[test]
+    #[should_panic(expected = "proof of work parameter \"salt\" is invalid")]
+    fn proof_of_work_server_short_salt_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        let _ = dragonfly.proof_of_work_server(
+            &b"password".to_vec(), &mut vec![0xff], &b"data".to_vec(),
+            &b"gamma".to_vec(), 64, 9, 0);
+    }
+
+    #[test]
+    fn verify_test() {
+        let mut dragonfly = ::default_instances::dragonfly::new();
+        dragonfly.g_low = 3;
+        dragonfly.g_high = 3;
+
+        let pwd = b"password".to_vec();
+        let ad = b"data".to_vec();
+        let gamma = b"gamma".to_vec();
+        let original = b"salt".to_vec();
+        let mut salt = original.clone();
+        let (_, masked_salt, _, _, _, hash, _, _) = dragonfly
+            .proof_of_work_server(&pwd, &mut salt, &ad, &gamma, 64, 8, 0);
+
+        assert_eq!(verify(&mut dragonfly, &pwd, &original, &ad, &gamma, 64,
+                          &hash), Ok(true));
+        assert_eq!(verify(&mut dragonfly, &pwd, &masked_salt, &ad, &gamma, 64,
+                          &hash), Ok(false));
+        assert_eq!(verify(&mut dragonfly, &pwd, &original, &ad, &gamma, 32,
+                          &hash), Ok(false));
+        assert_eq!(verify(&mut dragonfly, &pwd, &original, &ad, &gamma, 65,
+                          &hash),
+                   Err(CatenaError::OutputTooLong { requested: 65, max: 64 }));
+
+        // the hash of the proof of work is separated from `hash`
+        assert!(dragonfly.hash(&pwd, &original, &ad, 64, &gamma)
+                .unwrap().0 != hash);
+    }
+}
@@ -0,0 +1,367 @@
+//! Registry of the predefined Catena instances.
+
+use catena::DynCatena;
+
+use std::time::Duration;
+use std::time::Instant;
+
+/// The garlic at which `recommend` times the variants.
+const RECOMMEND_GARLIC: u8 = 10;
+
+/// The largest garlic `recommend` considers.
+const RECOMMEND_MAX_GARLIC: u8 = 40;
+
+/// The default instances and variants of Catena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// `catena::default_instances::dragonfly`
+    Dragonfly,
+    /// `catena::default_instances::dragonfly_full`
+    DragonflyFull,
+    /// `catena::default_instances::butterfly`
+    Butterfly,
+    /// `catena::default_instances::butterfly_full`
+    ButterflyFull,
+    /// `catena::variants::stonefly`
+    Stonefly,
+    /// `catena::variants::stonefly_full`
+    StoneflyFull,
+    /// `catena::variants::horsefly`
+    Horsefly,
+    /// `catena::variants::horsefly_full`
+    HorseflyFull,
+    /// `catena::variants::mydasfly`
+    Mydasfly,
+    /// `catena::variants::mydasfly_full`
+    MydasflyFull,
+    /// `catena::variants::lanternfly`
+    Lanternfly,
+    /// `catena::variants::lanternfly_full`
+    LanternflyFull,
+    /// `catena::variants::dragonfly_cta`
+    DragonflyCta,
+}
+
+const ALL: [Variant; 13] = [
+    Variant::Dragonfly,
+    Variant::DragonflyFull,
+    Variant::Butterfly,
+    Variant::ButterflyFull,
+    Variant::Stonefly,
+    Variant::StoneflyFull,
+    Variant::Horsefly,
+    Variant::HorseflyFull,
+    Variant::Mydasfly,
+    Variant::MydasflyFull,
+    Variant::Lanternfly,
+    Variant::LanternflyFull,
+    Variant::DragonflyCta,
+];
+
+impl Variant {
+    /// All default instances and variants.
+    ///
+    /// These are the instances built from the components of the Catena
+    /// specification. The experimental variants Spongillafly and
+    /// Lanternfly-Rows, whose F or H' is not part of it, are left out, so
+    /// `recommend` never picks them and no PHC string refers to them.
+    pub fn all() -> &'static [Variant] {
+        &ALL
+    }
+
+    /// Find a variant by its version ID, ignoring case.
+    pub fn from_name(name: &str) -> Option<Variant> {
+        Variant::all().iter()
+            .find(|variant| variant.vid().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// The version ID of the variant.
+    pub fn vid(&self) -> &'static str {
+        match *self {
+            Variant::Dragonfly => "Dragonfly",
+            Variant::DragonflyFull => "Dragonfly-Full",
+            Variant::Butterfly => "Butterfly",
+            Variant::ButterflyFull => "Butterfly-Full",
+            Variant::Stonefly => "Stonefly",
+            Variant::StoneflyFull => "Stonefly-Full",
+            Variant::Horsefly => "Horsefly",
+            Variant::HorseflyFull => "Horsefly-Full",
+            Variant::Mydasfly => "Mydasfly",
+            Variant::MydasflyFull => "Mydasfly-Full",
+            Variant::Lanternfly => "Lanternfly",
+            Variant::LanternflyFull => "Lanternfly-Full",
+            Variant::DragonflyCta => "Dragonfly-CTA",
+        }
+    }
+
+    /// A new instance of the variant with its default parameters.
+    pub fn instance(&self) -> Box<dyn DynCatena> {
+        match *self {
+            Variant::Dragonfly =>
+                Box::new(::default_instances::dragonfly::new()),
+            Variant::DragonflyFull =>
+                Box::new(::default_instances::dragonfly_full::new()),
+            Variant::Butterfly =>
+                Box::new(::default_instances::butterfly::new()),
+            Variant::ButterflyFull =>
+                Box::new(::default_instances::butterfly_full::new()),
+            Variant::Stonefly =>
+                Box::new(::variants::stonefly::new()),
+            Variant::StoneflyFull =>
+                Box::new(::variants::stonefly_full::new()),
+            Variant::Horsefly =>
+                Box::new(::variants::horsefly::new()),
+            Variant::HorseflyFull =>
+                Box::new(::variants::horsefly_full::new()),
+            Variant::Mydasfly =>
+                Box::new(::variants::mydasfly::new()),
+            Variant::MydasflyFull =>
+                Box::new(::variants::mydasfly_full::new()),
+            Variant::Lanternfly =>
+                Box::new(::variants::lanternfly::new()),
+            Variant::LanternflyFull =>
+                Box::new(::variants::lanternfly_full::new()),
+            Variant::DragonflyCta =>
+                Box::new(::variants::dragonfly_cta::new()),
+        }
+    }
+}
+
+/// Recommend a variant and a garlic for hashing passwords in about
+/// `target_time` with at most `memory_budget` bytes on this machine.
+///
+/// Every variant of `Variant::all()` hashes once with garlic 10. The time is
+/// extrapolated assuming it doubles with each garlic, which holds as the
+/// `2^g` calls of H' dominate. For each variant the largest garlic is chosen
+/// whose estimated time is at most `target_time` and which needs at most
+/// `memory_budget` bytes, see `Catena::memory_bytes`. Of these, the variant
+/// using the most memory is returned, as the memory is what makes an attack
+/// expensive.
+///
+/// Returns `None` if no variant fits with garlic 1, e.g. for a target time
+/// below the time of a single hash or a budget smaller than the state of any
+/// variant. Any pair returned then would exceed the budget, so the caller
+/// has to decide whether to raise it instead of receiving parameters which
+/// silently ignore it.
+///
+/// This takes a few hundred milliseconds and the estimate depends on the
+/// load of the machine, so it is meant for choosing the parameters once,
+/// not on every start.
+pub fn recommend(target_time: Duration, memory_budget: usize)
+    -> Option<(Variant, u8)> {
+    let mut best: Option<(Variant, u8, usize)> = None;
+
+    for variant in Variant::all() {
+        let mut instance = variant.instance();
+        instance.set_garlic(RECOMMEND_GARLIC, RECOMMEND_GARLIC);
+        let params = instance.params();
+
+        let start = Instant::now();
+        if instance.hash(b"password", b"salt", b"", params.n as u16, b"gamma")
+            .is_err() {
+            continue;
+        }
+        let elapsed = start.elapsed();
+
+        let memory_bytes = |garlic| instance.memory_bytes(garlic);
+        if let Some(garlic) = fit_garlic(elapsed, &memory_bytes, target_time,
+                                         memory_budget) {
+            let memory = memory_bytes(garlic);
+            if best.map_or(true, |(_, _, most)| memory > most) {
+                best = Some((*variant, garlic, memory));
+            }
+        }
+    }
+    best.map(|(variant, garlic, _)| (variant, garlic))
+}
+
+/// The largest garlic for which the time `measured` at `RECOMMEND_GARLIC`,
+/// doubled for each garlic, is at most `target_time` and the memory given by
+/// `memory_bytes` is at most `memory_budget`.
+fn fit_garlic(
+    measured: Duration,
+    memory_bytes: &dyn Fn(u8) -> usize,
+    target_time: Duration,
+    memory_budget: usize
+) -> Option<u8> {
+    let measured = seconds(measured);
+    let target = seconds(target_time);
+
+    (1..RECOMMEND_MAX_GARLIC + 1).rev().find(|&garlic| {
+        let time = measured
+            * 2f64.powi(garlic as i32 - RECOMMEND_GARLIC as i32);
+        time <= target && memory_bytes(garlic) <= memory_budget
+    })
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::HexRepresentation;
+
+    #[test]
+    fn from_name_test() {
+        for variant in Variant::all() {
+            assert_eq!(Variant::from_name(variant.vid()), Some(*variant));
+            assert_eq!(variant.instance().vid(), variant.vid());
+        }
+        assert_eq!(Variant::from_name("dragonfly-full"),
+                   Some(Variant::DragonflyFull));
+        assert_eq!(Variant::from_name("Firefly"), None);
+    }
+
+    /// The expected output of a snapshot, see `test/snapshots/variants.txt`.
+    enum Snapshot {
+        /// The first vector of a file of `test/test_vectors`
+        Vectors(&'static str),
+        /// The hex output for the fixed inputs of `known_answers`
+        Output(&'static str),
+    }
+
+    /// The lines of `test/snapshots/variants.txt` as version ID, garlic and
+    /// snapshot.
+    fn snapshots() -> Vec<(&'static str, u8, Snapshot)> {
+        include_str!("../test/snapshots/variants.txt").lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next(), fields.next(),
+                       fields.next()) {
+                    (Some(vid), Some(garlic), Some(expected), None) => {
+                        let garlic = garlic.parse().unwrap_or_else(
+                            |_| panic!("invalid garlic in \"{}\"", line));
+                        let snapshot = if expected.ends_with(".json") {
+                            Snapshot::Vectors(expected)
+                        } else {
+                            Snapshot::Output(expected)
+                        };
+                        (vid, garlic, snapshot)
+                    },
+                    _ => panic!("invalid snapshot line \"{}\"", line),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let snapshots = snapshots();
+        let mut report = Vec::new();
+
+        for variant in Variant::all() {
+            let (garlic, snapshot) = match snapshots.iter()
+                .find(|&&(vid, _, _)| vid == variant.vid()) {
+                Some(&(_, garlic, ref snapshot)) => (garlic, snapshot),
+                None => {
+                    report.push(format!("{} has no snapshot",
+                                        variant.vid()));
+                    continue;
+                },
+            };
+
+            let mut instance = variant.instance();
+            instance.set_garlic(garlic, garlic);
+            let (output, expected) = match *snapshot {
+                Snapshot::Vectors(file) => {
+                    let path = format!("test/test_vectors/{}", file);
+                    let vectors = ::conformance::load_vectors(&path)
+                        .unwrap_or_else(|e| panic!("{}: {}", path, e));
+                    let vector = &vectors[0];
+                    let output = instance.hash(
+                        &vector.pwd, &vector.salt, &vector.associated_data,
+                        vector.output_length, &vector.gamma).unwrap();
+                    (output.to_hex_string(), vector.res.to_hex_string())
+                },
+                Snapshot::Output(expected) => {
+                    let output = instance
+                        .hash(b"password", b"salt", b"data", 64, b"gamma")
+                        .unwrap();
+                    (output.to_hex_string(), expected.to_string())
+                },
+            };
+            if output != expected {
+                report.push(format!(
+                    "{} changed\n  snapshot: {}\n  output:   {}",
+                    variant.vid(), expected, output));
+            }
+        }
+        for &(vid, garlic, ref snapshot) in &snapshots {
+            if Variant::from_name(vid).is_none() {
+                report.push(format!("{} is not a variant", vid));
+            }
+            if let Snapshot::Output(expected) = *snapshot {
+                let known_answer = ::catena::known_answers().into_iter()
+                    .find(|&(known, _)| known == vid)
+                    .map(|(_, output)| output.to_hex_string());
+                if garlic != 8 || known_answer.as_ref().map(|s| &s[..])
+                    != Some(expected) {
+                    report.push(format!("{} differs from known_answers", vid));
+                }
+            }
+        }
+
+        assert!(report.is_empty(), "snapshots of test/snapshots/variants.txt \
+                do not match:\n{}", report.join("\n"));
+    }
+
+    #[test]
+    fn fit_garlic_test() {
+        let millis = Duration::from_millis;
+        let dragonfly = Variant::Dragonfly.instance();
+        let memory = |garlic| dragonfly.memory_bytes(garlic);
+
+        // 1 ms at garlic 10 takes 2^8 ms at garlic 18
+        assert_eq!(fit_garlic(millis(1), &memory, millis(256),
+                              usize::max_value()),
+                   Some(18));
+        assert_eq!(fit_garlic(millis(1), &memory, millis(255),
+                              usize::max_value()),
+                   Some(17));
+        // 2 * 64 * 2^14 bytes
+        assert_eq!(fit_garlic(millis(1), &memory, millis(256), 1 << 21),
+                   Some(14));
+        assert_eq!(fit_garlic(millis(1), &memory, millis(256), 255), None);
+        assert_eq!(fit_garlic(millis(1024), &memory, millis(1), 1 << 30), None);
+        assert_eq!(fit_garlic(millis(0), &memory, millis(1),
+                              usize::max_value()),
+                   Some(RECOMMEND_MAX_GARLIC));
+    }
+
+    #[test]
+    fn recommend_test() {
+        let budget = 1 << 30;
+        let (variant, garlic) = recommend(Duration::from_secs(1), budget)
+            .unwrap();
+        assert!(Variant::all().contains(&variant));
+        assert!(garlic >= 1 && garlic <= RECOMMEND_MAX_GARLIC);
+
+        let mut instance = variant.instance();
+        instance.set_garlic(garlic, garlic);
+        assert!(instance.memory_bytes(garlic) <= budget);
+
+        // no state fits into 0 bytes
+        assert_eq!(recommend(Duration::from_secs(1), 0), None);
+    }
+
+    #[test]
+    fn instance_hash_test() {
+        let mut erased = Variant::Butterfly.instance();
+        erased.set_garlic(9, 9);
+        assert_eq!(erased.garlic(), (9, 9));
+
+        let mut butterfly = ::default_instances::butterfly::new();
+        butterfly.g_low = 9;
+        butterfly.g_high = 9;
+
+        assert_eq!(
+            erased.hash(b"password", b"salt", b"data", 64, b"gamma"),
+            butterfly.hash(
+                &b"password".to_vec(), &b"salt".to_vec(), &b"data".to_vec(),
+                64, &b"gamma".to_vec()));
+    }
+}
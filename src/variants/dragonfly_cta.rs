@@ -0,0 +1,125 @@
+//! An implementation of Catena-Dragonfly-CTA. This variant of Catena is
+//! identical to Catena-Dragonfly except for the Φ layer, which adds
+//! resistance to tradeoff attacks.
+//!
+//! Φ reads the state at indices derived from the state itself, so unlike
+//! Catena-Dragonfly the memory access pattern depends on the password and the
+//! variant does not resist cache-timing attacks, see
+//! `Catena::is_cta_resistant`.
+//!
+//! Enabling Φ changes the output, so hashes computed with this variant are not
+//! compatible with hashes computed with Catena-Dragonfly.
+
+/// The choices for H, H', F, Γ and Φ for Catena-Dragonfly-CTA.
+///
+/// These choices are:
+///
+/// - H: Blake2b
+/// - H': Blake2b-1
+/// - F: BRH(21,2)
+/// - Γ: SaltMix
+/// - Φ: Standard phi-layer with lsb index function
+#[derive(Clone, Copy, Debug)]
+pub struct DragonflyCtaAlgorithms {
+    blake2b_1: ::components::fasthash::blake2b1::Blake2b1,
+}
+
+impl ::catena::Algorithms for DragonflyCtaAlgorithms {
+    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
+        ::components::hash::blake2b::hash(x)
+    }
+
+    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
+        self.blake2b_1.hash(x)
+    }
+
+    fn reset_h_prime(&mut self) {
+        self.blake2b_1.reset();
+    }
+
+    fn gamma (&mut self, garlic:u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8> {
+        ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
+    }
+
+    fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
+    -> Result<Vec<u8>, ::catena::CatenaError> {
+        ::components::graph::generic_graph::bit_reversal_hash(
+            self, garlic, state, lambda, n, k)
+    }
+
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::BitReversal
+    }
+
+    fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
+        ::components::phi::lsb::phi_lsb(self, garlic, state, mu, k)
+    }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        true
+    }
+}
+
+/// Constructor for a Catena-Dragonfly-CTA instance.
+pub fn new() -> ::catena::Catena<DragonflyCtaAlgorithms> {
+    let dfc_algorithms = DragonflyCtaAlgorithms {
+        blake2b_1: Default::default(),
+    };
+    ::catena::Catena {
+        algorithms: dfc_algorithms,
+        vid: "Dragonfly-CTA",
+        n: 64,
+        k: 64,
+        g_low: 21,
+        g_high: 21,
+        lambda: 2,
+        options: ::catena::Options::default(),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::HexRepresentation;
+    use helpers::fixtures::test_inputs;
+
+    #[test]
+    fn dragonfly_cta_reduced_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 14;
+        test_catena.g_high = 14;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        // regression snapshot computed by this implementation, there is no
+        // reference vector for this variant
+        let expected = "6ed4e1af545938231a577d968d9da5cec7734ab2280ae5c8a57db6ab\
+                        5715262f5f64b895123b8b71d0f33504ed992d4700eafd70691e6527\
+                        a2381fb4464d39ef";
+
+        assert_eq!(
+            test_catena.hash(&pwd, &salt, &ad, 64, &gamma)
+                .unwrap().to_hex_string(),
+            expected);
+    }
+
+    #[test]
+    fn dragonfly_cta_differs_from_dragonfly_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 14;
+        test_catena.g_high = 14;
+        let mut catena_df = ::default_instances::dragonfly::new();
+        catena_df.g_low = 14;
+        catena_df.g_high = 14;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        assert_ne!(
+            test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(),
+            catena_df.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+    }
+    #[test]
+    fn dragonfly_cta_is_not_cta_resistant_test() {
+        assert!(!new().is_cta_resistant());
+    }
+}
@@ -27,12 +27,20 @@ impl ::catena::Algorithms for HorseflyFullAlgorithms {
         // ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
     }
 
+    fn uses_gamma(&self) -> bool {
+        false
+    }
+
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::generic_graph::bit_reversal_hash(
             self, garlic, state, lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::BitReversal
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -50,6 +58,7 @@ pub fn new() -> ::catena::Catena<HorseflyFullAlgorithms> {
         g_low: 23,
         g_high: 23,
         lambda: 1,
+        options: ::catena::Options::default(),
         }
 }
 
@@ -84,7 +93,7 @@ mod tests {
                     &salt,
                     &ad,
                     len,
-                    &gamma).to_hex_string(),
+                    &gamma).unwrap().to_hex_string(),
                 expected);
         }
     }
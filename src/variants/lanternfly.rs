@@ -28,11 +28,15 @@ impl ::catena::Algorithms for LanternflyAlgorithms {
     }
 
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::generic_graph::gray_bit_reversal_hash(
             self, garlic, state, lambda, n, k, 3)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::GrayReversal { l: 3 }
+    }
+
     #[allow(unused_variables)]
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         state
@@ -50,6 +54,7 @@ pub fn new() -> ::catena::Catena<LanternflyAlgorithms> {
         g_low: 17,
         g_high: 17,
         lambda: 2,
+        options: ::catena::Options::default(),
         }
 }
 
@@ -84,7 +89,7 @@ mod tests {
                     &salt,
                     &ad,
                     len,
-                    &gamma).to_hex_string(),
+                    &gamma).unwrap().to_hex_string(),
                 expected);
         }
     }
@@ -0,0 +1,80 @@
+//! An implementation of Catena-Lanternfly-Rows. This experimental variant
+//! equals Catena-Lanternfly, but H' is the Argon2 compression function
+//! reduced to the row pass, see
+//! `catena::components::fasthash::cf_argon2::cf_argon2_gb_reduced`.
+//!
+//! It is weaker than Lanternfly and only meant to measure the contribution of
+//! the column pass to the speed of Lanternfly. It is not part of the Catena
+//! specification, so it is not in the registry, see
+//! `catena::registry::Variant::all`.
+
+define_variant!(
+    /// The choices for H, H', F, Γ and Φ for Catena-Lanternfly-Rows.
+    ///
+    /// These choices are:
+    ///
+    /// - H: Blake2b
+    /// - H': Row pass of the Argon2 compression function with G = G_B
+    /// - F: GRH3(17,2)
+    /// - Γ: SaltMix
+    /// - Φ: Identity
+    LanternflyRowsAlgorithms,
+    vid = "Lanternfly-Rows", n = 64, k = 1024, g = 17, lambda = 2,
+    h = blake2b, h_prime = cf_argon2_gb_reduced, f = gray, gamma = saltmix,
+    phi = identity);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::HexRepresentation;
+    use helpers::fixtures::test_inputs;
+
+    #[test]
+    fn lanternfly_rows_reduced_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+
+        // regression snapshot computed by this implementation, there is no
+        // reference vector for this variant
+        let expected = concat!(
+            "eef7a252293e04a62e41b221471d5f6bcafd6890c931b2aa28b27f075bdcbc27",
+            "4455f4885d4d5e091aedbe6fa173ee804dcbf7d7abd567abfb8df5539bbc4689");
+        assert_eq!(
+            test_catena.hash(&b"password".to_vec(), &b"salt".to_vec(),
+                             &b"data".to_vec(), 64, &b"gamma".to_vec()).unwrap()
+                .to_hex_string(),
+            expected);
+    }
+
+    #[test]
+    fn lanternfly_rows_differs_from_lanternfly_test() {
+        let mut rows = new();
+        rows.g_low = 9;
+        rows.g_high = 9;
+        let mut lanternfly = ::variants::lanternfly::new();
+        lanternfly.g_low = 9;
+        lanternfly.g_high = 9;
+        lanternfly.vid = "Lanternfly-Rows";
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        // the instances only differ in the column pass of H'
+        assert_ne!(rows.hash(&pwd, &salt, &ad, 64, &gamma).unwrap(),
+                   lanternfly.hash(&pwd, &salt, &ad, 64, &gamma).unwrap());
+    }
+
+    #[test]
+    fn lanternfly_rows_server_relief_parity_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 5;
+        test_catena.g_high = 6;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let hash = test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let client = test_catena.client_prep(pwd, salt, &ad, 64, &gamma)
+            .unwrap();
+        assert_eq!(test_catena.server_final(client, 64), Ok(hash.0));
+    }
+}
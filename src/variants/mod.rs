@@ -8,3 +8,6 @@ pub mod stonefly_full;
 pub mod horsefly_full;
 pub mod mydasfly_full;
 pub mod lanternfly_full;
+pub mod dragonfly_cta;
+pub mod spongillafly;
+pub mod lanternfly_rows;
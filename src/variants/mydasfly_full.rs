@@ -28,15 +28,27 @@ impl ::catena::Algorithms for MydasflyFullAlgorithms {
         state.to_vec()
     }
 
+    fn uses_gamma(&self) -> bool {
+        false
+    }
+
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::double_butterfly_graph::double_butterfly_hash(
             self, &garlic, state.to_vec(), lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::DoubleButterfly
+    }
+
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         ::components::phi::lsb::phi_lsb(self, garlic, state, mu, k)
     }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        true
+    }
 }
 
 /// Constructor for a Catena-Mydasfly-Full instance.
@@ -50,6 +62,7 @@ pub fn new() -> ::catena::Catena<MydasflyFullAlgorithms> {
         g_low: 18,
         g_high: 18,
         lambda: 2,
+        options: ::catena::Options::default(),
         }
 }
 
@@ -84,7 +97,7 @@ mod tests {
                     &salt,
                     &ad,
                     len,
-                    &gamma).to_hex_string(),
+                    &gamma).unwrap().to_hex_string(),
                 expected);
         }
     }
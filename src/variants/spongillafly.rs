@@ -0,0 +1,68 @@
+//! An implementation of Catena-Spongillafly. This experimental variant of
+//! Catena replaces the graph-based hash function F by a sponge in the style of
+//! Lyra2, see `catena::components::graph::sponge_graph`.
+//!
+//! It is not part of the Catena specification and there are no reference
+//! vectors of other implementations. It is not in the registry, see
+//! `catena::registry::Variant::all`.
+
+define_variant!(
+    /// The choices for H, H', F, Γ and Φ for Catena-Spongillafly.
+    ///
+    /// These choices are:
+    ///
+    /// - H: Blake2b
+    /// - H': Argon2 compression function with G = G_B
+    /// - F: Duplex sponge with the Argon2 compression function, λ = 2
+    /// - Γ: SaltMix
+    /// - Φ: Standard phi-layer with lsb index function
+    SpongillaflyAlgorithms,
+    vid = "Spongillafly", n = 64, k = 1024, g = 18, lambda = 2,
+    h = blake2b, h_prime = cf_argon2_gb, f = sponge, gamma = saltmix,
+    phi = lsb);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::HexRepresentation;
+    use catena::Algorithms;
+    use helpers::fixtures::test_inputs;
+
+    #[test]
+    fn spongillafly_reduced_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 9;
+        test_catena.g_high = 9;
+
+        // regression snapshot computed by this implementation, there is no
+        // reference vector for this variant
+        let expected = concat!(
+            "ad918aca5aa34e207d118eb30e194fa93bb34788b108b820f4a30b6f21b545bb",
+            "9f4e61b28ce17b5d526c0e951c7024c565023d42174b76f2e6f36f68f80f2a2b");
+        assert_eq!(
+            test_catena.hash(&b"password".to_vec(), &b"salt".to_vec(),
+                             &b"data".to_vec(), 64, &b"gamma".to_vec()).unwrap()
+                .to_hex_string(),
+            expected);
+    }
+
+    #[test]
+    fn spongillafly_server_relief_parity_test() {
+        let mut test_catena = new();
+        test_catena.g_low = 5;
+        test_catena.g_high = 6;
+
+        let (pwd, salt, ad, gamma) = test_inputs();
+
+        let hash = test_catena.hash(&pwd, &salt, &ad, 64, &gamma).unwrap();
+        let client = test_catena.client_prep(pwd, salt, &ad, 64, &gamma)
+            .unwrap();
+        assert_eq!(test_catena.server_final(client, 64), Ok(hash.0));
+    }
+
+    #[test]
+    fn spongillafly_graph_kind_test() {
+        assert_eq!(new().algorithms.graph_kind(),
+                   ::components::graph::GraphKind::Sponge);
+    }
+}
@@ -1,55 +1,19 @@
 //! An implementation of Catena-Stonefly. This variant of Catena provides
 //! resistance to ASIC-based adversaries.
 
-/// The choices for H, H', F, Γ and Φ for Catena-Stonefly.
-///
-/// These choices are:
-///
-/// - H: Blake2b
-/// - H': Argon2 compression function with G = G_B
-/// - F: BRH(18,2)
-/// - Γ: SaltMix
-/// - Φ: Standard phi-layer with lsb index function
-#[derive(Clone, Copy, Debug)]
-pub struct StoneflyAlgorithms;
-
-impl ::catena::Algorithms for StoneflyAlgorithms {
-    fn h (&self, x: &Vec<u8>) -> Vec<u8> {
-        ::components::hash::blake2b::hash(x)
-    }
-
-    fn h_prime (&mut self, x: &Vec<u8>) -> Vec<u8> {
-        ::components::fasthash::cf_argon2::cf_argon2_gb(x)
-    }
-
-    fn gamma (&mut self, garlic:u8, state: Vec<u8>, gamma: &Vec<u8>, k: usize) -> Vec<u8> {
-        ::components::gamma::saltmix::saltmix(self, garlic, state, gamma, k)
-    }
-
-    fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
-        ::components::graph::generic_graph::bit_reversal_hash(
-            self, garlic, state, lambda, n, k)
-    }
-
-    fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
-        ::components::phi::lsb::phi_lsb(self, garlic, state, mu, k)
-    }
-}
-
-/// Constructor for a Catena-Stonefly instance.
-pub fn new() -> ::catena::Catena<StoneflyAlgorithms> {
-    let sf_algorithms = StoneflyAlgorithms;
-    ::catena::Catena {
-        algorithms: sf_algorithms,
-        vid: "Stonefly",
-        n: 64,
-        k: 1024,
-        g_low: 18,
-        g_high: 18,
-        lambda: 2,
-        }
-}
+define_variant!(
+    /// The choices for H, H', F, Γ and Φ for Catena-Stonefly.
+    ///
+    /// These choices are:
+    ///
+    /// - H: Blake2b
+    /// - H': Argon2 compression function with G = G_B
+    /// - F: BRH(18,2)
+    /// - Γ: SaltMix
+    /// - Φ: Standard phi-layer with lsb index function
+    StoneflyAlgorithms,
+    vid = "Stonefly", n = 64, k = 1024, g = 18, lambda = 2,
+    h = blake2b, h_prime = cf_argon2_gb, f = brh, gamma = saltmix, phi = lsb);
 
 #[cfg(test)]
 mod tests {
@@ -82,7 +46,7 @@ mod tests {
                     &salt,
                     &ad,
                     len,
-                    &gamma).to_hex_string(),
+                    &gamma).unwrap().to_hex_string(),
                 expected);
         }
     }
@@ -27,14 +27,22 @@ impl ::catena::Algorithms for StoneflyFullAlgorithms {
     }
 
     fn f (&mut self, garlic: &u8, state: &mut Vec<u8>, lambda: u8, n: usize, k: usize)
-    -> Vec<u8> {
+    -> Result<Vec<u8>, ::catena::CatenaError> {
         ::components::graph::generic_graph::bit_reversal_hash(
             self, garlic, state, lambda, n, k)
     }
 
+    fn graph_kind(&self) -> ::components::graph::GraphKind {
+        ::components::graph::GraphKind::BitReversal
+    }
+
     fn phi (&mut self, garlic: u8, state: Vec<u8>, mu: &Vec<u8>, k: usize) -> Vec<u8> {
         ::components::phi::lsb::phi_lsb(self, garlic, state, mu, k)
     }
+
+    fn phi_is_data_dependent(&self) -> bool {
+        true
+    }
 }
 
 /// Constructor for a Catena-Stonefly-Full instance.
@@ -48,6 +56,7 @@ pub fn new() -> ::catena::Catena<StoneflyFullAlgorithms> {
         g_low: 22,
         g_high: 22,
         lambda: 2,
+        options: ::catena::Options::default(),
         }
 }
 
@@ -82,7 +91,7 @@ mod tests {
                     &salt,
                     &ad,
                     len,
-                    &gamma).to_hex_string(),
+                    &gamma).unwrap().to_hex_string(),
                 expected);
         }
     }